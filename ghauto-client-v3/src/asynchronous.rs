@@ -0,0 +1,119 @@
+//! Futures based execution of the query builders, enabled with the `async`
+//! feature. The endpoint DSL is the same as for the blocking client, only
+//! the last step differs:
+//!
+//! ```ignore
+//! let issues = gh.get().repos().owner("o").repo("r").issues().execute_async::<Vec<Issue>>();
+//! let pulls = gh.get().repos().owner("o").repo("r").pulls().execute_async::<Vec<Pull>>();
+//! let (issues, pulls) = block_on(futures::future::join(issues, pulls))?;
+//! ```
+
+use hyper::{HeaderMap, StatusCode};
+use reqwest::blocking::Request;
+use serde::de::DeserializeOwned;
+
+use std::future::Future;
+use std::pin::Pin;
+
+use crate::client::{parse_response, Error, Result};
+
+/// The future returned by `execute_async`. It borrows the `Github` the
+/// query was built from and can be sent to another thread, e.g. spawned
+/// on a multi threaded runtime.
+pub type BoxFuture<'g, T> = Pin<Box<dyn Future<Output = T> + Send + 'g>>;
+
+/// Implemented by every endpoint that can be executed with `Executor`
+pub trait AsyncExecutor<'g> {
+    fn execute_async<T>(self) -> BoxFuture<'g, Result<(HeaderMap, StatusCode, Option<T>)>>
+    where
+        T: DeserializeOwned + Send + 'g;
+}
+
+/// Run a future to completion on a new single threaded tokio runtime.
+/// Meant to be called from synchronous code like a console command.
+pub fn block_on<F>(future: F) -> std::io::Result<F::Output>
+where
+    F: Future,
+{
+    let mut runtime = tokio::runtime::Builder::new()
+        .basic_scheduler()
+        .enable_all()
+        .build()?;
+    Ok(runtime.block_on(future))
+}
+
+/// Copy a request built by the DSL into one the async client can send
+pub(crate) fn to_async_request(req: &Request) -> Result<reqwest::Request> {
+    let mut copy = reqwest::Request::new(req.method().clone(), req.url().clone());
+    *copy.headers_mut() = req.headers().clone();
+    if let Some(body) = req.body() {
+        let bytes = body
+            .as_bytes()
+            .ok_or_else(|| Error::InvalidRequest("streaming bodies cannot be sent asynchronously".to_string()))?;
+        *copy.body_mut() = Some(bytes.to_vec().into());
+    }
+    Ok(copy)
+}
+
+pub(crate) async fn read_response<T>(res: reqwest::Response) -> Result<(HeaderMap, StatusCode, Option<T>)>
+where
+    T: DeserializeOwned,
+{
+    let headers = res.headers().clone();
+    let status = res.status();
+    let body = res.bytes().await?;
+    parse_response(headers, status, &body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::Github;
+    use crate::mock::{serve, Canned};
+    use futures_util::future::join_all;
+
+    #[test]
+    fn executes_queries_concurrently() {
+        let (url, requests) = serve(vec![Canned::json(200, "[1]"), Canned::json(200, "[2]")]);
+        let github = Github::with_api_url("token", &url).unwrap();
+
+        let queries = vec!["one", "two"].into_iter().map(|repo| {
+            github
+                .get()
+                .repos()
+                .owner("octocat")
+                .repo(repo)
+                .issues()
+                .execute_async::<Vec<u32>>()
+        });
+        let results = block_on(join_all(queries)).unwrap();
+
+        let mut items: Vec<u32> = results
+            .into_iter()
+            .flat_map(|r| r.unwrap().2.unwrap())
+            .collect();
+        items.sort();
+        assert_eq!(vec![1, 2], items);
+
+        let mut paths = vec![requests.recv().unwrap().path, requests.recv().unwrap().path];
+        paths.sort();
+        assert_eq!(vec!["/repos/octocat/one/issues", "/repos/octocat/two/issues"], paths);
+    }
+
+    #[test]
+    fn futures_are_send() {
+        fn is_send<T: Send>(_: &T) {}
+        let github = Github::new("token");
+        is_send(&github.get().repos().owner("octocat").repo("one").issues().execute_async::<Vec<u32>>());
+    }
+
+    #[test]
+    fn errors_are_typed() {
+        let (url, _) = serve(vec![Canned::json(404, r#"{"message": "Not Found"}"#)]);
+        let github = Github::with_api_url("token", &url).unwrap();
+
+        let query = github.get().repos().owner("octocat").repo("gone").execute_async::<serde_json::Value>();
+        let err = block_on(query).unwrap().unwrap_err();
+        assert!(err.is_not_found());
+    }
+}
@@ -0,0 +1,267 @@
+//! An on-disk cache for `GET` responses. Cached responses are revalidated
+//! with `If-None-Match`/`If-Modified-Since`, and GitHub answers unchanged
+//! resources with `304 Not Modified`, which does not count against the rate
+//! limit. The cached body is then returned as if it had been sent again.
+
+use hyper::header::{HeaderName, HeaderValue, ACCEPT, AUTHORIZATION, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+use hyper::{HeaderMap, StatusCode};
+use reqwest::Method;
+use sha2::{Digest, Sha256};
+
+use std::fs;
+use std::io;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// A response as stored on disk
+#[derive(Deserialize, Serialize, Debug)]
+pub(crate) struct Entry {
+    url: String,
+    etag: Option<String>,
+    last_modified: Option<String>,
+    headers: Vec<(String, String)>,
+    body: String,
+}
+
+impl Entry {
+    pub(crate) fn headers(&self) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (k, v) in self.headers.iter() {
+            if let (Ok(k), Ok(v)) = (HeaderName::from_bytes(k.as_bytes()), HeaderValue::from_str(v)) {
+                headers.append(k, v);
+            }
+        }
+        headers
+    }
+
+    /// The cached response, answered with `200 OK`
+    pub(crate) fn to_response(&self) -> http::Response<Vec<u8>> {
+        let mut res = http::Response::new(self.body.as_bytes().to_vec());
+        *res.status_mut() = StatusCode::OK;
+        *res.headers_mut() = self.headers();
+        res
+    }
+}
+
+/// Stores one file per request in `dir`. The file name is a hash of the
+/// url, the `Accept` header and the token, so responses are never shared
+/// between tokens and the token itself is not written to disk.
+#[derive(Clone, Debug)]
+pub struct ResponseCache {
+    dir: PathBuf,
+}
+
+impl ResponseCache {
+    pub fn new<P>(dir: P) -> Self
+    where
+        P: Into<PathBuf>,
+    {
+        Self { dir: dir.into() }
+    }
+
+    /// The directory the responses are stored in
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// The key of a request, `None` for requests that are not cached
+    pub(crate) fn key(method: &Method, url: &str, headers: &HeaderMap) -> Option<String> {
+        // requests that are already conditional are left to the caller
+        if method != Method::GET || headers.contains_key(IF_NONE_MATCH) || headers.contains_key(IF_MODIFIED_SINCE) {
+            return None;
+        }
+
+        let mut hasher = Sha256::new();
+        hasher.update(url.as_bytes());
+        for name in [ACCEPT, AUTHORIZATION].iter() {
+            hasher.update(b"\n");
+            if let Some(v) = headers.get(name) {
+                hasher.update(v.as_bytes());
+            }
+        }
+        Some(format!("{:x}", hasher.finalize()))
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", key))
+    }
+
+    /// Look up the cached response of a request and add the headers turning
+    /// it into a conditional request
+    pub(crate) fn revalidate(&self, key: &str, headers: &mut HeaderMap) -> Option<Entry> {
+        let entry: Entry = fs::read(self.path(key))
+            .ok()
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok())?;
+
+        let etag = entry.etag.as_ref().and_then(|e| HeaderValue::from_str(e).ok());
+        let last_modified = entry.last_modified.as_ref().and_then(|l| HeaderValue::from_str(l).ok());
+        match (etag, last_modified) {
+            (Some(etag), _) => {
+                headers.insert(IF_NONE_MATCH, etag);
+            }
+            (None, Some(last_modified)) => {
+                headers.insert(IF_MODIFIED_SINCE, last_modified);
+            }
+            (None, None) => return None,
+        }
+        Some(entry)
+    }
+
+    /// Store a successful response if it can be revalidated later. The
+    /// file is replaced atomically, so parallel readers never see a partial
+    /// entry.
+    pub(crate) fn store(&self, key: &str, url: &str, headers: &HeaderMap, body: &[u8]) -> io::Result<()> {
+        let header_str = |name| headers.get(name).and_then(|v: &HeaderValue| v.to_str().ok()).map(String::from);
+        let etag = header_str(ETAG);
+        let last_modified = header_str(LAST_MODIFIED);
+        if etag.is_none() && last_modified.is_none() {
+            return Ok(());
+        }
+        let body = match std::str::from_utf8(body) {
+            Ok(body) => body.to_string(),
+            Err(_) => return Ok(()),
+        };
+
+        let entry = Entry {
+            url: url.to_string(),
+            etag: etag,
+            last_modified: last_modified,
+            headers: headers
+                .iter()
+                .filter(|(k, _)| !k.as_str().starts_with("x-ratelimit"))
+                .filter_map(|(k, v)| v.to_str().ok().map(|v| (k.to_string(), v.to_string())))
+                .collect(),
+            body: body,
+        };
+        let json = serde_json::to_vec(&entry).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+        // the bodies may belong to private repositories
+        ghauto_config::file::create_private_dir(&self.dir)?;
+        let tmp = self.dir.join(format!("{}.{}.tmp", key, rand::random::<u64>()));
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        options.open(&tmp)?.write_all(&json)?;
+        fs::rename(&tmp, self.path(key))
+    }
+
+    /// Mark an entry as used, so pruning keeps it
+    pub(crate) fn touch(&self, key: &str, entry: &Entry) {
+        let headers = entry.headers();
+        let _ = self.store(key, &entry.url, &headers, entry.body.as_bytes());
+    }
+
+    /// Remove all entries not used within `max_age` and return how many
+    /// were removed. A `max_age` of zero empties the cache.
+    pub fn prune(&self, max_age: Duration) -> io::Result<usize> {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(0),
+            Err(e) => return Err(e),
+        };
+
+        let now = SystemTime::now();
+        let mut removed = 0;
+        for entry in entries {
+            let entry = entry?;
+            let path = entry.path();
+            let is_cache_file = path
+                .extension()
+                .map(|e| e == "json" || e == "tmp")
+                .unwrap_or(false);
+            if !is_cache_file {
+                continue;
+            }
+
+            let age = entry
+                .metadata()?
+                .modified()
+                .ok()
+                .and_then(|m| now.duration_since(m).ok())
+                .unwrap_or_default();
+            if age >= max_age {
+                fs::remove_file(path)?;
+                removed += 1;
+            }
+        }
+        Ok(removed)
+    }
+}
+
+#[cfg(test)]
+pub(crate) fn temp_cache() -> ResponseCache {
+    use rand::Rng;
+    let dir = std::env::temp_dir().join(format!("bardo-cache-{}", rand::thread_rng().gen::<u64>()));
+    ResponseCache::new(dir)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(pairs: &[(&'static str, &'static str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (k, v) in pairs {
+            headers.insert(*k, HeaderValue::from_static(v));
+        }
+        headers
+    }
+
+    #[test]
+    fn key_depends_on_token() {
+        let url = "https://api.github.com/repos/o/r";
+        let a = ResponseCache::key(&Method::GET, url, &headers(&[("authorization", "token a")])).unwrap();
+        let b = ResponseCache::key(&Method::GET, url, &headers(&[("authorization", "token b")])).unwrap();
+        assert_ne!(a, b);
+        assert!(!a.contains("token"));
+
+        assert!(ResponseCache::key(&Method::POST, url, &HeaderMap::new()).is_none());
+        assert!(ResponseCache::key(&Method::GET, url, &headers(&[("if-none-match", "\"x\"")])).is_none());
+    }
+
+    #[test]
+    fn stores_and_revalidates() {
+        let cache = temp_cache();
+        let response = headers(&[("etag", "\"abc\""), ("link", "<x>; rel=\"next\""), ("x-ratelimit-remaining", "1")]);
+        cache.store("key", "https://api.github.com/x", &response, b"[1]").unwrap();
+
+        let mut request = HeaderMap::new();
+        let entry = cache.revalidate("key", &mut request).unwrap();
+        assert_eq!("\"abc\"", request.get(IF_NONE_MATCH).unwrap());
+        assert_eq!(b"[1]".to_vec(), *entry.to_response().body());
+        assert!(entry.headers().contains_key("link"));
+        assert!(!entry.headers().contains_key("x-ratelimit-remaining"));
+
+        assert_eq!(0, cache.prune(Duration::from_secs(3600)).unwrap());
+        assert!(cache.revalidate("key", &mut HeaderMap::new()).is_some());
+        assert_eq!(1, cache.prune(Duration::from_secs(0)).unwrap());
+        assert!(cache.revalidate("key", &mut HeaderMap::new()).is_none());
+        let _ = fs::remove_dir_all(cache.dir());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn only_the_owner_can_read_entries() {
+        use std::os::unix::fs::PermissionsExt;
+        let cache = temp_cache();
+        cache.store("key", "https://api.github.com/x", &headers(&[("etag", "\"abc\"")]), b"[1]").unwrap();
+
+        let mode = |path: &Path| fs::metadata(path).unwrap().permissions().mode() & 0o777;
+        assert_eq!(0o700, mode(cache.dir()));
+        assert_eq!(0o600, mode(&cache.path("key")));
+        let _ = fs::remove_dir_all(cache.dir());
+    }
+
+    #[test]
+    fn skips_responses_without_validator() {
+        let cache = temp_cache();
+        cache.store("key", "https://api.github.com/x", &HeaderMap::new(), b"[1]").unwrap();
+        assert!(cache.revalidate("key", &mut HeaderMap::new()).is_none());
+        assert_eq!(0, cache.prune(Duration::from_secs(0)).unwrap());
+    }
+}
@@ -1,15 +1,23 @@
-use hyper::header::{HeaderName, HeaderValue};
+use hyper::header::{HeaderName, HeaderValue, AUTHORIZATION};
 use hyper::{HeaderMap, StatusCode};
 
-use reqwest::blocking::{Client, Request};
+use reqwest::blocking::{Client, Request, Response};
 use reqwest::{Url, Method};
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 
 use std::cell::RefCell;
-use std::rc::Rc;
+use std::sync::{Arc, RwLock};
+use std::thread;
 
-use crate::util::url_join;
+use crate::app::{AppAuth, InstallationToken};
+use crate::cache::ResponseCache;
+use crate::errors::{error_message, is_rate_limited};
+use crate::ratelimit::{RateLimit, RateLimiter};
+use crate::retry::RetryPolicy;
+use crate::scopes::ScopeCheck;
+
+use crate::util::{api_url_for_host, url_join};
 
 #[derive(Deserialize, Serialize, Clone, Debug)]
 struct User {
@@ -17,26 +25,33 @@ struct User {
     name: String,
 }
 
-// A simple type alias so as to DRY.
-pub type Result<T> = std::result::Result<T, Box<dyn std::error::Error + Send + Sync + 'static>>;
+pub use crate::errors::{Error, Result};
 
-pub struct Github {
-    token: String,
-    client: Rc<Client>,
-}
+/// The API root of github.com
+pub const GITHUB_API_URL: &str = "https://api.github.com";
 
-impl Clone for Github {
-    fn clone(&self) -> Self {
-        Self {
-            token: self.token.clone(),
-            client: Rc::clone(&self.client),
-        }
-    }
+/// A handle to the GitHub API. It is `Send + Sync` and cheap to clone; all
+/// clones share the HTTP client, the token and the rate limit budget, so a
+/// single handle can be used from several worker threads at once.
+#[derive(Clone)]
+pub struct Github {
+    token: Arc<RwLock<String>>,
+    api_url: Url,
+    client: Arc<Client>,
+    #[cfg(feature = "async")]
+    async_client: reqwest::Client,
+    rate_limiter: Arc<RateLimiter>,
+    retry_policy: RetryPolicy,
+    cache: Option<ResponseCache>,
+    app: Option<Arc<AppAuth>>,
+    scope_check: Arc<ScopeCheck>,
 }
 
 new_type!(GetQueryBuilder);
 new_type!(PostQueryBuilder);
 new_type!(PutQueryBuilder);
+new_type!(PatchQueryBuilder);
+new_type!(DeleteQueryBuilder);
 new_type!(CustomQuery);
 exec!(CustomQuery);
 
@@ -46,6 +61,80 @@ pub trait Executor {
         T: DeserializeOwned;
 }
 
+/// Turn a response into headers, status and the deserialized body. Error
+/// statuses become an `Error`, an empty body (e.g. `204 No Content`) is `None`.
+pub(crate) fn read_response<T>(res: Response) -> Result<(HeaderMap, StatusCode, Option<T>)>
+where
+    T: DeserializeOwned,
+{
+    let headers = res.headers().clone();
+    let status: StatusCode = res.status();
+    let body = res.bytes()?;
+    parse_response(headers, status, &body)
+}
+
+/// Turn a response into the executor result: an error for any non success
+/// status, `None` for an empty body, the deserialized body otherwise
+pub(crate) fn parse_response<T>(headers: HeaderMap, status: StatusCode, body: &[u8]) -> Result<(HeaderMap, StatusCode, Option<T>)>
+where
+    T: DeserializeOwned,
+{
+    if !status.is_success() && status != StatusCode::NOT_MODIFIED {
+        return Err(Error::from_response(status, &headers, body));
+    }
+
+    if body.iter().all(|b| b.is_ascii_whitespace()) {
+        return Ok((headers, status, None));
+    }
+
+    match serde_json::from_slice(body) {
+        Ok(d) => Ok((headers, status, Some(d))),
+        Err(e) => Err(Error::Deserialize {
+            status: status,
+            error: e,
+        }),
+    }
+}
+
+/// Whether GitHub rejected the request because of a rate limit. A
+/// secondary limit may only be named in the message, so the body of a
+/// `403` is read and the response rebuilt from it.
+fn rate_limited(res: Response) -> Result<(Response, bool)> {
+    if res.status() != StatusCode::FORBIDDEN {
+        let rate_limited = is_rate_limited(res.status(), res.headers(), "");
+        return Ok((res, rate_limited));
+    }
+
+    let status = res.status();
+    let headers = res.headers().clone();
+    let body = res.bytes()?;
+    let rate_limited = is_rate_limited(status, &headers, &error_message(&body));
+
+    let mut copy = http::Response::new(body.to_vec());
+    *copy.status_mut() = status;
+    *copy.headers_mut() = headers;
+    Ok((Response::from(copy), rate_limited))
+}
+
+/// The asynchronous counterpart of `rate_limited`
+#[cfg(feature = "async")]
+async fn rate_limited_async(res: reqwest::Response) -> Result<(reqwest::Response, bool)> {
+    if res.status() != StatusCode::FORBIDDEN {
+        let rate_limited = is_rate_limited(res.status(), res.headers(), "");
+        return Ok((res, rate_limited));
+    }
+
+    let status = res.status();
+    let headers = res.headers().clone();
+    let body = res.bytes().await?;
+    let rate_limited = is_rate_limited(status, &headers, &error_message(&body));
+
+    let mut copy = http::Response::new(body.to_vec());
+    *copy.status_mut() = status;
+    *copy.headers_mut() = headers;
+    Ok((reqwest::Response::from(copy), rate_limited))
+}
+
 impl Github {
     pub fn new<T>(token: T) -> Self
     where
@@ -53,23 +142,319 @@ impl Github {
     {
         let client = Client::new();
         Self {
-            token: token.to_string(),
-            client: Rc::new(client),
+            token: Arc::new(RwLock::new(token.to_string())),
+            api_url: Url::parse(GITHUB_API_URL).unwrap(),
+            client: Arc::new(client),
+            #[cfg(feature = "async")]
+            async_client: reqwest::Client::new(),
+            rate_limiter: Arc::new(RateLimiter::new()),
+            retry_policy: RetryPolicy::default(),
+            cache: None,
+            app: None,
+            scope_check: Arc::new(ScopeCheck::default()),
+        }
+    }
+
+    /// Create a client authenticating as a GitHub App installation. The
+    /// installation token is requested with the first request and replaced
+    /// before it expires, so the builders work as with a personal token.
+    pub fn with_app(auth: AppAuth) -> Self {
+        let mut gh = Self::new("");
+        gh.set_app_auth(Some(auth));
+        gh
+    }
+
+    /// Create a client that sends every request to the given API root
+    /// instead of `https://api.github.com`, e.g. the `/api/v3` root of a
+    /// GitHub Enterprise Server or a local mock server.
+    pub fn with_api_url<T, U>(token: T, api_url: U) -> Result<Self>
+    where
+        T: ToString,
+        U: AsRef<str>,
+    {
+        let mut gh = Self::new(token);
+        gh.set_api_url(api_url)?;
+        Ok(gh)
+    }
+
+    /// Create a client for the given host. `github.com` maps to
+    /// `https://api.github.com`, any other host is treated as a GitHub
+    /// Enterprise Server and gets the `/api/v3` path appended.
+    pub fn with_host<T, H>(token: T, host: H) -> Result<Self>
+    where
+        T: ToString,
+        H: AsRef<str>,
+    {
+        let mut gh = Self::new(token);
+        gh.set_host(host)?;
+        Ok(gh)
+    }
+
+    /// Send all requests to the API of the given host, see `with_host`
+    pub fn set_host<H>(&mut self, host: H) -> Result<()>
+    where
+        H: AsRef<str>,
+    {
+        self.api_url = api_url_for_host(host.as_ref())?;
+        Ok(())
+    }
+
+    /// Get the API root all requests are sent to
+    pub fn get_api_url(&self) -> &Url {
+        &self.api_url
+    }
+
+    /// Change the API root all requests are sent to
+    pub fn set_api_url<U>(&mut self, api_url: U) -> Result<()>
+    where
+        U: AsRef<str>,
+    {
+        self.api_url = Url::parse(api_url.as_ref())?;
+        Ok(())
+    }
+
+    /// The request budget of the token as reported by the last response
+    pub fn rate_limit(&self) -> RateLimit {
+        self.rate_limiter.rate_limit()
+    }
+
+    /// The rate limiter shared by this handle and all of its clones
+    pub fn rate_limiter(&self) -> &Arc<RateLimiter> {
+        &self.rate_limiter
+    }
+
+    /// Get the policy deciding which failed requests are sent again
+    pub fn get_retry_policy(&self) -> &RetryPolicy {
+        &self.retry_policy
+    }
+
+    /// Change the policy deciding which failed requests are sent again
+    pub fn set_retry_policy(&mut self, retry_policy: RetryPolicy) {
+        self.retry_policy = retry_policy;
+    }
+
+    /// The cache `GET` responses are revalidated against, if any
+    pub fn cache(&self) -> Option<&ResponseCache> {
+        self.cache.as_ref()
+    }
+
+    /// Enable or, with `None`, disable the response cache
+    pub fn set_cache(&mut self, cache: Option<ResponseCache>) {
+        self.cache = cache;
+    }
+
+    /// Require OAuth scopes of the token. They are checked against the
+    /// first response reporting the granted scopes, after which requests
+    /// fail with `Error::MissingScopes` if any is lacking. Applies to this
+    /// handle and all of its clones.
+    pub fn require_scopes(&self, scopes: &[&'static str]) {
+        self.scope_check.require(scopes);
+    }
+
+    /// The GitHub App the installation tokens are requested for, if any
+    pub fn app_auth(&self) -> Option<&AppAuth> {
+        self.app.as_deref()
+    }
+
+    /// Authenticate as a GitHub App installation or, with `None`, use the
+    /// token as set
+    pub fn set_app_auth(&mut self, auth: Option<AppAuth>) {
+        self.app = auth.map(Arc::new);
+    }
+
+    /// Request a new installation token if the current one is about to
+    /// expire and put the current token on the request. Requests built
+    /// before a refresh carry the old token, so it is replaced here.
+    fn authorize(&self, req: &mut Request) -> Result<()> {
+        let app = match &self.app {
+            Some(app) => app,
+            None => return Ok(()),
+        };
+        if app.needs_refresh() {
+            let _refreshing = app.refreshing.lock().unwrap();
+            // another thread may have refreshed the token in the meantime
+            if app.needs_refresh() {
+                let res = self.send_uncached(app.token_request(&self.api_url)?)?;
+                let (_, _, token) = read_response::<InstallationToken>(res)?;
+                self.set_installation_token(app, token)?;
+            }
+        }
+        self.authorize_with_current_token(req)
+    }
+
+    /// The asynchronous counterpart of `authorize`. Parallel requests may
+    /// each refresh an expiring token, which is harmless.
+    #[cfg(feature = "async")]
+    async fn authorize_async(&self, req: &mut Request) -> Result<()> {
+        let app = match &self.app {
+            Some(app) => app,
+            None => return Ok(()),
+        };
+        if app.needs_refresh() {
+            let res = self.send_uncached_async(app.token_request(&self.api_url)?).await?;
+            let (headers, status) = (res.headers().clone(), res.status());
+            let body = res.bytes().await?;
+            let (_, _, token) = parse_response::<InstallationToken>(headers, status, &body)?;
+            self.set_installation_token(app, token)?;
+        }
+        self.authorize_with_current_token(req)
+    }
+
+    fn set_installation_token(&self, app: &AppAuth, token: Option<InstallationToken>) -> Result<()> {
+        let token = token.ok_or_else(|| Error::InvalidCredentials("GitHub returned no installation token".to_string()))?;
+        self.set_token(app.update(token));
+        Ok(())
+    }
+
+    fn authorize_with_current_token(&self, req: &mut Request) -> Result<()> {
+        let token = HeaderValue::from_str(&format!("token {}", self.get_token()))?;
+        req.headers_mut().insert(AUTHORIZATION, token);
+        Ok(())
+    }
+
+    /// Send a request, answering it from the response cache if GitHub
+    /// reports the cached response as still valid.
+    pub(crate) fn send(&self, mut req: Request) -> Result<Response> {
+        self.scope_check.before_request()?;
+        self.authorize(&mut req)?;
+        let cache = self
+            .cache
+            .as_ref()
+            .and_then(|cache| ResponseCache::key(req.method(), req.url().as_str(), req.headers()).map(|key| (cache, key)));
+        let cached = cache.as_ref().and_then(|(cache, key)| cache.revalidate(key, req.headers_mut()));
+
+        let res = self.send_uncached(req)?;
+        self.scope_check.after_response(res.headers())?;
+        match (cache, cached) {
+            (Some((cache, key)), Some(entry)) if res.status() == StatusCode::NOT_MODIFIED => {
+                cache.touch(&key, &entry);
+                Ok(Response::from(entry.to_response()))
+            }
+            (Some((cache, key)), _) if res.status() == StatusCode::OK => {
+                let url = res.url().to_string();
+                let headers = res.headers().clone();
+                let body = res.bytes()?;
+                let _ = cache.store(&key, &url, &headers, &body);
+
+                let mut copy = http::Response::new(body.to_vec());
+                *copy.headers_mut() = headers;
+                Ok(Response::from(copy))
+            }
+            _ => Ok(res),
+        }
+    }
+
+    /// Send a request through the shared client. Blocks while the rate limit
+    /// budget is exhausted, spaces out mutating requests and retries
+    /// transient failures according to the retry policy.
+    fn send_uncached(&self, req: Request) -> Result<Response> {
+        let mut attempt = 1;
+        let mut pending = req;
+        loop {
+            // a request with a streaming body cannot be sent twice
+            let next = pending.try_clone();
+            let method = pending.method().clone();
+
+            self.rate_limiter.acquire(&method);
+            let delay = match self.client.execute(pending) {
+                Ok(res) => {
+                    let (res, rate_limited) = rate_limited(res)?;
+                    self.rate_limiter.update(res.headers(), rate_limited);
+                    match self.retry_policy.after_response(&method, attempt, res.status(), res.headers(), rate_limited) {
+                        Some(delay) if next.is_some() => delay,
+                        _ => return Ok(res),
+                    }
+                }
+                Err(e) => match self.retry_policy.after_error(&method, attempt, &e) {
+                    Some(delay) if next.is_some() => delay,
+                    _ => return Err(e.into()),
+                },
+            };
+
+            thread::sleep(delay);
+            attempt += 1;
+            pending = next.unwrap();
+        }
+    }
+
+    /// The asynchronous counterpart of `send`, waiting on tokio timers
+    /// instead of blocking the thread. Shares the rate limiter, the retry
+    /// policy and the response cache with the blocking client.
+    #[cfg(feature = "async")]
+    pub(crate) async fn send_async(&self, mut req: Request) -> Result<reqwest::Response> {
+        self.scope_check.before_request()?;
+        self.authorize_async(&mut req).await?;
+        let cache = self
+            .cache
+            .as_ref()
+            .and_then(|cache| ResponseCache::key(req.method(), req.url().as_str(), req.headers()).map(|key| (cache, key)));
+        let cached = cache.as_ref().and_then(|(cache, key)| cache.revalidate(key, req.headers_mut()));
+
+        let res = self.send_uncached_async(req).await?;
+        self.scope_check.after_response(res.headers())?;
+        match (cache, cached) {
+            (Some((cache, key)), Some(entry)) if res.status() == StatusCode::NOT_MODIFIED => {
+                cache.touch(&key, &entry);
+                Ok(reqwest::Response::from(entry.to_response()))
+            }
+            (Some((cache, key)), _) if res.status() == StatusCode::OK => {
+                let url = res.url().to_string();
+                let headers = res.headers().clone();
+                let body = res.bytes().await?;
+                let _ = cache.store(&key, &url, &headers, &body);
+
+                let mut copy = http::Response::new(body.to_vec());
+                *copy.headers_mut() = headers;
+                Ok(reqwest::Response::from(copy))
+            }
+            _ => Ok(res),
+        }
+    }
+
+    #[cfg(feature = "async")]
+    async fn send_uncached_async(&self, req: Request) -> Result<reqwest::Response> {
+        let method = req.method().clone();
+        let mut attempt = 1;
+        loop {
+            let pending = crate::asynchronous::to_async_request(&req)?;
+
+            let wait = self.rate_limiter.reserve(&method);
+            if wait > std::time::Duration::from_secs(0) {
+                tokio::time::delay_for(wait).await;
+            }
+            let delay = match self.async_client.execute(pending).await {
+                Ok(res) => {
+                    let (res, rate_limited) = rate_limited_async(res).await?;
+                    self.rate_limiter.update(res.headers(), rate_limited);
+                    match self.retry_policy.after_response(&method, attempt, res.status(), res.headers(), rate_limited) {
+                        Some(delay) => delay,
+                        None => return Ok(res),
+                    }
+                }
+                Err(e) => match self.retry_policy.after_error(&method, attempt, &e) {
+                    Some(delay) => delay,
+                    None => return Err(e.into()),
+                },
+            };
+
+            tokio::time::delay_for(delay).await;
+            attempt += 1;
         }
     }
 
     /// Get the currently set Authorization Token
-    pub fn get_token(&self) -> &str {
-        &self.token
+    pub fn get_token(&self) -> String {
+        self.token.read().unwrap().clone()
     }
 
     /// Change the currently set Authorization Token using a type that can turn
-    /// into an &str. Must be a valid API Token for requests to work.
-    pub fn set_token<T>(&mut self, token: T)
+    /// into an &str. Must be a valid API Token for requests to work. The
+    /// token is shared, so all clones of this handle use the new one.
+    pub fn set_token<T>(&self, token: T)
     where
         T: ToString,
     {
-        self.token = token.to_string();
+        *self.token.write().unwrap() = token.to_string();
     }
 
     /// Begin building up a GET request to GitHub
@@ -77,59 +462,80 @@ impl Github {
         self.into()
     }
 
+    /// Begin building up a POST request to GitHub with a JSON body
     pub fn post<T>(&self, body: T) -> PostQueryBuilder
     where
         T: Serialize,
     {
         let mut qb: PostQueryBuilder = self.into();
-        if let Ok(mut qbr) = qb.request {
-            let serialized = serde_json::to_vec(&body);
-            match serialized {
-                Ok(json) => {
-                    let json_str: Vec<u8> = json.into();
-                    let body = reqwest::blocking::Body::from(json_str);
-                    *qbr.get_mut().body_mut() = Some(body);
-                    qb.request = Ok(qbr);
-                }
-                Err(_) => {
-                    qb.request = Err("Unable to serialize data to JSON".into());
-                }
-            }
-        }
-
+        qb.request = json_body(qb.request, body);
         qb
     }
 
+    /// Begin building up a PUT request to GitHub with a JSON body
     pub fn put<T>(&self, body: T) -> PutQueryBuilder
     where
         T: Serialize,
     {
         let mut qb: PutQueryBuilder = self.into();
-        if let Ok(mut qbr) = qb.request {
-            let serialized = serde_json::to_vec(&body);
-            match serialized {
-                Ok(json) => {
-                    let json_str: Vec<u8> = json.into();
-                    let body = reqwest::blocking::Body::from(json_str);
-                    *qbr.get_mut().body_mut() = Some(body);
-                    qb.request = Ok(qbr);
-                }
-                Err(_) => {
-                    qb.request = Err("Unable to serialize data to JSON".into());
-                }
-            }
-        }
+        qb.request = json_body(qb.request, body);
+        qb
+    }
+
+    /// Begin building up a PATCH request to GitHub with a JSON body
+    pub fn patch<T>(&self, body: T) -> PatchQueryBuilder
+    where
+        T: Serialize,
+    {
+        let mut qb: PatchQueryBuilder = self.into();
+        qb.request = json_body(qb.request, body);
+        qb
+    }
+
+    /// Begin building up a DELETE request to GitHub
+    pub fn delete(&self) -> DeleteQueryBuilder {
+        self.into()
+    }
 
+    /// Begin building up a DELETE request to GitHub with a JSON body, as
+    /// needed e.g. to remove requested reviewers or to delete a file
+    pub fn delete_with_body<T>(&self, body: T) -> DeleteQueryBuilder
+    where
+        T: Serialize,
+    {
+        let mut qb: DeleteQueryBuilder = self.into();
+        qb.request = json_body(qb.request, body);
         qb
     }
 }
 
+/// Serialize the body to JSON and attach it to the request
+fn json_body<T>(request: Result<RefCell<Request>>, body: T) -> Result<RefCell<Request>>
+where
+    T: Serialize,
+{
+    let mut req = request?;
+    match serde_json::to_vec(&body) {
+        Ok(json) => {
+            *req.get_mut().body_mut() = Some(reqwest::blocking::Body::from(json));
+            Ok(req)
+        }
+        Err(e) => Err(Error::InvalidRequest(format!("unable to serialize body to JSON: {}", e))),
+    }
+}
+
 impl<'g> GetQueryBuilder<'g> {
     func_client!(custom_endpoint, CustomQuery, endpoint_str);
 
     /// Query the user endpoint
     func_client!(user, crate::users::get::User<'g>);
 
+    // Query the users endpoint, for other users than the authenticated one
+    func_client!(users, crate::users::get::Users<'g>);
+
+    // Query the orgs endpoint
+    func_client!(orgs, crate::orgs::get::Orgs<'g>);
+
     /// Query the repos endpoint
     func_client!(repos, crate::repos::get::Repos<'g>);
 
@@ -164,6 +570,16 @@ impl<'g> PutQueryBuilder<'g> {
     func_client!(repos, crate::repos::put::Repos<'g>);
 }
 
+impl<'g> PatchQueryBuilder<'g> {
+    func_client!(custom_endpoint, CustomQuery, endpoint_str);
+    func_client!(repos, crate::repos::patch::Repos<'g>);
+}
+
+impl<'g> DeleteQueryBuilder<'g> {
+    func_client!(custom_endpoint, CustomQuery, endpoint_str);
+    func_client!(repos, crate::repos::delete::Repos<'g>);
+}
+
 // exec!(Github);
 
 from!(
@@ -173,6 +589,10 @@ from!(
         => "POST"
     @PutQueryBuilder
         => "PUT"
+    @PatchQueryBuilder
+        => "PATCH"
+    @DeleteQueryBuilder
+        => "DELETE"
 );
 
 from!(
@@ -182,6 +602,10 @@ from!(
         => CustomQuery
     @PutQueryBuilder
         => CustomQuery
+    @PatchQueryBuilder
+        => CustomQuery
+    @DeleteQueryBuilder
+        => CustomQuery
 );
 
 impl<'a> CustomQuery<'a> {
@@ -208,52 +632,403 @@ impl<'a> CustomQuery<'a> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::mock::{serve, Canned};
+    use crate::pagination::Paginate;
 
-    fn auth_token() -> Result<String> {
-        Ok("test_token".to_string())
-    }
-
-    fn setup_github_connection() -> Github {
-        Github::new(ghauto_config::credentials::access_token().unwrap().to_string())
+    fn setup_github_connection(api_url: &str) -> Github {
+        Github::with_api_url("test_token", api_url).unwrap()
     }
 
     #[test]
     fn set_and_load_token() {
-        let g = setup_github_connection()
+        let (url, requests) = serve(vec![Canned::json(200, r#"[{"id": 1, "name": "octocat"}]"#)]);
+        let (_, status, users) = setup_github_connection(&url)
             .get()
             .custom_endpoint("users")
-            .execute::<serde_json::Value>()
-            //.execute::<Vec<User>>()
+            .execute::<Vec<User>>()
             .unwrap();
-        // println!("response {:#?}", g);
-        //.expect("Connection failed");
 
-        // let response = CustomQuery::execute::<Vec<User>>().await;
-        // let (_, _, users) = response.ok();
-
-        // let u = match g {
-        //     Ok(u) => println!("success: {:#?}", u),
-        //     Err(e) => {
-        //         println!("error: {:#?}", e);
-        //     }
-        // };
+        assert_eq!(StatusCode::OK, status);
+        assert_eq!("octocat", users.unwrap()[0].name);
+        let req = requests.recv().unwrap();
+        assert_eq!("/users", req.path);
+        assert_eq!(Some("token test_token"), req.header("authorization"));
     }
 
- #[test]
+    #[test]
     fn users() {
-        let g = setup_github_connection()
+        let (url, requests) = serve(vec![Canned::json(200, r#"[{"email": "octocat@github.com"}]"#)]);
+        let (_, _, emails) = setup_github_connection(&url)
             .get()
             .user()
             .emails()
             .execute::<serde_json::Value>()
-            //.execute::<Vec<User>>()
             .unwrap();
-        println!("response {:#?}", g);
+
+        assert_eq!("octocat@github.com", emails.unwrap()[0]["email"]);
+        assert_eq!("/user/emails", requests.recv().unwrap().path);
+    }
+
+    #[test]
+    fn org_and_user_repos() {
+        let (url, requests) = serve(vec![
+            Canned::json(200, r#"[{"name": "hello-world"}]"#),
+            Canned::json(200, r#"[{"name": "spoon-knife"}]"#),
+            Canned::json(200, r#"[{"name": "secret", "private": true}]"#),
+        ]);
+        let gh = setup_github_connection(&url);
+
+        let repos: Vec<serde_json::Value> = gh
+            .get()
+            .orgs()
+            .org("github")
+            .repos()
+            .per_page(100)
+            .paginate()
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!("hello-world", repos[0]["name"]);
+        assert_eq!("/orgs/github/repos?per_page=100", requests.recv().unwrap().path);
+
+        let repos: Vec<serde_json::Value> = gh
+            .get()
+            .users()
+            .username("octocat")
+            .repos()
+            .paginate()
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!("spoon-knife", repos[0]["name"]);
+        assert_eq!("/users/octocat/repos", requests.recv().unwrap().path);
+
+        let repos: Vec<serde_json::Value> = gh
+            .get()
+            .user()
+            .repos()
+            .affiliation(crate::params::Affiliation::Owner)
+            .paginate()
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!("secret", repos[0]["name"]);
+        assert_eq!("/user/repos?affiliation=owner", requests.recv().unwrap().path);
+    }
+
+    #[test]
+    fn enterprise_api_url() {
+        let (url, requests) = serve(vec![Canned::json(200, "{}")]);
+        let gh = Github::with_host("test_token", &url).unwrap();
+        assert_eq!(format!("{}/api/v3", url), gh.get_api_url().as_str());
+
+        gh.get()
+            .repos()
+            .owner("octocat")
+            .repo("hello-world")
+            .execute::<serde_json::Value>()
+            .unwrap();
+        assert_eq!("/api/v3/repos/octocat/hello-world", requests.recv().unwrap().path);
+    }
+
+    #[test]
+    fn builder_sends_no_request() {
+        let (url, requests) = serve(vec![Canned::json(200, "[]")]);
+        let gh = setup_github_connection(&url);
+
+        let query = gh.get().repos().owner("octocat").repo("hello-world").issues();
+        assert!(requests.recv_timeout(std::time::Duration::from_millis(200)).is_err());
+
+        query.execute::<serde_json::Value>().unwrap();
+        let req = requests.recv().unwrap();
+        assert_eq!("/repos/octocat/hello-world/issues", req.path);
+        assert!(requests.recv_timeout(std::time::Duration::from_millis(200)).is_err());
+    }
+
+    #[test]
+    fn error_status_is_returned_as_error() {
+        let body = r#"{"message": "Not Found", "documentation_url": "https://developer.github.com/v3"}"#;
+        let (url, _) = serve(vec![Canned::json(404, body)]);
+        let err = setup_github_connection(&url)
+            .get()
+            .repos()
+            .owner("octocat")
+            .repo("missing")
+            .execute::<serde_json::Value>()
+            .unwrap_err();
+
+        match err {
+            Error::Http { status, message, .. } => {
+                assert_eq!(StatusCode::NOT_FOUND, status);
+                assert_eq!("Not Found", message);
+            }
+            e => panic!("unexpected error {:?}", e),
+        }
+    }
+
+    #[test]
+    fn forbidden_is_not_retried_and_keeps_its_message() {
+        let body = r#"{"message": "Resource not accessible by integration"}"#;
+        let (url, requests) = serve(vec![Canned::json(403, body), Canned::json(200, "{}")]);
+        let err = setup_github_connection(&url)
+            .get()
+            .repos()
+            .owner("octocat")
+            .repo("private")
+            .execute::<serde_json::Value>()
+            .unwrap_err();
+
+        match err {
+            Error::Auth { message, .. } => assert_eq!("Resource not accessible by integration", message),
+            e => panic!("unexpected error {:?}", e),
+        }
+        requests.recv().unwrap();
+        assert!(requests.recv_timeout(std::time::Duration::from_millis(200)).is_err());
+    }
+
+    #[test]
+    fn missing_scopes_stop_further_requests() {
+        let (url, requests) = serve(vec![
+            Canned::json(200, "{}").header("x-oauth-scopes", "public_repo"),
+            Canned::json(200, "{}"),
+        ]);
+        let gh = setup_github_connection(&url);
+        gh.clone().require_scopes(&["repo"]);
+
+        let err = gh.get().repos().owner("octocat").repo("a").execute::<serde_json::Value>().unwrap_err();
+        match err {
+            Error::MissingScopes { missing, .. } => assert_eq!(vec!["repo"], missing),
+            e => panic!("unexpected error {:?}", e),
+        }
+        assert!(gh.get().repos().owner("octocat").repo("b").execute::<serde_json::Value>().is_err());
+        requests.recv().unwrap();
+        assert!(requests.recv_timeout(std::time::Duration::from_millis(200)).is_err());
+    }
+
+    #[test]
+    fn model_mismatch_is_returned_as_error() {
+        let (url, _) = serve(vec![Canned::json(200, r#"{"id": "not a number"}"#)]);
+        let err = setup_github_connection(&url)
+            .get()
+            .custom_endpoint("users")
+            .execute::<Vec<User>>()
+            .unwrap_err();
+
+        match err {
+            Error::Deserialize { status, .. } => assert_eq!(StatusCode::OK, status),
+            e => panic!("unexpected error {:?}", e),
+        }
+    }
+
+    #[test]
+    fn empty_body_is_none() {
+        let (url, _) = serve(vec![Canned::json(204, "")]);
+        let (_, status, body) = setup_github_connection(&url)
+            .get()
+            .custom_endpoint("user/following/octocat")
+            .execute::<serde_json::Value>()
+            .unwrap();
+
+        assert_eq!(StatusCode::NO_CONTENT, status);
+        assert!(body.is_none());
+    }
+
+    #[test]
+    fn tracks_rate_limit() {
+        let (url, _) = serve(vec![Canned::json(200, "{}")
+            .header("x-ratelimit-limit", "5000")
+            .header("x-ratelimit-remaining", "4321")
+            .header("x-ratelimit-reset", "1600000000")]);
+        let gh = setup_github_connection(&url);
+        assert_eq!(None, gh.rate_limit().remaining);
+
+        gh.get().user().execute::<serde_json::Value>().unwrap();
+        let shared = gh.clone();
+        assert_eq!(Some(4321), shared.rate_limit().remaining);
+        assert_eq!(Some(1600000000), shared.rate_limit().reset);
+    }
+
+    #[test]
+    fn retries_transient_failures() {
+        let (url, requests) = serve(vec![
+            Canned::json(502, r#"{"message": "Server Error"}"#),
+            Canned::json(200, r#"{"login": "octocat"}"#),
+        ]);
+        let mut gh = setup_github_connection(&url);
+        gh.set_retry_policy(RetryPolicy {
+            initial_backoff: std::time::Duration::from_millis(1),
+            ..Default::default()
+        });
+
+        let (_, _, user) = gh.get().user().execute::<serde_json::Value>().unwrap();
+        assert_eq!("octocat", user.unwrap()["login"]);
+        assert_eq!("/user", requests.recv().unwrap().path);
+        assert_eq!("/user", requests.recv().unwrap().path);
+    }
+
+    #[test]
+    fn does_not_retry_post() {
+        let (url, requests) = serve(vec![
+            Canned::json(502, r#"{"message": "Server Error"}"#),
+            Canned::json(201, "{}"),
+        ]);
+        let mut gh = setup_github_connection(&url);
+        gh.set_retry_policy(RetryPolicy {
+            initial_backoff: std::time::Duration::from_millis(1),
+            ..Default::default()
+        });
+
+        let err = gh
+            .post(serde_json::json!({"title": "test"}))
+            .repos()
+            .owner("octocat")
+            .repo("hello-world")
+            .pulls()
+            .execute::<serde_json::Value>()
+            .unwrap_err();
+        assert_eq!(Some(StatusCode::BAD_GATEWAY), err.status());
+        requests.recv().unwrap();
+        assert!(requests.recv_timeout(std::time::Duration::from_millis(200)).is_err());
+    }
+
+    #[test]
+    fn patch_and_delete() {
+        let (url, requests) = serve(vec![Canned::json(200, r#"{"state": "closed"}"#), Canned::json(204, "")]);
+        let mut gh = setup_github_connection(&url);
+        gh.rate_limiter().set_mutation_interval(std::time::Duration::from_secs(0));
+        gh.set_retry_policy(RetryPolicy::none());
+
+        let (_, status, _) = gh
+            .patch(serde_json::json!({"state": "closed"}))
+            .repos()
+            .owner("octocat")
+            .repo("hello-world")
+            .issues()
+            .number("42")
+            .execute::<serde_json::Value>()
+            .unwrap();
+        assert_eq!(StatusCode::OK, status);
+        let req = requests.recv().unwrap();
+        assert_eq!("PATCH", req.method);
+        assert_eq!("/repos/octocat/hello-world/issues/42", req.path);
+        assert_eq!(r#"{"state":"closed"}"#, String::from_utf8_lossy(&req.body));
+
+        let (_, status, body) = gh
+            .delete_with_body(serde_json::json!({"reviewers": ["octocat"]}))
+            .repos()
+            .owner("octocat")
+            .repo("hello-world")
+            .pulls()
+            .number("7")
+            .requested_reviewers()
+            .execute::<serde_json::Value>()
+            .unwrap();
+        assert_eq!(StatusCode::NO_CONTENT, status);
+        assert!(body.is_none());
+        let req = requests.recv().unwrap();
+        assert_eq!("DELETE", req.method);
+        assert_eq!("/repos/octocat/hello-world/pulls/7/requested_reviewers", req.path);
+        assert_eq!(r#"{"reviewers":["octocat"]}"#, String::from_utf8_lossy(&req.body));
+    }
+
+    #[test]
+    fn invalid_names_are_rejected_before_sending() {
+        let (url, requests) = serve(vec![Canned::json(200, "{}")]);
+        let gh = setup_github_connection(&url);
+
+        let res = gh
+            .get()
+            .repos()
+            .owner("octocat")
+            .repo("../hello-world")
+            .execute::<serde_json::Value>();
+        match res {
+            Err(Error::InvalidName { name, .. }) => assert_eq!("../hello-world", name),
+            r => panic!("unexpected result {:?}", r.map(|(_, s, _)| s)),
+        }
+        assert!(requests.recv_timeout(std::time::Duration::from_millis(200)).is_err());
+    }
+
+    #[test]
+    fn file_paths_are_encoded() {
+        let (url, requests) = serve(vec![Canned::json(200, "{}")]);
+        let gh = setup_github_connection(&url);
+
+        gh.get()
+            .repos()
+            .owner("octocat")
+            .repo("hello-world")
+            .contents()
+            .path("docs/read me#1.md")
+            .execute::<serde_json::Value>()
+            .unwrap();
+        assert_eq!(
+            "/repos/octocat/hello-world/contents/docs/read%20me%231.md",
+            requests.recv().unwrap().path
+        );
+    }
+
+    #[test]
+    fn shared_between_threads() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<Github>();
+
+        let (url, requests) = serve(vec![Canned::json(200, "{}"), Canned::json(200, "{}")]);
+        let gh = setup_github_connection(&url);
+        let clone = gh.clone();
+        clone.set_token("rotated");
+
+        thread::scope(|scope| {
+            for repo in &["one", "two"] {
+                let gh = &gh;
+                scope.spawn(move || {
+                    gh.get()
+                        .repos()
+                        .owner("octocat")
+                        .repo(repo)
+                        .execute::<serde_json::Value>()
+                        .unwrap();
+                });
+            }
+        });
+
+        for _ in 0..2 {
+            assert_eq!(Some("token rotated"), requests.recv().unwrap().header("authorization"));
+        }
+    }
+
+    #[test]
+    fn revalidates_cached_responses() {
+        let (url, requests) = serve(vec![
+            Canned::json(200, "[1, 2]").header("etag", "\"v1\""),
+            Canned::json(304, ""),
+        ]);
+        let mut gh = setup_github_connection(&url);
+        gh.set_cache(Some(crate::cache::temp_cache()));
+
+        let issues = |gh: &Github| {
+            gh.get()
+                .repos()
+                .owner("octocat")
+                .repo("hello-world")
+                .issues()
+                .execute::<Vec<u32>>()
+                .unwrap()
+        };
+
+        let (_, status, first) = issues(&gh);
+        assert_eq!(StatusCode::OK, status);
+        assert_eq!(None, requests.recv().unwrap().header("if-none-match"));
+
+        let (headers, status, second) = issues(&gh);
+        assert_eq!(StatusCode::OK, status);
+        assert_eq!(first, second);
+        assert_eq!("\"v1\"", headers.get("etag").unwrap());
+        assert_eq!(Some("\"v1\""), requests.recv().unwrap().header("if-none-match"));
+
+        let _ = std::fs::remove_dir_all(gh.cache().unwrap().dir());
     }
 
-    // #[test]
-    // fn auth() {
-    //     use crate::gh_auth::*;
-    //     github_authorize();
-    // }
+    #[test]
+    fn default_api_url() {
+        let gh = Github::new("test_token");
+        assert_eq!("https://api.github.com/", gh.get_api_url().as_str());
+    }
 }
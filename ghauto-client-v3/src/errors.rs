@@ -1,13 +1,345 @@
-#![allow(deprecated)] // cause()
-error_chain! {
-    foreign_links {
-        CellBorrowMut(::std::cell::BorrowMutError);
-        Hyper(::hyper::Error);
-        Http(::hyper::http::Error);
-        HeaderValue(::hyper::header::InvalidHeaderValue);
-        Uri(::hyper::http::uri::InvalidUriParts);
-        HyperTls(::native_tls::Error) #[cfg(feature = "rust-native-tls")];
-        Io(::std::io::Error);
-        SerdeJson(::serde_json::Error);
+use hyper::header::HeaderMap;
+use hyper::StatusCode;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+use crate::graphql::GraphqlError;
+
+/// A single entry of the `errors` array GitHub attaches to validation
+/// failures, e.g. `{"resource": "Issue", "field": "title", "code": "missing_field"}`.
+#[derive(Deserialize, Serialize, Clone, Debug, Default, PartialEq)]
+pub struct ErrorDetail {
+    pub resource: Option<String>,
+    pub field: Option<String>,
+    pub code: Option<String>,
+    pub message: Option<String>,
+}
+
+/// Some endpoints report plain strings instead of objects in `errors`
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+enum RawErrorDetail {
+    Detail(ErrorDetail),
+    Message(String),
+}
+
+/// The body GitHub sends along with an error status
+#[derive(Deserialize, Debug, Default)]
+struct ApiErrorBody {
+    #[serde(default)]
+    message: String,
+    #[serde(default)]
+    errors: Vec<RawErrorDetail>,
+    documentation_url: Option<String>,
+}
+
+impl Display for ErrorDetail {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match (&self.message, &self.resource, &self.field, &self.code) {
+            (Some(m), _, _, _) => write!(f, "{}", m),
+            (None, Some(r), Some(fd), Some(c)) => write!(f, "{}.{}: {}", r, fd, c),
+            (None, _, _, Some(c)) => write!(f, "{}", c),
+            _ => write!(f, "unknown error"),
+        }
+    }
+}
+
+/// Errors returned by the Github client
+#[derive(Debug)]
+pub enum Error {
+    /// The request could not be sent or the response could not be read
+    Transport(reqwest::Error),
+    /// The request could not be built, e.g. because of an invalid url or body
+    InvalidRequest(String),
+    /// An owner or repository name does not follow GitHub's naming rules,
+    /// caught before any request is sent
+    InvalidName { name: String, reason: String },
+    /// The credentials cannot be used, e.g. an unreadable private key of a
+    /// GitHub App
+    InvalidCredentials(String),
+    /// An OAuth flow did not yield a token, e.g. because the user declined
+    /// or the device code expired. `error` is the OAuth error code.
+    OAuth { error: String, description: Option<String> },
+    /// GitHub answered with an error status
+    Http {
+        status: StatusCode,
+        message: String,
+        errors: Vec<ErrorDetail>,
+        documentation_url: Option<String>,
+    },
+    /// GitHub rejected the request because a rate limit was exhausted.
+    /// `reset` is the epoch second the budget resets, `retry_after` the
+    /// number of seconds GitHub asks to wait.
+    RateLimited {
+        status: StatusCode,
+        message: String,
+        reset: Option<u64>,
+        retry_after: Option<u64>,
+    },
+    /// The token is missing, invalid or lacks the permission for the request
+    Auth {
+        status: StatusCode,
+        message: String,
+        documentation_url: Option<String>,
+    },
+    /// A GraphQL query was rejected, GitHub reports these with `200 OK`
+    Graphql { errors: Vec<GraphqlError> },
+    /// The token lacks scopes the client was told to require, see
+    /// `Github::require_scopes`
+    MissingScopes { missing: Vec<String>, granted: Vec<String> },
+    /// The response body does not match the expected model
+    Deserialize {
+        status: StatusCode,
+        error: serde_json::Error,
+    },
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Whether GitHub rejected a request because of a rate limit: `429`, or a
+/// `403` with an exhausted budget, a `Retry-After` header or a message
+/// naming the limit, e.g. "You have exceeded a secondary rate limit"
+pub(crate) fn is_rate_limited(status: StatusCode, headers: &HeaderMap, message: &str) -> bool {
+    if status == StatusCode::TOO_MANY_REQUESTS {
+        return true;
+    }
+    if status != StatusCode::FORBIDDEN {
+        return false;
+    }
+
+    let exhausted = headers
+        .get("x-ratelimit-remaining")
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.trim() == "0")
+        .unwrap_or(false);
+    exhausted || headers.contains_key("retry-after") || message.to_lowercase().contains("rate limit")
+}
+
+/// The `message` of an error body, empty if there is none
+pub(crate) fn error_message(body: &[u8]) -> String {
+    serde_json::from_slice::<ApiErrorBody>(body).unwrap_or_default().message
+}
+
+impl Error {
+    /// The HTTP status of the response that caused the error, if any
+    pub fn status(&self) -> Option<StatusCode> {
+        match self {
+            Error::Http { status, .. }
+            | Error::RateLimited { status, .. }
+            | Error::Auth { status, .. }
+            | Error::Deserialize { status, .. } => Some(*status),
+            Error::Transport(e) => e.status(),
+            Error::InvalidRequest(_)
+            | Error::InvalidName { .. }
+            | Error::InvalidCredentials(_)
+            | Error::OAuth { .. }
+            | Error::Graphql { .. }
+            | Error::MissingScopes { .. } => None,
+        }
+    }
+
+    /// Whether GitHub answered with `404 Not Found`
+    pub fn is_not_found(&self) -> bool {
+        self.status() == Some(StatusCode::NOT_FOUND)
+    }
+
+    /// Build the error for a response with a non success status
+    pub(crate) fn from_response(status: StatusCode, headers: &HeaderMap, body: &[u8]) -> Self {
+        let parsed: ApiErrorBody = serde_json::from_slice(body).unwrap_or_default();
+        let message = if parsed.message.is_empty() {
+            status.canonical_reason().unwrap_or("unknown error").to_string()
+        } else {
+            parsed.message
+        };
+
+        let header_u64 = |name: &str| {
+            headers
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.trim().parse::<u64>().ok())
+        };
+        let retry_after = header_u64("retry-after");
+
+        if is_rate_limited(status, headers, &message) {
+            return Error::RateLimited {
+                status: status,
+                message: message,
+                reset: header_u64("x-ratelimit-reset"),
+                retry_after: retry_after,
+            };
+        }
+
+        match status {
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => Error::Auth {
+                status: status,
+                message: message,
+                documentation_url: parsed.documentation_url,
+            },
+            _ => Error::Http {
+                status: status,
+                message: message,
+                errors: parsed
+                    .errors
+                    .into_iter()
+                    .map(|e| match e {
+                        RawErrorDetail::Detail(d) => d,
+                        RawErrorDetail::Message(m) => ErrorDetail {
+                            message: Some(m),
+                            ..Default::default()
+                        },
+                    })
+                    .collect(),
+                documentation_url: parsed.documentation_url,
+            },
+        }
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match self {
+            Error::Transport(e) => write!(f, "request failed: {}", e),
+            Error::InvalidRequest(m) => write!(f, "invalid request: {}", m),
+            Error::InvalidName { name, reason } => write!(f, "invalid name `{}`: {}", name, reason),
+            Error::InvalidCredentials(m) => write!(f, "invalid credentials: {}", m),
+            Error::OAuth { error, description } => match description {
+                Some(d) => write!(f, "login failed, {}: {}", error, d),
+                None => write!(f, "login failed, {}", error),
+            },
+            Error::Http {
+                status,
+                message,
+                errors,
+                documentation_url,
+            } => {
+                write!(f, "{}: {}", status, message)?;
+                for e in errors.iter() {
+                    write!(f, "; {}", e)?;
+                }
+                if let Some(url) = documentation_url {
+                    write!(f, " (see {})", url)?;
+                }
+                Ok(())
+            }
+            Error::RateLimited {
+                status,
+                message,
+                reset,
+                retry_after,
+            } => {
+                write!(f, "{}: {}", status, message)?;
+                match (retry_after, reset) {
+                    (Some(s), _) => write!(f, " (retry after {}s)", s),
+                    (None, Some(r)) => write!(f, " (resets at {})", r),
+                    (None, None) => Ok(()),
+                }
+            }
+            Error::Auth {
+                status,
+                message,
+                documentation_url,
+            } => {
+                write!(f, "authentication failed, {}: {}", status, message)?;
+                if let Some(url) = documentation_url {
+                    write!(f, " (see {})", url)?;
+                }
+                Ok(())
+            }
+            Error::Graphql { errors } => {
+                write!(f, "GraphQL query failed")?;
+                for (i, e) in errors.iter().enumerate() {
+                    write!(f, "{} {}", if i == 0 { ":" } else { ";" }, e)?;
+                }
+                Ok(())
+            }
+            Error::MissingScopes { missing, granted } => write!(
+                f,
+                "the token lacks the scope(s) {} and only grants {}; create a token with these scopes or run `bardo auth login`",
+                missing.join(", "),
+                if granted.is_empty() { "no scopes".to_string() } else { granted.join(", ") }
+            ),
+            Error::Deserialize { status, error } => {
+                write!(f, "unexpected response body ({}): {}", status, error)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Transport(e) => Some(e),
+            Error::Deserialize { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(e: reqwest::Error) -> Self {
+        Error::Transport(e)
+    }
+}
+
+impl From<url::ParseError> for Error {
+    fn from(e: url::ParseError) -> Self {
+        Error::InvalidRequest(format!("invalid url: {}", e))
+    }
+}
+
+impl From<hyper::header::InvalidHeaderValue> for Error {
+    fn from(e: hyper::header::InvalidHeaderValue) -> Self {
+        Error::InvalidRequest(format!("invalid header value: {}", e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hyper::header::HeaderValue;
+
+    #[test]
+    fn validation_failed() {
+        let body = br#"{
+            "message": "Validation Failed",
+            "errors": [{"resource": "Issue", "field": "title", "code": "missing_field"}, "plain"],
+            "documentation_url": "https://developer.github.com/v3/issues/#create-an-issue"
+        }"#;
+        let err = Error::from_response(StatusCode::UNPROCESSABLE_ENTITY, &HeaderMap::new(), body);
+        match &err {
+            Error::Http { message, errors, documentation_url, .. } => {
+                assert_eq!("Validation Failed", message);
+                assert_eq!(2, errors.len());
+                assert_eq!(Some("title".to_string()), errors[0].field);
+                assert_eq!(Some("plain".to_string()), errors[1].message);
+                assert!(documentation_url.is_some());
+            }
+            _ => panic!("unexpected error {:?}", err),
+        }
+        assert_eq!(Some(StatusCode::UNPROCESSABLE_ENTITY), err.status());
+    }
+
+    #[test]
+    fn rate_limited() {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-ratelimit-remaining", HeaderValue::from_static("0"));
+        headers.insert("x-ratelimit-reset", HeaderValue::from_static("1600000000"));
+        let body = br#"{"message": "API rate limit exceeded"}"#;
+        match Error::from_response(StatusCode::FORBIDDEN, &headers, body) {
+            Error::RateLimited { reset, .. } => assert_eq!(Some(1600000000), reset),
+            e => panic!("unexpected error {:?}", e),
+        }
+    }
+
+    #[test]
+    fn auth_and_not_found() {
+        let body = br#"{"message": "Bad credentials"}"#;
+        match Error::from_response(StatusCode::UNAUTHORIZED, &HeaderMap::new(), body) {
+            Error::Auth { message, .. } => assert_eq!("Bad credentials", message),
+            e => panic!("unexpected error {:?}", e),
+        }
+
+        let err = Error::from_response(StatusCode::NOT_FOUND, &HeaderMap::new(), b"");
+        assert!(err.is_not_found());
+        assert_eq!("404 Not Found: Not Found", err.to_string());
     }
 }
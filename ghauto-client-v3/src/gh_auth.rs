@@ -1,113 +1,418 @@
-use oauth2::basic::BasicClient;
+//! Obtaining an access token for a user through the OAuth flows of an OAuth
+//! App. The device flow works on machines without a browser, the web flow
+//! redirects the browser to a listener on `localhost`.
+//!
+//! ```ignore
+//! let app = OAuthApp::new("client id", None);
+//! let token = app.device_flow(|code| {
+//!     println!("enter {} at {}", code.user_code, code.verification_uri)
+//! })?;
+//! ```
+
+use hyper::header::{HeaderValue, ACCEPT, USER_AGENT};
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+use reqwest::blocking::Client;
+use reqwest::Url;
+use serde::de::DeserializeOwned;
 
-use oauth2::reqwest::http_client;
-use oauth2::{
-    AuthUrl, AuthorizationCode, ClientId, ClientSecret, CsrfToken, RedirectUrl, Scope,
-    TokenResponse, TokenUrl,
-};
 use std::io::{BufRead, BufReader, Write};
-use std::net::TcpListener;
-use oauth2::url::Url;
-use webbrowser;
+use std::net::{TcpListener, TcpStream};
+use std::thread;
+use std::time::{Duration, Instant};
 
-pub fn github_authorize(client_id: Option<String>, client_secret: Option<String>) {
-    let github_client_id = ClientId::new(
-        client_id.expect("Missing the GITHUB_CLIENT_ID environment variable."),
-    );
-    let github_client_secret = ClientSecret::new(
-        client_secret.expect("Missing the GITHUB_CLIENT_SECRET environment variable."),
-    );
-    let auth_url = AuthUrl::new("https://github.com/login/oauth/authorize".to_string()).expect("Invalid token URL");
-    let token_url = TokenUrl::new("https://github.com/login/oauth/access_token".to_string()).expect("Invalid token endpoint URL");
-
-    let client = BasicClient::new(
-        github_client_id,
-        Some(github_client_secret),
-        auth_url,
-        Some(token_url),
-    ).set_redirect_url(
-        RedirectUrl::new("http://localhost:8080".to_string()).expect("Invalid redirect URL"),
-    );
+use crate::client::{parse_response, Error, Result};
+use crate::util::url_join;
+
+/// The host serving the OAuth endpoints of github.com
+pub const GITHUB_URL: &str = "https://github.com";
+
+/// The scopes requested unless others are set
+pub const DEFAULT_SCOPES: &[&str] = &["public_repo", "repo", "user:email"];
+
+const DEVICE_GRANT_TYPE: &str = "urn:ietf:params:oauth:grant-type:device_code";
+
+/// A token obtained through one of the flows
+#[derive(Clone, Debug, PartialEq)]
+pub struct AccessToken {
+    pub access_token: String,
+    /// The scopes granted by the user, which may differ from the requested
+    pub scopes: Vec<String>,
+}
+
+/// The code the user enters at `verification_uri` to authorize a device
+#[derive(Deserialize, Clone, Debug)]
+pub struct DeviceCode {
+    pub device_code: String,
+    pub user_code: String,
+    pub verification_uri: String,
+    /// Seconds until the codes expire
+    pub expires_in: u64,
+    /// Seconds to wait between two polls
+    pub interval: u64,
+}
+
+/// Answer of the token endpoint, either a token or an error
+#[derive(Deserialize, Debug)]
+struct TokenResponse {
+    access_token: Option<String>,
+    scope: Option<String>,
+    error: Option<String>,
+    error_description: Option<String>,
+    interval: Option<u64>,
+}
+
+impl TokenResponse {
+    fn into_token(self) -> Result<AccessToken> {
+        match (self.access_token, self.error) {
+            (Some(token), None) => Ok(AccessToken {
+                access_token: token,
+                scopes: self
+                    .scope
+                    .unwrap_or_default()
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|s| !s.is_empty())
+                    .map(String::from)
+                    .collect(),
+            }),
+            (_, error) => Err(Error::OAuth {
+                error: error.unwrap_or_else(|| "missing_token".to_string()),
+                description: self.error_description,
+            }),
+        }
+    }
+}
+
+/// An OAuth App registered on GitHub. The client secret is only needed for
+/// the web flow.
+pub struct OAuthApp {
+    client_id: String,
+    client_secret: Option<String>,
+    base_url: Url,
+    scopes: Vec<String>,
+    client: Client,
+}
 
-    let(authorize_url, csrf_state) = client
-        .authorize_url(CsrfToken::new_random)
-        .add_scope(Scope::new("public_repo".to_string()))
-        .add_scope(Scope::new("repo".to_string()))
-        .add_scope(Scope::new("user:email".to_string()))
-        .url();
-
-    let _ = webbrowser::open(&authorize_url.to_string());
-
-    let listener = TcpListener::bind("127.0.0.1:8080").unwrap();
-    for stream in listener.incoming() {
-        if let Ok(mut stream) = stream {
-            let code;
-            let state;
-            {
-                let mut reader = BufReader::new(&stream);
-                let mut request_line = String::new();
-                reader.read_line(&mut request_line).unwrap();
-
-                let redirect_url = request_line.split_whitespace().nth(1).unwrap();
-                let url = Url::parse(&("http://localhost".to_string() + redirect_url)).unwrap();
-
-                let code_pair = url
-                    .query_pairs()
-                    .find(|pair| {
-                        let &(ref key, _) = pair;
-                        key == "code"
-                    })
-                    .unwrap();
-
-                let (_, value) = code_pair;
-                code = AuthorizationCode::new(value.into_owned());
-
-                let state_pair = url
-                    .query_pairs()
-                    .find(|pair| {
-                        let &(ref key, _) = pair;
-                        key == "state"
-                    })
-                    .unwrap();
-                let (_, value) = state_pair;
-                state = CsrfToken::new(value.into_owned());
+impl OAuthApp {
+    pub fn new<T>(client_id: T, client_secret: Option<String>) -> Self
+    where
+        T: ToString,
+    {
+        Self {
+            client_id: client_id.to_string(),
+            client_secret: client_secret,
+            base_url: Url::parse(GITHUB_URL).unwrap(),
+            scopes: DEFAULT_SCOPES.iter().map(|s| s.to_string()).collect(),
+            client: Client::new(),
+        }
+    }
+
+    /// Use the OAuth endpoints of another host, e.g. `https://github.example.com`
+    /// for a GitHub Enterprise Server
+    pub fn set_base_url<U>(&mut self, base_url: U) -> Result<()>
+    where
+        U: AsRef<str>,
+    {
+        self.base_url = Url::parse(base_url.as_ref())?;
+        Ok(())
+    }
+
+    /// Change the scopes the user is asked to grant
+    pub fn set_scopes<S>(&mut self, scopes: &[S])
+    where
+        S: ToString,
+    {
+        self.scopes = scopes.iter().map(|s| s.to_string()).collect();
+    }
+
+    fn post_form<T>(&self, path: &str, form: &[(&str, &str)]) -> Result<T>
+    where
+        T: DeserializeOwned,
+    {
+        let res = self
+            .client
+            .post(url_join(&self.base_url, path)?)
+            .header(ACCEPT, HeaderValue::from_static("application/json"))
+            .header(USER_AGENT, HeaderValue::from_static("bardo-github"))
+            .form(form)
+            .send()?;
+        let (headers, status) = (res.headers().clone(), res.status());
+        let body = res.bytes()?;
+        match parse_response(headers, status, &body)? {
+            (_, _, Some(body)) => Ok(body),
+            (_, status, None) => Err(Error::OAuth {
+                error: "empty_response".to_string(),
+                description: Some(format!("{} answered with an empty body ({})", path, status)),
+            }),
+        }
+    }
+
+    /// Start the device flow. Show `user_code` and `verification_uri` to
+    /// the user and then call `poll_device_token`.
+    pub fn request_device_code(&self) -> Result<DeviceCode> {
+        let scope = self.scopes.join(" ");
+        self.post_form("login/device/code", &[("client_id", &self.client_id), ("scope", &scope)])
+    }
+
+    /// Wait until the user entered the code, declined or the code expired.
+    /// Polls no faster than GitHub asks for.
+    pub fn poll_device_token(&self, code: &DeviceCode) -> Result<AccessToken> {
+        let deadline = Instant::now() + Duration::from_secs(code.expires_in);
+        let mut interval = Duration::from_secs(code.interval);
+        loop {
+            let res: TokenResponse = self.post_form(
+                "login/oauth/access_token",
+                &[
+                    ("client_id", &self.client_id),
+                    ("device_code", &code.device_code),
+                    ("grant_type", DEVICE_GRANT_TYPE),
+                ],
+            )?;
+            match res.error.as_ref().map(String::as_str) {
+                Some("authorization_pending") => {}
+                Some("slow_down") => {
+                    interval = res
+                        .interval
+                        .map(Duration::from_secs)
+                        .unwrap_or(interval + Duration::from_secs(5));
+                }
+                _ => return res.into_token(),
             }
 
-            let message = "Go back to your terminal.";
-            let response = format!(
-                "HTTP/1.1 200 OK\r\ncontent-length: {}\r\n\r\n{}",
-                message.len(),
-                message
-            );
-            stream.write_all(response.as_bytes()).unwrap();
-
-            println!("Github returned the following code:\n{}\n", code.secret());
-            println!(
-                "Github returned the following state:\n{} (expected `{}`)\n",
-                state.secret(),
-                csrf_state.secret()
-            );
-
-            let token_res = client.exchange_code(code).request(http_client);
-            println!("Github returned the following token:\n{:?}\n", token_res);
-
-            if let Ok(token) = token_res {
-                // println!("token: {:#?}", token.access_token().secret());
-                // ghauto_config::credentials::write_access_token(token.access_token().secret());
-                let scopes = if let Some(scopes_vec) = token.scopes() {
-                    scopes_vec
-                        .iter()
-                        .map(|comma_separated| comma_separated.split(','))
-                        .flatten()
-                        .collect::<Vec<_>>()
-                } else {
-                    Vec::new()
-                };
-
-                println!("Github returned the following scopes:\n{:?}\n", scopes);
+            if Instant::now() + interval > deadline {
+                return Err(Error::OAuth {
+                    error: "expired_token".to_string(),
+                    description: Some("the device code expired before it was entered".to_string()),
+                });
+            }
+            thread::sleep(interval);
+        }
+    }
+
+    /// Run the whole device flow, `prompt` shows the code to the user
+    pub fn device_flow<F>(&self, prompt: F) -> Result<AccessToken>
+    where
+        F: FnOnce(&DeviceCode),
+    {
+        let code = self.request_device_code()?;
+        prompt(&code);
+        self.poll_device_token(&code)
+    }
+
+    /// Run the web flow. Listens on `port` of localhost for the redirect,
+    /// which has to match the callback url of the OAuth App, and passes
+    /// the authorization url to `open`. The `state` returned by GitHub is
+    /// checked before the code is exchanged for a token.
+    pub fn web_flow<F>(&self, port: u16, open: F) -> Result<AccessToken>
+    where
+        F: FnOnce(&Url),
+    {
+        let client_secret = self
+            .client_secret
+            .as_ref()
+            .ok_or_else(|| Error::InvalidCredentials("the web flow needs a client secret".to_string()))?;
+
+        let listener = TcpListener::bind(("127.0.0.1", port))
+            .map_err(|e| Error::InvalidRequest(format!("unable to listen on port {}: {}", port, e)))?;
+        let port = listener
+            .local_addr()
+            .map_err(|e| Error::InvalidRequest(format!("unable to listen on port {}: {}", port, e)))?
+            .port();
+        let redirect_url = format!("http://localhost:{}", port);
+        let state: String = rand::thread_rng().sample_iter(&Alphanumeric).take(32).collect();
+
+        let mut authorize_url = url_join(&self.base_url, "login/oauth/authorize")?;
+        authorize_url
+            .query_pairs_mut()
+            .append_pair("client_id", &self.client_id)
+            .append_pair("redirect_uri", &redirect_url)
+            .append_pair("scope", &self.scopes.join(" "))
+            .append_pair("state", &state);
+        open(&authorize_url);
+
+        for stream in listener.incoming() {
+            let mut stream = match stream {
+                Ok(s) => s,
+                Err(_) => continue,
+            };
+            let params = match read_callback(&stream) {
+                Some(params) => params,
+                None => {
+                    // e.g. the browser asking for a favicon
+                    respond(&mut stream, "404 Not Found", "Not found.");
+                    continue;
+                }
+            };
+            let param = |name: &str| params.iter().find(|(k, _)| k == name).map(|(_, v)| v.clone());
+
+            if param("state").as_ref() != Some(&state) {
+                respond(&mut stream, "400 Bad Request", "Login failed, go back to your terminal.");
+                return Err(Error::OAuth {
+                    error: "state_mismatch".to_string(),
+                    description: Some("the redirect does not belong to this login".to_string()),
+                });
             }
+            let code = match (param("code"), param("error")) {
+                (Some(code), None) => code,
+                (_, error) => {
+                    respond(&mut stream, "400 Bad Request", "Login failed, go back to your terminal.");
+                    return Err(Error::OAuth {
+                        error: error.unwrap_or_else(|| "missing_code".to_string()),
+                        description: param("error_description"),
+                    });
+                }
+            };
+            respond(&mut stream, "200 OK", "Go back to your terminal.");
+
+            let res: TokenResponse = self.post_form(
+                "login/oauth/access_token",
+                &[
+                    ("client_id", &self.client_id),
+                    ("client_secret", client_secret),
+                    ("code", &code),
+                    ("redirect_uri", &redirect_url),
+                ],
+            )?;
+            return res.into_token();
+        }
+        Err(Error::InvalidRequest("the callback listener stopped".to_string()))
+    }
+}
+
+/// Open the url in the default browser, failing silently
+pub fn open_browser(url: &Url) {
+    let _ = webbrowser::open(url.as_str());
+}
+
+/// The query parameters of a redirect to the callback listener, `None` if
+/// the request is not a redirect carrying `state`
+fn read_callback(stream: &TcpStream) -> Option<Vec<(String, String)>> {
+    let mut request_line = String::new();
+    BufReader::new(stream).read_line(&mut request_line).ok()?;
+    let path = request_line.split_whitespace().nth(1)?;
+    let url = Url::parse(&format!("http://localhost{}", path)).ok()?;
+
+    let params: Vec<(String, String)> = url.query_pairs().into_owned().collect();
+    if params.iter().any(|(k, _)| k == "state") {
+        Some(params)
+    } else {
+        None
+    }
+}
+
+fn respond(stream: &mut TcpStream, status: &str, message: &str) {
+    let response = format!(
+        "HTTP/1.1 {}\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+        status,
+        message.len(),
+        message
+    );
+    let _ = stream.write_all(response.as_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{serve, Canned};
+    use std::io::Read;
+
+    fn app(url: &str) -> OAuthApp {
+        let mut app = OAuthApp::new("client", Some("secret".to_string()));
+        app.set_base_url(url).unwrap();
+        app
+    }
+
+    fn form(body: &[u8]) -> Vec<(String, String)> {
+        url::form_urlencoded::parse(body).into_owned().collect()
+    }
+
+    #[test]
+    fn device_flow_polls_until_authorized() {
+        let (url, requests) = serve(vec![
+            Canned::json(
+                200,
+                r#"{"device_code": "dc", "user_code": "ABCD-1234", "verification_uri": "https://github.com/login/device", "expires_in": 900, "interval": 0}"#,
+            ),
+            Canned::json(200, r#"{"error": "authorization_pending"}"#),
+            Canned::json(200, r#"{"access_token": "gho_token", "token_type": "bearer", "scope": "repo,user:email"}"#),
+        ]);
+
+        let mut shown = None;
+        let token = app(&url).device_flow(|code| shown = Some(code.user_code.clone())).unwrap();
+        assert_eq!(Some("ABCD-1234".to_string()), shown);
+        assert_eq!("gho_token", token.access_token);
+        assert_eq!(vec!["repo", "user:email"], token.scopes);
+
+        let start = requests.recv().unwrap();
+        assert_eq!("/login/device/code", start.path);
+        assert!(form(&start.body).contains(&("scope".to_string(), "public_repo repo user:email".to_string())));
+        for _ in 0..2 {
+            let poll = requests.recv().unwrap();
+            assert_eq!("/login/oauth/access_token", poll.path);
+            assert!(form(&poll.body).contains(&("device_code".to_string(), "dc".to_string())));
+        }
+    }
+
+    #[test]
+    fn device_flow_reports_denial() {
+        let (url, _) = serve(vec![
+            Canned::json(
+                200,
+                r#"{"device_code": "dc", "user_code": "ABCD-1234", "verification_uri": "https://github.com/login/device", "expires_in": 900, "interval": 0}"#,
+            ),
+            Canned::json(200, r#"{"error": "access_denied", "error_description": "The user has denied your application access."}"#),
+        ]);
+
+        match app(&url).device_flow(|_| {}) {
+            Err(Error::OAuth { error, .. }) => assert_eq!("access_denied", error),
+            _ => panic!("expected the flow to be denied"),
+        }
+    }
+
+    /// Follow the redirect of the authorization url like a browser would,
+    /// optionally replacing the state
+    fn redirect(authorize_url: &Url, state: Option<&str>) {
+        let param = |name: &str| {
+            authorize_url
+                .query_pairs()
+                .find(|(k, _)| k == name)
+                .map(|(_, v)| v.into_owned())
+                .unwrap()
+        };
+        let redirect = Url::parse(&param("redirect_uri")).unwrap();
+        let state = state.map(String::from).unwrap_or_else(|| param("state"));
+
+        let port = redirect.port().unwrap();
+        thread::spawn(move || {
+            let mut stream = TcpStream::connect(("127.0.0.1", port)).unwrap();
+            let request = format!("GET /?code=the_code&state={} HTTP/1.1\r\nhost: localhost\r\n\r\n", state);
+            stream.write_all(request.as_bytes()).unwrap();
+            let _ = stream.read_to_end(&mut Vec::new());
+        });
+    }
+
+    #[test]
+    fn web_flow_exchanges_code() {
+        let (url, requests) = serve(vec![Canned::json(200, r#"{"access_token": "gho_token", "scope": "repo"}"#)]);
+
+        let token = app(&url).web_flow(0, |u| redirect(u, None)).unwrap();
+        assert_eq!("gho_token", token.access_token);
+
+        let exchange = requests.recv().unwrap();
+        let params = form(&exchange.body);
+        assert!(params.contains(&("code".to_string(), "the_code".to_string())));
+        assert!(params.contains(&("client_secret".to_string(), "secret".to_string())));
+    }
+
+    #[test]
+    fn web_flow_checks_state() {
+        let (url, requests) = serve(vec![Canned::json(200, r#"{"access_token": "gho_token"}"#)]);
 
-            break;
+        match app(&url).web_flow(0, |u| redirect(u, Some("forged"))) {
+            Err(Error::OAuth { error, .. }) => assert_eq!("state_mismatch", error),
+            _ => panic!("expected a state mismatch"),
         }
+        assert!(requests.recv_timeout(Duration::from_millis(100)).is_err());
     }
 }
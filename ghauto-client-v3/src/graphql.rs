@@ -0,0 +1,389 @@
+//! Access to the GraphQL API (v4). A single query can select data of many
+//! repositories at once, e.g. with aliases, which saves hundreds of REST
+//! calls for org wide overviews:
+//!
+//! ```ignore
+//! let data: Data = gh
+//!     .graphql("query($owner: String!, $name: String!) { repository(owner: $owner, name: $name) { name } }")
+//!     .variable("owner", "octocat")
+//!     .variable("name", "hello-world")
+//!     .execute()?;
+//! ```
+
+use hyper::header::{HeaderValue, ACCEPT, AUTHORIZATION, CONTENT_TYPE, USER_AGENT};
+use reqwest::blocking::{Body, Request};
+use reqwest::{Method, Url};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::marker::PhantomData;
+use std::vec::IntoIter;
+
+use crate::client::{read_response, Error, Github, Result};
+
+/// A location in the query an error refers to
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct Location {
+    pub line: u32,
+    pub column: u32,
+}
+
+/// An entry of the `errors` array of a GraphQL response
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct GraphqlError {
+    pub message: String,
+    /// GitHub's error type, e.g. `NOT_FOUND` or `RATE_LIMITED`
+    #[serde(rename = "type")]
+    pub kind: Option<String>,
+    #[serde(default)]
+    pub path: Vec<Value>,
+    #[serde(default)]
+    pub locations: Vec<Location>,
+}
+
+impl Display for GraphqlError {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        if !self.path.is_empty() {
+            let path: Vec<String> = self
+                .path
+                .iter()
+                .map(|p| match p {
+                    Value::String(s) => s.clone(),
+                    v => v.to_string(),
+                })
+                .collect();
+            write!(f, "{}: ", path.join("."))?;
+        }
+        write!(f, "{}", self.message)
+    }
+}
+
+#[derive(Deserialize)]
+struct Response<T> {
+    data: Option<T>,
+    #[serde(default)]
+    errors: Vec<GraphqlError>,
+}
+
+/// The pagination info of a connection
+#[derive(Deserialize, Serialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PageInfo {
+    pub has_next_page: bool,
+    pub end_cursor: Option<String>,
+}
+
+/// A page of a connection, select it as
+/// `{ totalCount pageInfo { hasNextPage endCursor } nodes { ... } }`
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Connection<T> {
+    pub total_count: Option<u32>,
+    pub page_info: PageInfo,
+    #[serde(default = "Vec::new")]
+    pub nodes: Vec<T>,
+}
+
+/// The GraphQL endpoint belonging to a REST API root: `/graphql` on
+/// github.com, `/api/graphql` on a GitHub Enterprise Server.
+pub fn graphql_url(api_url: &Url) -> Result<Url> {
+    let mut url = api_url.clone();
+    let path = url.path().trim_end_matches('/').to_string();
+    let path = match path.strip_suffix("/v3") {
+        Some(api) => format!("{}/graphql", api),
+        None => format!("{}/graphql", path),
+    };
+    url.set_path(&path);
+    Ok(url)
+}
+
+/// A GraphQL query together with its variables
+pub struct Query<'g> {
+    github: &'g Github,
+    query: String,
+    variables: Map<String, Value>,
+    error: Option<Error>,
+}
+
+impl Github {
+    /// Begin building up a GraphQL query
+    pub fn graphql<Q>(&self, query: Q) -> Query
+    where
+        Q: ToString,
+    {
+        Query {
+            github: self,
+            query: query.to_string(),
+            variables: Map::new(),
+            error: None,
+        }
+    }
+}
+
+impl<'g> Query<'g> {
+    /// Set a variable of the query
+    pub fn variable<V>(mut self, name: &str, value: V) -> Self
+    where
+        V: Serialize,
+    {
+        match serde_json::to_value(value) {
+            Ok(v) => {
+                self.variables.insert(name.to_string(), v);
+            }
+            Err(e) => self.error = Some(Error::InvalidRequest(format!("invalid value for ${}: {}", name, e))),
+        }
+        self
+    }
+
+    fn request(&self) -> Result<Request> {
+        let url = graphql_url(self.github.get_api_url())?;
+        let body = serde_json::json!({"query": self.query, "variables": self.variables});
+        let body = serde_json::to_vec(&body).map_err(|e| Error::InvalidRequest(format!("unable to serialize query: {}", e)))?;
+
+        let mut req = Request::new(Method::POST, url);
+        let headers = req.headers_mut();
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+        headers.insert(USER_AGENT, HeaderValue::from_static("bardo-github"));
+        headers.insert(ACCEPT, HeaderValue::from_static("application/json"));
+        headers.insert(AUTHORIZATION, HeaderValue::from_str(&format!("bearer {}", self.github.get_token()))?);
+        *req.body_mut() = Some(Body::from(body));
+        Ok(req)
+    }
+
+    /// Send the query and deserialize `data`. GraphQL reports most errors
+    /// with `200 OK`, they are returned as `Error::Graphql`.
+    pub fn execute<T>(self) -> Result<T>
+    where
+        T: DeserializeOwned,
+    {
+        match self.execute_partial()? {
+            (data, errors) if errors.is_empty() => Ok(data),
+            (_, errors) => Err(Error::Graphql { errors: errors }),
+        }
+    }
+
+    /// Send the query and deserialize `data` even if some fields failed.
+    /// GitHub answers a query selecting a missing repository under an
+    /// alias with the data of the other aliases, the missing one `null`,
+    /// and a `NOT_FOUND` error; both are returned. Fails with
+    /// `Error::Graphql` only if there is no data at all.
+    pub fn execute_partial<T>(mut self) -> Result<(T, Vec<GraphqlError>)>
+    where
+        T: DeserializeOwned,
+    {
+        if let Some(e) = self.error.take() {
+            return Err(e);
+        }
+
+        let res = self.github.send(self.request()?)?;
+        let (_, status, body) = read_response::<Response<T>>(res)?;
+        match body {
+            Some(Response { data: Some(data), errors }) => Ok((data, errors)),
+            Some(Response { errors, .. }) if !errors.is_empty() => Err(Error::Graphql { errors: errors }),
+            _ => Err(Error::Graphql {
+                errors: vec![GraphqlError {
+                    message: format!("the response ({}) contains no data", status),
+                    kind: None,
+                    path: Vec::new(),
+                    locations: Vec::new(),
+                }],
+            }),
+        }
+    }
+
+    /// Iterate over all nodes of a connection. The query has to declare the
+    /// variable `cursor_variable` (e.g. `$after: String`) and pass it as
+    /// `after` argument of the connection, `connection` picks the
+    /// connection out of the deserialized data.
+    pub fn paginate<D, T, F>(self, cursor_variable: &str, connection: F) -> CursorPaginator<'g, D, T, F>
+    where
+        D: DeserializeOwned,
+        T: DeserializeOwned,
+        F: Fn(D) -> Option<Connection<T>>,
+    {
+        CursorPaginator {
+            query: Some(self),
+            cursor_variable: cursor_variable.to_string(),
+            connection: connection,
+            nodes: Vec::new().into_iter(),
+            total_count: None,
+            data: PhantomData,
+        }
+    }
+}
+
+/// Iterates over the nodes of a connection, requesting the next page with
+/// `pageInfo.endCursor` until `hasNextPage` is false or an error occurs
+pub struct CursorPaginator<'g, D, T, F> {
+    query: Option<Query<'g>>,
+    cursor_variable: String,
+    connection: F,
+    nodes: IntoIter<T>,
+    total_count: Option<u32>,
+    data: PhantomData<D>,
+}
+
+impl<'g, D, T, F> CursorPaginator<'g, D, T, F> {
+    /// The total number of nodes if the query selects `totalCount`
+    pub fn total_count(&self) -> Option<u32> {
+        self.total_count
+    }
+}
+
+impl<'g, D, T, F> Iterator for CursorPaginator<'g, D, T, F>
+where
+    D: DeserializeOwned,
+    T: DeserializeOwned,
+    F: Fn(D) -> Option<Connection<T>>,
+{
+    type Item = Result<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(node) = self.nodes.next() {
+                return Some(Ok(node));
+            }
+
+            let query = self.query.take()?;
+            let github = query.github;
+            let graphql = query.query.clone();
+            let mut variables = query.variables.clone();
+
+            let page = match query.execute::<D>() {
+                Ok(data) => (self.connection)(data),
+                Err(e) => return Some(Err(e)),
+            };
+            let page = match page {
+                Some(page) => page,
+                None => return None,
+            };
+
+            self.total_count = page.total_count.or(self.total_count);
+            if let (true, Some(cursor)) = (page.page_info.has_next_page, page.page_info.end_cursor) {
+                variables.insert(self.cursor_variable.clone(), Value::String(cursor));
+                self.query = Some(Query {
+                    github: github,
+                    query: graphql,
+                    variables: variables,
+                    error: None,
+                });
+            }
+            self.nodes = page.nodes.into_iter();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{serve, Canned};
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Issue {
+        number: u32,
+    }
+
+    #[derive(Deserialize)]
+    struct Repository {
+        issues: Connection<Issue>,
+    }
+
+    #[derive(Deserialize)]
+    struct Data {
+        repository: Option<Repository>,
+    }
+
+    #[test]
+    fn graphql_urls() {
+        let url = graphql_url(&Url::parse("https://api.github.com").unwrap()).unwrap();
+        assert_eq!("https://api.github.com/graphql", url.as_str());
+
+        let url = graphql_url(&Url::parse("https://ghe.example.com/api/v3/").unwrap()).unwrap();
+        assert_eq!("https://ghe.example.com/api/graphql", url.as_str());
+    }
+
+    #[test]
+    fn posts_query_with_variables() {
+        let (url, requests) = serve(vec![Canned::json(200, r#"{"data": {"viewer": {"login": "octocat"}}}"#)]);
+        let github = Github::with_api_url("token", &url).unwrap();
+
+        let data: Value = github
+            .graphql("query($n: Int!) { viewer { login } }")
+            .variable("n", 3)
+            .execute()
+            .unwrap();
+        assert_eq!("octocat", data["viewer"]["login"]);
+
+        let req = requests.recv().unwrap();
+        assert_eq!("POST", req.method);
+        assert_eq!("/graphql", req.path);
+        assert_eq!(Some("bearer token"), req.header("authorization"));
+        let body: Value = serde_json::from_slice(&req.body).unwrap();
+        assert_eq!(3, body["variables"]["n"]);
+    }
+
+    #[test]
+    fn errors_are_typed() {
+        let (url, _) = serve(vec![Canned::json(
+            200,
+            r#"{"data": {"repository": null}, "errors": [{"type": "NOT_FOUND", "path": ["repository"], "locations": [{"line": 1, "column": 3}], "message": "Could not resolve to a Repository"}]}"#,
+        )]);
+        let github = Github::with_api_url("token", &url).unwrap();
+
+        match github.graphql("{ repository(owner: \"o\", name: \"gone\") { id } }").execute::<Data>() {
+            Err(Error::Graphql { errors }) => {
+                assert_eq!(Some("NOT_FOUND".to_string()), errors[0].kind);
+                assert_eq!("repository: Could not resolve to a Repository", errors[0].to_string());
+            }
+            _ => panic!("expected a GraphQL error"),
+        }
+    }
+
+    #[test]
+    fn partial_data_comes_with_errors() {
+        let (url, _) = serve(vec![Canned::json(
+            200,
+            r#"{"data": {"r0": {"name": "a"}, "r1": null, "r2": {"name": "c"}}, "errors": [{"type": "NOT_FOUND", "path": ["r1"], "message": "Could not resolve to a Repository"}]}"#,
+        )]);
+        let github = Github::with_api_url("token", &url).unwrap();
+
+        let (data, errors) = github
+            .graphql("{ r0: repository(owner: \"o\", name: \"a\") { name } r1: repository(owner: \"o\", name: \"gone\") { name } r2: repository(owner: \"o\", name: \"c\") { name } }")
+            .execute_partial::<std::collections::HashMap<String, Option<Value>>>()
+            .unwrap();
+        assert_eq!("a", data["r0"].as_ref().unwrap()["name"]);
+        assert_eq!(None, data["r1"]);
+        assert_eq!("c", data["r2"].as_ref().unwrap()["name"]);
+        assert_eq!(1, errors.len());
+        assert_eq!(Some("NOT_FOUND".to_string()), errors[0].kind);
+    }
+
+    #[test]
+    fn follows_cursors() {
+        let (url, requests) = serve(vec![
+            Canned::json(
+                200,
+                r#"{"data": {"repository": {"issues": {"totalCount": 3, "pageInfo": {"hasNextPage": true, "endCursor": "c1"}, "nodes": [{"number": 1}, {"number": 2}]}}}}"#,
+            ),
+            Canned::json(
+                200,
+                r#"{"data": {"repository": {"issues": {"totalCount": 3, "pageInfo": {"hasNextPage": false, "endCursor": "c2"}, "nodes": [{"number": 3}]}}}}"#,
+            ),
+        ]);
+        let github = Github::with_api_url("token", &url).unwrap();
+
+        let mut issues = github
+            .graphql("query($after: String) { repository(owner: \"o\", name: \"r\") { issues(first: 2, after: $after) { totalCount pageInfo { hasNextPage endCursor } nodes { number } } } }")
+            .paginate("after", |data: Data| data.repository.map(|r| r.issues));
+        let numbers: Vec<u32> = issues.by_ref().map(|i| i.unwrap().number).collect();
+        assert_eq!(vec![1, 2, 3], numbers);
+        assert_eq!(Some(3), issues.total_count());
+
+        let first: Value = serde_json::from_slice(&requests.recv().unwrap().body).unwrap();
+        assert!(first["variables"].get("after").is_none());
+        let second: Value = serde_json::from_slice(&requests.recv().unwrap().body).unwrap();
+        assert_eq!("c1", second["variables"]["after"]);
+    }
+}
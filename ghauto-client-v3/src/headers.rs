@@ -1,53 +1,116 @@
 use reqwest::header::HeaderMap;
+use reqwest::Url;
 use std::collections::HashMap;
 
-use regex::Regex;
-
 pub type Links = HashMap<String, HashMap<String, String>>;
 
-/// Extract however many requests the authenticated user can
-/// make from the Headers
+/// Parse the `Link` header used for pagination into a map from relation
+/// (`next`, `last`, ...) to the url (key `link`), the link parameters and
+/// the query parameters of the url. Malformed entries are skipped.
 pub fn link(head: &HeaderMap) -> Option<Links> {
-    head.get("link").map(|l| parse(l.to_str().unwrap_or("")))
+    head.get("link")
+        .and_then(|l| l.to_str().ok())
+        .map(parse)
+}
+
+/// When the token expires as reported in `GitHub-Authentication-Token-Expiration`,
+/// e.g. `2030-01-01 00:00:00 UTC`. Tokens without an expiry send no header.
+pub fn token_expiration(head: &HeaderMap) -> Option<String> {
+    head.get("github-authentication-token-expiration")
+        .and_then(|e| e.to_str().ok())
+        .map(String::from)
 }
 
 fn parse(link_header: &str) -> Links {
     let mut result: Links = HashMap::new();
-    let re = Regex::new(r#"[<>"\s]"#).unwrap();
-    let preprocessed = re.replace_all(link_header, "");
-    let splited = preprocessed.split(',');
 
-    for s in splited {
-        let mut link_vec: Vec<&str> = s.split(";").collect();
-        link_vec.reverse();
+    for entry in split_entries(link_header) {
+        let entry = entry.trim();
+        let (target, params) = match (entry.find('<'), entry.find('>')) {
+            (Some(0), Some(end)) => (&entry[1..end], &entry[end + 1..]),
+            _ => continue,
+        };
+        let url_parsed = match Url::parse(target.trim()) {
+            Ok(u) => u,
+            Err(_) => continue,
+        };
 
-        let link_val = link_vec.pop().unwrap();
-        let url_parsed = reqwest::Url::parse(link_val).unwrap();
-        let query_pairs = url_parsed.query_pairs();
-
-        let mut rel_val = String::from("");
+        let mut rels: Vec<String> = Vec::new();
         let mut map: HashMap<String, String> = HashMap::new();
 
-        for param in link_vec {
-            let param_kv: Vec<&str> = param.split("=").collect();
-            let key = param_kv[0];
-            let val = param_kv[1];
+        for param in params.split(';').map(str::trim).filter(|p| !p.is_empty()) {
+            let mut param_kv = param.splitn(2, '=');
+            let key = param_kv.next().unwrap_or("").trim();
+            let val = param_kv.next().unwrap_or("").trim().trim_matches('"');
+            if key.is_empty() {
+                continue;
+            }
 
             if key == "rel" {
-                rel_val = val.to_string();
+                rels = val.split_whitespace().map(str::to_string).collect();
             }
 
             map.insert(key.to_string(), val.to_string());
         }
 
-        for pair in query_pairs {
+        for pair in url_parsed.query_pairs() {
             map.insert(pair.0.to_string(), pair.1.to_string());
         }
 
-        map.insert("link".to_string(), link_val.to_string());
+        map.insert("link".to_string(), target.trim().to_string());
 
-        result.insert(rel_val, map);
+        for rel in rels {
+            result.insert(rel, map.clone());
+        }
     }
 
     result
 }
+
+/// Split a header value on the commas separating links, ignoring commas
+/// inside of `<...>` and quoted strings
+fn split_entries(header: &str) -> Vec<&str> {
+    let mut entries = Vec::new();
+    let mut in_url = false;
+    let mut in_quotes = false;
+    let mut start = 0;
+
+    for (i, c) in header.char_indices() {
+        match c {
+            '<' if !in_quotes => in_url = true,
+            '>' if !in_quotes => in_url = false,
+            '"' if !in_url => in_quotes = !in_quotes,
+            ',' if !in_url && !in_quotes => {
+                entries.push(&header[start..i]);
+                start = i + 1;
+            }
+            _ => (),
+        }
+    }
+    entries.push(&header[start..]);
+    entries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_github_links() {
+        let links = parse(
+            r#"<https://api.github.com/repositories/1/issues?page=2>; rel="next", <https://api.github.com/repositories/1/issues?page=5&labels=a,b>; rel="last""#,
+        );
+        assert_eq!("2", links["next"]["page"]);
+        assert_eq!("https://api.github.com/repositories/1/issues?page=2", links["next"]["link"]);
+        assert_eq!("5", links["last"]["page"]);
+        assert_eq!("a,b", links["last"]["labels"]);
+    }
+
+    #[test]
+    fn skip_malformed_links() {
+        let links = parse(r#"garbage, <not a url>; rel="prev", <https://api.github.com/x?page=3>; rel="next", ;;"#);
+        assert_eq!(1, links.len());
+        assert_eq!("3", links["next"]["page"]);
+        assert!(parse("").is_empty());
+    }
+}
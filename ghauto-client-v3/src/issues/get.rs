@@ -1,6 +1,7 @@
 imports!();
 
 use crate::client::GetQueryBuilder;
+use crate::params::{Direction, IssueFilter, IssueSort, State};
 
 new_type!(
     Issues
@@ -16,4 +17,16 @@ impl_macro!(
         |
 );
 
+query_params!(
+    @Issues
+        |&> filter: IssueFilter = "filter"
+        |&> state: State = "state"
+        |&> labels: &[&str] = "labels"
+        |&> since: &str = "since"
+        |&> sort: IssueSort = "sort"
+        |&> direction: Direction = "direction"
+        |&> per_page: u32 = "per_page"
+);
+
 exec!(Issues);
+paginate!(Issues);
@@ -7,34 +7,50 @@
 
 #[macro_use]
 extern crate serde_derive;
-#[macro_use]
-extern crate error_chain;
 
 extern crate bytes;
 extern crate hyper;
-extern crate oauth2;
 extern crate reqwest;
 extern crate serde;
 extern crate serde_json;
+#[cfg(feature = "async")]
 extern crate tokio;
 extern crate webbrowser;
 extern crate dirs_sys;
 extern crate toml;
-extern crate regex;
+extern crate url;
+extern crate rand;
+extern crate sha2;
+extern crate jsonwebtoken;
+extern crate chrono;
 
 extern crate ghauto_config;
 
 #[macro_use]
 mod macros;
 mod util;
+#[cfg(test)]
+mod mock;
 
+pub mod app;
+pub mod cache;
 pub mod client;
+#[cfg(feature = "async")]
+pub mod asynchronous;
 pub mod errors;
 pub mod gh_auth;
+pub mod graphql;
 pub mod headers;
+pub mod pagination;
+pub mod params;
+pub mod ratelimit;
+pub mod retry;
+pub mod scopes;
 
 pub mod users;
 pub mod repos;
 pub mod issues;
+pub mod orgs;
 
+pub use errors::Error;
 pub use hyper::{HeaderMap, StatusCode};
@@ -10,7 +10,7 @@ macro_rules! from {
             fn from(f: $f<'g>) -> Self {
                 Self {
                     request: f.request,
-                    client: f.client,
+                    github: f.github,
                     parameter: None,
                 }
             }
@@ -18,8 +18,6 @@ macro_rules! from {
         )*$(
         impl <'g> From<$f<'g>> for $i1<'g> {
             fn from(mut f: $f<'g>) -> Self {
-                use std::str::FromStr;
-
                 // This is borrow checking abuse and about the only
                 // time I'd do is_ok(). Essentially this allows us
                 // to either pass the error message along or update
@@ -27,48 +25,21 @@ macro_rules! from {
                 if f.request.is_ok() {
                     // We've checked that this works
                     let mut req = f.request.unwrap();
-                    let url = f.parameter
-                        .ok_or("Expecting parameter".into())
-                        .and_then(|param| {
-                            let sep =
-                                if req
-                                    .get_mut()
-                                    .url()
-                                    .query()
-                                    .is_some() { "&" } else { "?" };
-                            reqwest::Url::from_str(
-                                &format!("{}{}{}={}",
-                                    req.get_mut().url(),
-                                    sep,
-                                    $e1,
-                                    param
-                                )
-                            ).map_err(|_| "Failed to parse Url")
-                        });
-                    match url {
-                        Ok(u) => {
-                            *req.get_mut().url_mut() = u;
+                    match f.parameter {
+                        Some(param) => {
+                            $crate::pagination::set_query_param(req.get_mut().url_mut(), $e1, &param);
                             f.request = Ok(req);
                         },
-                        Err(_) => {
-                            f.request = Err(Box::new(std::io::Error::new(std::io::ErrorKind::InvalidData, "Failed to parse Url")));
+                        None => {
+                            f.request = Err($crate::errors::Error::InvalidRequest("expecting parameter".to_string()));
                         }
                     }
+                }
 
-                    Self {
-                        request: f.request,
-                        client: f.client,
-                        parameter: None,
-                    }
-
-                } else {
-
-                    Self {
-                        request: f.request,
-                        client: f.client,
-                        parameter: None,
-                    }
-
+                Self {
+                    request: f.request,
+                    github: f.github,
+                    parameter: None,
                 }
             }
         }
@@ -95,7 +66,7 @@ macro_rules! from {
 
                     Self {
                         request: f.request,
-                        client: f.client,
+                        github: f.github,
                         parameter: None,
                     }
 
@@ -103,7 +74,7 @@ macro_rules! from {
 
                     Self {
                         request: f.request,
-                        client: f.client,
+                        github: f.github,
                         parameter: None,
                     }
 
@@ -120,26 +91,21 @@ macro_rules! from {
                     "GET" => Method::GET,
                     "POST" => Method::POST,
                     "PUT" => Method::PUT,
+                    "PATCH" => Method::PATCH,
                     "DELETE" => Method::DELETE,
                     "OPTIONS" => Method::OPTIONS,
                     _ => Method::GET,
                 };
 
                 use hyper::header::{ ACCEPT, AUTHORIZATION, CONTENT_TYPE, USER_AGENT };
-                // let url_str = "http://jsonplaceholder.typicode.com/users";
-                let url_str = "https://api.github.com";
-                let url = Url::parse(url_str).unwrap();
-                let request = Request::new(method, url);
-
-                let client = Client::new();
 
-                let res = client.execute(request.try_clone().unwrap()).and_then(|_| {
-                    let token = String::from("token ") + &gh.token;
-                    Ok((request, HeaderValue::from_str(&token).unwrap()))
-                });
+                // Building a request is purely local, nothing is sent until
+                // the query is executed through the shared client.
+                let mut req = Request::new(method, gh.api_url.clone());
+                let token = HeaderValue::from_str(&(String::from("token ") + &gh.get_token()));
 
-                match res {
-                    Ok((mut req, token)) => {
+                match token {
+                    Ok(token) => {
                         {
                             let headers = req.headers_mut();
                             headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
@@ -149,14 +115,14 @@ macro_rules! from {
                         }
                         Self {
                             request: Ok(RefCell::new(req)),
-                            client: &gh.client,
+                            github: gh,
                             parameter: None,
                         }
                     }
                     Err(err) => {
                         Self {
-                            request: Err(Box::new(err)),
-                            client: &gh.client,
+                            request: Err(err.into()),
+                            github: gh,
                             parameter: None,
                         }
                     }
@@ -177,7 +143,7 @@ macro_rules! new_type {
         $(
         pub struct $i<'g> {
             pub(crate) request: Result<RefCell<Request>>,
-            pub(crate) client: &'g Rc<Client>,
+            pub(crate) github: &'g Github,
             pub(crate) parameter: Option<String>,
         }
         )*
@@ -194,13 +160,37 @@ macro_rules! exec {
                 let req = self.request?.into_inner();
                 // Sometimes this stupid debug hack is useful
                 // println!("result {:?}", req);
-                let res = self.client.execute(req)?;
-                let headers = res.headers().clone();
-                let status: StatusCode = StatusCode::from(res.status());
-                match res.json() {
-                    Ok(d) => Ok((headers, status, d)),
-                    Err(_) =>  Ok((headers, status, None)),
-                }
+                let res = self.github.send(req)?;
+                $crate::client::read_response(res)
+            }
+        }
+
+        #[cfg(feature = "async")]
+        impl<'a> $crate::asynchronous::AsyncExecutor<'a> for $t1<'a> {
+            fn execute_async<T>(self) -> $crate::asynchronous::BoxFuture<'a, Result<(HeaderMap, StatusCode, Option<T>)>>
+            where
+                T: DeserializeOwned + Send + 'a,
+            {
+                let github = self.github;
+                let request = self.request;
+                Box::pin(async move {
+                    let res = github.send_async(request?.into_inner()).await?;
+                    $crate::asynchronous::read_response(res).await
+                })
+            }
+        }
+    };
+}
+
+/// Make a list endpoint iterable over the items of all of its pages
+macro_rules! paginate {
+    ($t1:ident) => {
+        impl<'a> $crate::pagination::Paginate<'a> for $t1<'a> {
+            fn paginate<T>(self) -> $crate::pagination::Paginator<'a, T>
+            where
+                T: DeserializeOwned,
+            {
+                $crate::pagination::Paginator::new(self.github, self.request.map(RefCell::into_inner))
             }
         }
     };
@@ -210,7 +200,8 @@ macro_rules! exec {
 /// that creates all the functions to transition from one node type to another
 macro_rules! impl_macro {
     ($(@$i: ident $(|=> $id1: ident -> $t1: ident)*|
-     $(|=> $id2: ident -> $t2: ident = $e2: ident)*
+     $(|=> $id2: ident -> $t2: ident = $e2: ident $(where $v2: path)?)*
+     $(|/> $id4: ident -> $t4: ident = $e4: ident)*
      $(|?> $id3: ident -> $t3: ident = $e3: ident)*)+
     )=> (
         $(
@@ -220,6 +211,7 @@ macro_rules! impl_macro {
                     self.into()
                 }
             )*$(
+                /// Appends the value as a single, percent-encoded path segment
                 pub fn $id2(mut self, $e2: &str) -> $t2<'g> {
                     // This is borrow checking abuse and about the only
                     // time I'd do is_ok(). Essentially this allows us
@@ -228,14 +220,34 @@ macro_rules! impl_macro {
                     if self.request.is_ok() {
                         // We've checked that this works
                         let mut req = self.request.unwrap();
-                        let url = url_join(req.borrow().url(), $e2);
+                        let valid: Result<()> = Ok(());
+                        $(let valid = valid.and_then(|_| $v2(req.borrow().url(), $e2));)?
+                        let url = valid.and_then(|_| $crate::util::url_push_segment(req.borrow().url(), $e2));
+                        match url {
+                            Ok(u) => {
+                                *req.get_mut().url_mut() = u;
+                                self.request = Ok(req);
+                            },
+                            Err(e) => {
+                                self.request = Err(e);
+                            }
+                        }
+                    }
+                    self.into()
+                }
+            )*$(
+                /// Appends a `/` separated path, every segment percent-encoded
+                pub fn $id4(mut self, $e4: &str) -> $t4<'g> {
+                    if self.request.is_ok() {
+                        let mut req = self.request.unwrap();
+                        let url = $crate::util::url_push_path(req.borrow().url(), $e4);
                         match url {
                             Ok(u) => {
                                 *req.get_mut().url_mut() = u;
                                 self.request = Ok(req);
                             },
                             Err(e) => {
-                                self.request = Err(e.into());
+                                self.request = Err(e);
                             }
                         }
                     }
@@ -252,6 +264,30 @@ macro_rules! impl_macro {
     );
 }
 
+/// Generate chainable setters for the query parameters of a list endpoint.
+/// Each setter replaces a previous value of the same parameter, values are
+/// converted with `QueryValue` and percent-encoded.
+macro_rules! query_params {
+    ($(@$i: ident $(|&> $id: ident: $t: ty = $key: tt)*)+) => (
+        $(
+            impl<'g> $i<'g> {
+            $(
+                pub fn $id(mut self, $id: $t) -> Self {
+                    if let Ok(req) = self.request.as_mut() {
+                        $crate::pagination::set_query_param(
+                            req.get_mut().url_mut(),
+                            $key,
+                            &$crate::params::QueryValue::to_query_value(&$id),
+                        );
+                    }
+                    self
+                }
+            )*
+            }
+        )+
+    );
+}
+
 /// A variation of `impl_macro` for the client module that allows partitioning of
 /// types. Create a function with a given name and return type. Used for
 /// creating functions for simple conversions from one type to another, where
@@ -292,15 +328,14 @@ macro_rules! imports {
     () => {
         use crate::util::url_join;
 
-        use reqwest::blocking::{Client, Request};
+        use reqwest::blocking::Request;
 
         use hyper::{HeaderMap, StatusCode};
         use serde::de::DeserializeOwned;
 
         use std::cell::RefCell;
-        use std::rc::Rc;
 
         use $crate::client::Result;
-        use $crate::client::Executor;
+        use $crate::client::{Executor, Github};
     };
 }
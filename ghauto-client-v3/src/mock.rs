@@ -0,0 +1,136 @@
+//! A tiny HTTP/1.1 server standing in for the GitHub API in tests.
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::sync::mpsc::{channel, Receiver};
+use std::thread;
+
+/// A request as seen by the mock server
+#[derive(Debug)]
+pub struct Recorded {
+    pub method: String,
+    pub path: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl Recorded {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+/// A canned response, served verbatim apart from `content-length`
+pub struct Canned {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl Canned {
+    pub fn json(status: u16, body: &str) -> Self {
+        Self {
+            status: status,
+            headers: vec![("content-type".to_string(), "application/json".to_string())],
+            body: body.to_string(),
+        }
+    }
+
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+}
+
+/// Start a server on a free local port that answers each connection with the
+/// next canned response; the last one is repeated. `{base}` in headers and
+/// bodies is replaced by the base url of the server. Returns the base url and
+/// a receiver yielding every request received.
+pub fn serve(responses: Vec<Canned>) -> (String, Receiver<Recorded>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (tx, rx) = channel();
+    let base = format!("http://{}", addr);
+    let responses: Vec<Canned> = responses
+        .into_iter()
+        .map(|c| Canned {
+            status: c.status,
+            headers: c
+                .headers
+                .into_iter()
+                .map(|(k, v)| (k, v.replace("{base}", &base)))
+                .collect(),
+            body: c.body.replace("{base}", &base),
+        })
+        .collect();
+
+    thread::spawn(move || {
+        let mut i = 0;
+        for stream in listener.incoming() {
+            let mut stream = match stream {
+                Ok(s) => s,
+                Err(_) => continue,
+            };
+            let recorded = match read_request(&mut stream) {
+                Some(r) => r,
+                None => continue,
+            };
+            let _ = tx.send(recorded);
+
+            let canned = &responses[i.min(responses.len() - 1)];
+            i += 1;
+            let mut response = format!("HTTP/1.1 {} Mock\r\n", canned.status);
+            for (k, v) in canned.headers.iter() {
+                response.push_str(&format!("{}: {}\r\n", k, v));
+            }
+            response.push_str(&format!(
+                "content-length: {}\r\nconnection: close\r\n\r\n{}",
+                canned.body.len(),
+                canned.body
+            ));
+            let _ = stream.write_all(response.as_bytes());
+        }
+    });
+
+    (base, rx)
+}
+
+fn read_request<R: Read>(stream: R) -> Option<Recorded> {
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+    reader.read_line(&mut line).ok()?;
+    let mut parts = line.split_whitespace();
+    let method = parts.next()?.to_string();
+    let path = parts.next()?.to_string();
+
+    let mut headers = Vec::new();
+    loop {
+        let mut header = String::new();
+        reader.read_line(&mut header).ok()?;
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        let mut kv = header.splitn(2, ':');
+        let k = kv.next()?.trim().to_string();
+        let v = kv.next().unwrap_or("").trim().to_string();
+        headers.push((k, v));
+    }
+
+    let len = headers
+        .iter()
+        .find(|(k, _)| k.eq_ignore_ascii_case("content-length"))
+        .and_then(|(_, v)| v.parse::<usize>().ok())
+        .unwrap_or(0);
+    let mut body = vec![0; len];
+    reader.read_exact(&mut body).ok()?;
+
+    Some(Recorded {
+        method: method,
+        path: path,
+        headers: headers,
+        body: body,
+    })
+}
@@ -0,0 +1,36 @@
+imports!();
+use crate::client::GetQueryBuilder;
+use crate::util::validate_owner;
+
+new_type!(
+    Org
+    Orgs
+    Repos
+);
+
+from!(
+    @GetQueryBuilder
+        -> Orgs = "orgs"
+    @Orgs
+        => Org
+    @Org
+        -> Repos = "repos"
+);
+
+impl_macro!(
+    @Orgs
+        |
+        |=> org -> Org = org_str where validate_owner
+    @Org
+        |=> repos -> Repos
+        |
+);
+
+query_params!(
+    @Repos
+        |&> per_page: u32 = "per_page"
+);
+
+exec!(Org);
+exec!(Repos);
+paginate!(Repos);
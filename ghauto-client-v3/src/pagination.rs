@@ -0,0 +1,197 @@
+use reqwest::blocking::Request;
+use reqwest::Url;
+use serde::de::DeserializeOwned;
+
+use std::vec::IntoIter;
+
+use crate::client::{read_response, Github, Result};
+use crate::headers;
+
+/// Implemented by every list endpoint, turns the query into an iterator
+/// over all items of all pages.
+pub trait Paginate<'g> {
+    fn paginate<T>(self) -> Paginator<'g, T>
+    where
+        T: DeserializeOwned;
+}
+
+/// Iterates over the items of a list endpoint, page by page. The next page
+/// is requested lazily by following the `rel="next"` url of the `Link`
+/// header, so the iterator stops after the last page or the first error.
+pub struct Paginator<'g, T> {
+    github: &'g Github,
+    next: Option<Result<Request>>,
+    items: IntoIter<T>,
+    page: Option<u32>,
+    last_page: Option<u32>,
+}
+
+impl<'g, T> Paginator<'g, T>
+where
+    T: DeserializeOwned,
+{
+    pub(crate) fn new(github: &'g Github, request: Result<Request>) -> Self {
+        Self {
+            github: github,
+            next: Some(request),
+            items: Vec::new().into_iter(),
+            page: None,
+            last_page: None,
+        }
+    }
+
+    /// The number of the page fetched last, `None` before the first request
+    pub fn page(&self) -> Option<u32> {
+        self.page
+    }
+
+    /// The number of the last page as announced by GitHub, useful for
+    /// progress reporting. `None` before the first request or if the
+    /// endpoint does not announce it.
+    pub fn last_page(&self) -> Option<u32> {
+        self.last_page
+    }
+
+    fn fetch(&mut self, request: Request) -> Result<Vec<T>> {
+        let next = request.try_clone();
+        let page = page_of(request.url()).unwrap_or(1);
+
+        let res = self.github.send(request)?;
+        let (headers, _, items) = read_response::<Vec<T>>(res)?;
+        let links = headers::link(&headers).unwrap_or_default();
+
+        self.page = Some(page);
+        self.last_page = links
+            .get("last")
+            .and_then(|l| l.get("page"))
+            .and_then(|p| p.parse().ok())
+            .or(self.last_page.filter(|l| *l >= page));
+
+        let next_url = links.get("next").and_then(|l| l.get("link"));
+        match (next, next_url) {
+            (Some(mut req), Some(url)) => {
+                *req.url_mut() = Url::parse(url)?;
+                self.next = Some(Ok(req));
+            }
+            _ => self.last_page = Some(page),
+        }
+
+        Ok(items.unwrap_or_default())
+    }
+}
+
+impl<'g, T> Iterator for Paginator<'g, T>
+where
+    T: DeserializeOwned,
+{
+    type Item = Result<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(item) = self.items.next() {
+                return Some(Ok(item));
+            }
+
+            let items = match self.next.take()? {
+                Ok(request) => self.fetch(request),
+                Err(e) => Err(e),
+            };
+
+            match items {
+                Ok(items) => self.items = items.into_iter(),
+                Err(e) => return Some(Err(e)),
+            }
+        }
+    }
+}
+
+fn page_of(url: &Url) -> Option<u32> {
+    url.query_pairs()
+        .find(|(k, _)| k == "page")
+        .and_then(|(_, v)| v.parse().ok())
+}
+
+/// Replace or append a query parameter, percent-encoding the value
+pub(crate) fn set_query_param(url: &mut Url, key: &str, value: &str) {
+    let pairs: Vec<(String, String)> = url
+        .query_pairs()
+        .filter(|(k, _)| k != key)
+        .map(|(k, v)| (k.into_owned(), v.into_owned()))
+        .collect();
+
+    url.query_pairs_mut()
+        .clear()
+        .extend_pairs(pairs)
+        .append_pair(key, value);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{serve, Canned};
+
+    fn issues(github: &Github) -> Paginator<u32> {
+        github
+            .get()
+            .repos()
+            .owner("octocat")
+            .repo("hello-world")
+            .issues()
+            .paginate::<u32>()
+    }
+
+    #[test]
+    fn follows_next_links() {
+        let (url, requests) = serve(vec![
+            Canned::json(200, "[1, 2]").header(
+                "link",
+                r#"<{base}/repositories/1/issues?per_page=2&page=2>; rel="next", <{base}/repositories/1/issues?per_page=2&page=2>; rel="last""#,
+            ),
+            Canned::json(200, "[3]").header(
+                "link",
+                r#"<{base}/repositories/1/issues?per_page=2&page=1>; rel="prev", <{base}/repositories/1/issues?per_page=2&page=1>; rel="first""#,
+            ),
+        ]);
+        let github = Github::with_api_url("token", &url).unwrap();
+        // the page size is set on the builder, the next links keep it
+        let mut paginator = github
+            .get()
+            .repos()
+            .owner("octocat")
+            .repo("hello-world")
+            .issues()
+            .per_page(2)
+            .paginate::<u32>();
+        assert_eq!(None, paginator.last_page());
+
+        assert_eq!(1, paginator.next().unwrap().unwrap());
+        assert_eq!(Some(1), paginator.page());
+        assert_eq!(Some(2), paginator.last_page());
+
+        let rest: Vec<u32> = paginator.by_ref().map(|i| i.unwrap()).collect();
+        assert_eq!(vec![2, 3], rest);
+        assert_eq!(Some(2), paginator.page());
+        assert_eq!(Some(2), paginator.last_page());
+
+        assert_eq!("/repos/octocat/hello-world/issues?per_page=2", requests.recv().unwrap().path);
+        assert_eq!("/repositories/1/issues?per_page=2&page=2", requests.recv().unwrap().path);
+        assert!(requests.recv_timeout(std::time::Duration::from_millis(200)).is_err());
+    }
+
+    #[test]
+    fn stops_after_error() {
+        let (url, _) = serve(vec![Canned::json(404, r#"{"message": "Not Found"}"#)]);
+        let github = Github::with_api_url("token", &url).unwrap();
+        let mut paginator = issues(&github);
+
+        assert!(paginator.next().unwrap().unwrap_err().is_not_found());
+        assert!(paginator.next().is_none());
+    }
+
+    #[test]
+    fn set_query_param_replaces_value() {
+        let mut url = Url::parse("https://api.github.com/repos/o/r/issues?page=2&state=open").unwrap();
+        set_query_param(&mut url, "page", "3");
+        assert_eq!("https://api.github.com/repos/o/r/issues?state=open&page=3", url.as_str());
+    }
+}
@@ -0,0 +1,168 @@
+//! Typed values for the query parameters of list endpoints. Every list node
+//! gets chainable setters (see `query_params!`), so several parameters can
+//! be combined, e.g.
+//! `.issues().state(State::Closed).labels(&["bug", "ui"]).sort(IssueSort::Updated)`.
+
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+/// Converts a value into the string sent as query parameter value
+pub trait QueryValue {
+    fn to_query_value(&self) -> String;
+}
+
+impl<'a> QueryValue for &'a str {
+    fn to_query_value(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl QueryValue for String {
+    fn to_query_value(&self) -> String {
+        self.clone()
+    }
+}
+
+impl QueryValue for u32 {
+    fn to_query_value(&self) -> String {
+        self.to_string()
+    }
+}
+
+/// Lists are sent comma separated, e.g. `labels=bug,ui`
+impl<'a, T> QueryValue for &'a [T]
+where
+    T: QueryValue,
+{
+    fn to_query_value(&self) -> String {
+        self.iter().map(QueryValue::to_query_value).collect::<Vec<_>>().join(",")
+    }
+}
+
+macro_rules! query_enum {
+    ($(#[$m: meta] $t: ident { $($(#[$vm: meta])* $v: ident = $s: expr),* })*) => (
+        $(
+        #[$m]
+        #[derive(Clone, Copy, Debug, PartialEq)]
+        pub enum $t {
+            $($(#[$vm])* $v),*
+        }
+
+        impl $t {
+            pub fn as_str(&self) -> &'static str {
+                match self {
+                    $($t::$v => $s),*
+                }
+            }
+        }
+
+        impl Display for $t {
+            fn fmt(&self, f: &mut Formatter) -> FmtResult {
+                write!(f, "{}", self.as_str())
+            }
+        }
+
+        impl QueryValue for $t {
+            fn to_query_value(&self) -> String {
+                self.as_str().to_string()
+            }
+        }
+        )*
+    );
+}
+
+query_enum!(
+    /// The state of issues and pull requests to list
+    State {
+        Open = "open",
+        Closed = "closed",
+        All = "all"
+    }
+
+    /// The order of listed issues
+    IssueSort {
+        Created = "created",
+        Updated = "updated",
+        Comments = "comments"
+    }
+
+    /// The order of listed pull requests
+    PullSort {
+        Created = "created",
+        Updated = "updated",
+        Popularity = "popularity",
+        /// Age, filtered by update time in the last month
+        LongRunning = "long-running"
+    }
+
+    /// The direction of the sort
+    Direction {
+        Asc = "asc",
+        Desc = "desc"
+    }
+
+    /// Which issues of the authenticated user to list
+    IssueFilter {
+        Assigned = "assigned",
+        Created = "created",
+        Mentioned = "mentioned",
+        Subscribed = "subscribed",
+        All = "all"
+    }
+
+    /// Which repositories of the authenticated user to list
+    Affiliation {
+        Owner = "owner",
+        Collaborator = "collaborator",
+        OrganizationMember = "organization_member"
+    }
+);
+
+#[cfg(test)]
+mod tests {
+    use crate::client::Github;
+    use super::*;
+
+    #[test]
+    fn composes_and_encodes_parameters() {
+        let github = Github::with_api_url("token", "https://api.github.com").unwrap();
+        let query = github
+            .get()
+            .repos()
+            .owner("octocat")
+            .repo("hello-world")
+            .issues()
+            .state(State::All)
+            .labels(&["bug", "help wanted"])
+            .assignee("octocat")
+            .creator("hubot")
+            .since("2020-01-01T00:00:00Z")
+            .sort(IssueSort::Updated)
+            .direction(Direction::Asc)
+            .per_page(50)
+            .state(State::Closed);
+
+        let url = query.request.unwrap().into_inner().url().clone();
+        assert_eq!(
+            "labels=bug%2Chelp+wanted&assignee=octocat&creator=hubot&since=2020-01-01T00%3A00%3A00Z&sort=updated&direction=asc&per_page=50&state=closed",
+            url.query().unwrap()
+        );
+    }
+
+    #[test]
+    fn pull_parameters() {
+        let github = Github::with_api_url("token", "https://api.github.com").unwrap();
+        let query = github
+            .get()
+            .repos()
+            .owner("octocat")
+            .repo("hello-world")
+            .pulls()
+            .state(State::Open)
+            .base("main")
+            .sort(PullSort::LongRunning)
+            .direction(Direction::Desc);
+
+        let url = query.request.unwrap().into_inner().url().clone();
+        assert_eq!("state=open&base=main&sort=long-running&direction=desc", url.query().unwrap());
+    }
+}
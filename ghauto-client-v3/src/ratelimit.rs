@@ -0,0 +1,226 @@
+use hyper::HeaderMap;
+use reqwest::Method;
+
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// GitHub asks integrations to leave at least one second between
+/// requests that create, update or delete content.
+pub const MUTATION_INTERVAL: Duration = Duration::from_secs(1);
+
+/// GitHub asks to wait at least one minute after hitting a secondary
+/// rate limit when no `Retry-After` header is sent.
+pub(crate) const SECONDARY_LIMIT_WAIT: Duration = Duration::from_secs(60);
+
+/// The request budget as reported by the last response
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RateLimit {
+    /// `X-RateLimit-Limit`, requests allowed per window
+    pub limit: Option<u64>,
+    /// `X-RateLimit-Remaining`, requests left in the current window
+    pub remaining: Option<u64>,
+    /// `X-RateLimit-Reset`, the epoch second the window resets
+    pub reset: Option<u64>,
+}
+
+#[derive(Default)]
+struct State {
+    rate: RateLimit,
+    blocked_until: Option<SystemTime>,
+    next_mutation: Option<Instant>,
+}
+
+/// Tracks the rate limit budget of a token and holds back requests that
+/// would exceed it. It is shared by all clones of a `Github` and can be
+/// used from several threads at once.
+pub struct RateLimiter {
+    state: Mutex<State>,
+    mutation_interval: Mutex<Duration>,
+}
+
+impl Default for RateLimiter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RateLimiter {
+    pub fn new() -> Self {
+        Self {
+            state: Mutex::new(State::default()),
+            mutation_interval: Mutex::new(MUTATION_INTERVAL),
+        }
+    }
+
+    /// The budget as reported by the most recent response
+    pub fn rate_limit(&self) -> RateLimit {
+        self.state.lock().unwrap().rate.clone()
+    }
+
+    /// Change the pause enforced between two mutating requests
+    pub fn set_mutation_interval(&self, interval: Duration) {
+        *self.mutation_interval.lock().unwrap() = interval;
+    }
+
+    /// How long a request with the given method has to wait before it may be
+    /// sent. Mutating requests reserve their slot, so concurrent callers are
+    /// spaced out by the mutation interval.
+    pub(crate) fn reserve(&self, method: &Method) -> Duration {
+        let interval = *self.mutation_interval.lock().unwrap();
+        let mut state = self.state.lock().unwrap();
+        let now = SystemTime::now();
+
+        let mut wait = Duration::from_secs(0);
+        if let Some(until) = state.blocked_until {
+            wait = wait.max(until.duration_since(now).unwrap_or_default());
+        }
+        if let (Some(0), Some(reset)) = (state.rate.remaining, state.rate.reset) {
+            let reset = UNIX_EPOCH + Duration::from_secs(reset);
+            // the reset second is inclusive, so wait a little longer
+            wait = wait.max(reset.duration_since(now).map(|d| d + Duration::from_secs(1)).unwrap_or_default());
+        }
+
+        if is_mutation(method) {
+            let earliest = Instant::now() + wait;
+            let slot = match state.next_mutation {
+                Some(next) if next > earliest => next,
+                _ => earliest,
+            };
+            state.next_mutation = Some(slot + interval);
+            wait = slot.saturating_duration_since(Instant::now());
+        }
+
+        wait
+    }
+
+    /// Block the current thread until a request with the given method may be sent
+    pub(crate) fn acquire(&self, method: &Method) {
+        let wait = self.reserve(method);
+        if wait > Duration::from_secs(0) {
+            thread::sleep(wait);
+        }
+    }
+
+    /// Record the rate limit headers of a response. `rate_limited` tells
+    /// whether GitHub rejected the request because of a rate limit, see
+    /// `errors::is_rate_limited`.
+    pub(crate) fn update(&self, headers: &HeaderMap, rate_limited: bool) {
+        let header_u64 = |name: &str| {
+            headers
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.trim().parse::<u64>().ok())
+        };
+
+        let mut state = self.state.lock().unwrap();
+        if let Some(remaining) = header_u64("x-ratelimit-remaining") {
+            state.rate = RateLimit {
+                limit: header_u64("x-ratelimit-limit"),
+                remaining: Some(remaining),
+                reset: header_u64("x-ratelimit-reset"),
+            };
+        }
+
+        // secondary limits are not reflected in the primary budget
+        let secondary = rate_limited && state.rate.remaining != Some(0);
+        let wait = match header_u64("retry-after") {
+            Some(after) => Some(Duration::from_secs(after)),
+            None if secondary => Some(SECONDARY_LIMIT_WAIT),
+            None => None,
+        };
+        if let Some(wait) = wait {
+            state.blocked_until = Some(SystemTime::now() + wait);
+        }
+    }
+}
+
+fn is_mutation(method: &Method) -> bool {
+    method == Method::POST || method == Method::PUT || method == Method::PATCH || method == Method::DELETE
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::is_rate_limited;
+    use hyper::header::HeaderValue;
+    use hyper::StatusCode;
+
+    fn headers(remaining: &str, reset: u64) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-ratelimit-limit", HeaderValue::from_static("5000"));
+        headers.insert("x-ratelimit-remaining", HeaderValue::from_str(remaining).unwrap());
+        headers.insert("x-ratelimit-reset", HeaderValue::from_str(&reset.to_string()).unwrap());
+        headers
+    }
+
+    fn now() -> u64 {
+        SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs()
+    }
+
+    #[test]
+    fn tracks_budget() {
+        let limiter = RateLimiter::new();
+        limiter.update(&headers("4999", 1600000000), false);
+        assert_eq!(
+            RateLimit {
+                limit: Some(5000),
+                remaining: Some(4999),
+                reset: Some(1600000000),
+            },
+            limiter.rate_limit()
+        );
+        assert_eq!(Duration::from_secs(0), limiter.reserve(&Method::GET));
+    }
+
+    #[test]
+    fn waits_for_reset_when_exhausted() {
+        let limiter = RateLimiter::new();
+        limiter.update(&headers("0", now() + 30), false);
+        let wait = limiter.reserve(&Method::GET);
+        assert!(wait > Duration::from_secs(29) && wait <= Duration::from_secs(31));
+
+        limiter.update(&headers("0", now() - 1), false);
+        assert_eq!(Duration::from_secs(0), limiter.reserve(&Method::GET));
+    }
+
+    #[test]
+    fn honours_retry_after() {
+        let limiter = RateLimiter::new();
+        let mut headers = HeaderMap::new();
+        headers.insert("retry-after", HeaderValue::from_static("10"));
+        limiter.update(&headers, true);
+        let wait = limiter.reserve(&Method::GET);
+        assert!(wait > Duration::from_secs(9) && wait <= Duration::from_secs(10));
+    }
+
+    #[test]
+    fn pauses_after_secondary_limit() {
+        let limiter = RateLimiter::new();
+        let message = "You have exceeded a secondary rate limit";
+        limiter.update(&headers("4000", now() + 3600), is_rate_limited(StatusCode::FORBIDDEN, &HeaderMap::new(), message));
+        let wait = limiter.reserve(&Method::GET);
+        assert!(wait > SECONDARY_LIMIT_WAIT - Duration::from_secs(1) && wait <= SECONDARY_LIMIT_WAIT);
+
+        let limiter = RateLimiter::new();
+        limiter.update(&headers("4000", now() + 3600), is_rate_limited(StatusCode::FORBIDDEN, &HeaderMap::new(), "Forbidden"));
+        assert_eq!(Duration::from_secs(0), limiter.reserve(&Method::GET));
+    }
+
+    #[test]
+    fn spaces_mutations() {
+        let limiter = RateLimiter::new();
+        assert_eq!(Duration::from_secs(0), limiter.reserve(&Method::GET));
+        assert_eq!(Duration::from_secs(0), limiter.reserve(&Method::POST));
+        let wait = limiter.reserve(&Method::PUT);
+        assert!(wait > Duration::from_millis(900));
+        let wait = limiter.reserve(&Method::DELETE);
+        assert!(wait > Duration::from_millis(1900));
+        assert_eq!(Duration::from_secs(0), limiter.reserve(&Method::GET));
+
+        let limiter = RateLimiter::new();
+        limiter.set_mutation_interval(Duration::from_secs(0));
+        limiter.reserve(&Method::POST);
+        assert_eq!(Duration::from_secs(0), limiter.reserve(&Method::POST));
+    }
+}
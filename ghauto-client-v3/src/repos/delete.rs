@@ -0,0 +1,110 @@
+imports!();
+use crate::client::DeleteQueryBuilder;
+use crate::util::{validate_owner, validate_repo};
+
+new_type!(
+    Assignees
+    Contents
+    Git
+    Issues
+    IssuesLabels
+    IssuesLabelsName
+    IssuesNumber
+    Labels
+    LabelsName
+    Owner
+    Path
+    Pulls
+    PullsNumber
+    Reference
+    Refs
+    Repo
+    Repos
+    RequestedReviewers
+);
+
+from!(
+    @Contents
+        => Path
+    @Git
+        -> Refs = "refs"
+    @Issues
+        => IssuesNumber
+    @IssuesLabels
+        => IssuesLabelsName
+    @IssuesNumber
+        -> Assignees = "assignees"
+        -> IssuesLabels = "labels"
+    @Labels
+        => LabelsName
+    @Owner
+        => Repo
+    @DeleteQueryBuilder
+        -> Repos = "repos"
+    @Pulls
+        => PullsNumber
+    @PullsNumber
+        -> RequestedReviewers = "requested_reviewers"
+    @Refs
+        => Reference
+    @Repo
+        -> Contents = "contents"
+        -> Git = "git"
+        -> Issues = "issues"
+        -> Labels = "labels"
+        -> Pulls = "pulls"
+    @Repos
+        => Owner
+);
+
+impl_macro!(
+    @Contents
+        |
+        |/> path -> Path = path
+    @Git
+        |=> refs -> Refs
+        |
+    @Issues
+        |
+        |=> number -> IssuesNumber = issue_number
+    @IssuesLabels
+        |
+        |=> labelname -> IssuesLabelsName = labelname
+    @IssuesNumber
+        |=> assignees -> Assignees
+        |=> labels -> IssuesLabels
+        |
+    @Labels
+        |
+        |=> labelname -> LabelsName = labelname
+    @Owner
+        |
+        |=> repo -> Repo = repo_str where validate_repo
+    @Pulls
+        |
+        |=> number -> PullsNumber = pulls_number
+    @PullsNumber
+        |=> requested_reviewers -> RequestedReviewers
+        |
+    @Refs
+        |
+        |/> reference -> Reference = ref_str
+    @Repo
+        |=> contents -> Contents
+        |=> git -> Git
+        |=> issues -> Issues
+        |=> labels -> Labels
+        |=> pulls -> Pulls
+        |
+    @Repos
+        |
+        |=> owner -> Owner = username_str where validate_owner
+);
+
+exec!(Assignees);
+exec!(IssuesLabelsName);
+exec!(LabelsName);
+exec!(Path);
+exec!(Reference);
+exec!(Repo);
+exec!(RequestedReviewers);
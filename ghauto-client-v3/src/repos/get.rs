@@ -1,12 +1,13 @@
 imports!();
 use crate::client::GetQueryBuilder;
+use crate::util::{validate_owner, validate_repo};
+use crate::params::{Direction, IssueSort, PullSort, State};
 
 new_type!(
     Commits
     Contents
     Issues
     IssuesPage
-    IssuesState
     IssuesNumber
     Labels
     LabelsName
@@ -15,7 +16,6 @@ new_type!(
     Pulls
     PullsNumber
     PullsPage
-    PullsState
     Reference
     Repo
     Repos
@@ -60,10 +60,10 @@ from!(
 impl_macro!(
     @Commits
         |
-        |=> reference -> Reference = ref_str
+        |/> reference -> Reference = ref_str
     @Contents
         |
-        |=> path -> Path = path
+        |/> path -> Path = path
     @Issues
         |
         |=> number -> IssuesNumber = issue_number
@@ -73,7 +73,7 @@ impl_macro!(
         |=> labelname -> LabelsName = labelname
     @Owner
         |
-        |=> repo -> Repo = repo_str
+        |=> repo -> Repo = repo_str where validate_repo
     @Pulls
         |
         |=> number -> PullsNumber = pulls_number
@@ -87,20 +87,40 @@ impl_macro!(
         |
     @Repos
         |
-        |=> owner -> Owner = username_str
+        |=> owner -> Owner = username_str where validate_owner
     @Reference
         |
 );
 
+query_params!(
+    @Issues
+        |&> state: State = "state"
+        |&> labels: &[&str] = "labels"
+        |&> assignee: &str = "assignee"
+        |&> creator: &str = "creator"
+        |&> since: &str = "since"
+        |&> sort: IssueSort = "sort"
+        |&> direction: Direction = "direction"
+        |&> per_page: u32 = "per_page"
+    @Pulls
+        |&> state: State = "state"
+        |&> head: &str = "head"
+        |&> base: &str = "base"
+        |&> sort: PullSort = "sort"
+        |&> direction: Direction = "direction"
+        |&> per_page: u32 = "per_page"
+);
+
 exec!(Issues);
+paginate!(Issues);
 exec!(IssuesPage);
-exec!(IssuesState);
 exec!(IssuesNumber);
 exec!(Labels);
+paginate!(Labels);
 exec!(Path);
 exec!(Pulls);
+paginate!(Pulls);
 exec!(PullsNumber);
 exec!(PullsPage);
-exec!(PullsState);
 exec!(Repo);
 exec!(Reference);
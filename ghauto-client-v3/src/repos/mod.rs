@@ -1,3 +1,5 @@
+pub mod delete;
 pub mod get;
+pub mod patch;
 pub mod post;
 pub mod put;
@@ -0,0 +1,78 @@
+imports!();
+use crate::client::PatchQueryBuilder;
+use crate::util::{validate_owner, validate_repo};
+
+new_type!(
+    Git
+    Issues
+    IssuesNumber
+    Labels
+    LabelsName
+    Owner
+    Pulls
+    PullsNumber
+    Reference
+    Refs
+    Repo
+    Repos
+);
+
+from!(
+    @Git
+        -> Refs = "refs"
+    @Issues
+        => IssuesNumber
+    @Labels
+        => LabelsName
+    @Owner
+        => Repo
+    @PatchQueryBuilder
+        -> Repos = "repos"
+    @Pulls
+        => PullsNumber
+    @Refs
+        => Reference
+    @Repo
+        -> Git = "git"
+        -> Issues = "issues"
+        -> Labels = "labels"
+        -> Pulls = "pulls"
+    @Repos
+        => Owner
+);
+
+impl_macro!(
+    @Git
+        |=> refs -> Refs
+        |
+    @Issues
+        |
+        |=> number -> IssuesNumber = issue_number
+    @Labels
+        |
+        |=> labelname -> LabelsName = labelname
+    @Owner
+        |
+        |=> repo -> Repo = repo_str where validate_repo
+    @Pulls
+        |
+        |=> number -> PullsNumber = pulls_number
+    @Refs
+        |
+        |/> reference -> Reference = ref_str
+    @Repo
+        |=> git -> Git
+        |=> issues -> Issues
+        |=> labels -> Labels
+        |=> pulls -> Pulls
+        |
+    @Repos
+        |
+        |=> owner -> Owner = username_str where validate_owner
+);
+
+exec!(IssuesNumber);
+exec!(LabelsName);
+exec!(PullsNumber);
+exec!(Reference);
+exec!(Repo);
@@ -1,11 +1,13 @@
 imports!();
 use crate::client::PostQueryBuilder;
+use crate::util::{validate_owner, validate_repo};
 
 new_type!(
     Assignees
     Git
     Issues
     IssuesNumber
+    Labels
     Owner
     Pulls
     PullsNumber
@@ -35,6 +37,7 @@ from!(
     @Repo
         -> Issues = "issues"
         -> Git = "git"
+        -> Labels = "labels"
         -> Pulls = "pulls"
     @Repos
         => Owner
@@ -52,7 +55,7 @@ impl_macro!(
         |
     @Owner
         |
-        |=> repo -> Repo = repo_str
+        |=> repo -> Repo = repo_str where validate_repo
     @Pulls
         |
         |=> pulls_number -> PullsNumber = pulls_number
@@ -65,13 +68,15 @@ impl_macro!(
         |=> pulls -> Pulls
         |=> git -> Git
         |=> issues -> Issues
+        |=> labels -> Labels
         |
     @Repos
         |
-        |=> owner -> Owner = username_str
+        |=> owner -> Owner = username_str where validate_owner
 );
 
 exec!(Assignees);
+exec!(Labels);
 exec!(Pulls);
 exec!(Refs);
 exec!(RequestedReviewers);
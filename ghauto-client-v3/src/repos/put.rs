@@ -1,5 +1,6 @@
 imports!();
 use crate::client::PutQueryBuilder;
+use crate::util::{validate_owner, validate_repo};
 
 new_type!(
     Contents
@@ -26,16 +27,16 @@ from!(
 impl_macro!(
     @Contents
         |
-        |=> path -> Path = path
+        |/> path -> Path = path
     @Owner
         |
-        |=> repo -> Repo = repo_str
+        |=> repo -> Repo = repo_str where validate_repo
     @Repo
         |=> contents -> Contents
         |
     @Repos
         |
-        |=> owner -> Owner = username_str
+        |=> owner -> Owner = username_str where validate_owner
 );
 
 exec!(Path);
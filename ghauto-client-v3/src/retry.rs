@@ -0,0 +1,211 @@
+use hyper::{HeaderMap, StatusCode};
+use rand::Rng;
+use reqwest::Method;
+
+use std::time::Duration;
+
+use crate::ratelimit::SECONDARY_LIMIT_WAIT;
+
+/// Decides whether and when a failed request is sent again.
+///
+/// Transient failures (connection errors, timeouts and the configured
+/// statuses) are only retried for `methods`, which by default are the
+/// idempotent ones. A request that never reached GitHub (connection refused)
+/// or that GitHub rejected because of a rate limit was not processed, so it
+/// is retried for every method, including `POST` and `PATCH`.
+#[derive(Clone, Debug)]
+pub struct RetryPolicy {
+    /// How often a request is sent at most, the first attempt included
+    pub max_attempts: u32,
+    /// The delay before the first retry, doubled for every further one
+    pub initial_backoff: Duration,
+    /// The upper bound of a single delay
+    pub max_backoff: Duration,
+    /// Randomize delays, so parallel clients do not retry in lockstep
+    pub jitter: bool,
+    /// Response statuses considered transient
+    pub statuses: Vec<StatusCode>,
+    /// Methods that may be retried after a transient failure
+    pub methods: Vec<Method>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+            jitter: true,
+            statuses: vec![
+                StatusCode::INTERNAL_SERVER_ERROR,
+                StatusCode::BAD_GATEWAY,
+                StatusCode::SERVICE_UNAVAILABLE,
+                StatusCode::GATEWAY_TIMEOUT,
+            ],
+            methods: vec![
+                Method::GET,
+                Method::HEAD,
+                Method::OPTIONS,
+                Method::PUT,
+                Method::DELETE,
+            ],
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// A policy that sends every request exactly once
+    pub fn none() -> Self {
+        Self {
+            max_attempts: 1,
+            ..Default::default()
+        }
+    }
+
+    /// The delay before retry number `retry` (starting at 1)
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let factor = 2u32.saturating_pow(retry.saturating_sub(1));
+        let delay = self
+            .initial_backoff
+            .checked_mul(factor)
+            .unwrap_or(self.max_backoff)
+            .min(self.max_backoff);
+
+        if self.jitter && delay > Duration::from_millis(0) {
+            // "equal jitter": keep half of the delay, randomize the rest
+            let half = delay / 2;
+            let millis = half.as_millis() as u64;
+            half + Duration::from_millis(rand::thread_rng().gen_range(0, millis + 1))
+        } else {
+            delay
+        }
+    }
+
+    /// The delay before the request is sent again after a response with the
+    /// given status, or `None` if it must not be retried. `rate_limited`
+    /// tells whether GitHub rejected the request because of a rate limit,
+    /// see `errors::is_rate_limited`.
+    pub(crate) fn after_response(
+        &self,
+        method: &Method,
+        attempt: u32,
+        status: StatusCode,
+        headers: &HeaderMap,
+        rate_limited: bool,
+    ) -> Option<Duration> {
+        if attempt >= self.max_attempts {
+            return None;
+        }
+
+        let header_u64 = |name: &str| {
+            headers
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.trim().parse::<u64>().ok())
+        };
+        let retry_after = header_u64("retry-after").map(Duration::from_secs);
+        let exhausted = header_u64("x-ratelimit-remaining") == Some(0);
+
+        if rate_limited {
+            // An exhausted primary budget is waited out by the rate limiter
+            // before the next attempt is sent.
+            return match (retry_after, exhausted) {
+                (Some(after), _) => Some(after),
+                (None, true) => Some(Duration::from_secs(0)),
+                (None, false) => Some(SECONDARY_LIMIT_WAIT),
+            };
+        }
+
+        if self.statuses.contains(&status) && self.methods.contains(method) {
+            return Some(self.backoff(attempt));
+        }
+
+        None
+    }
+
+    /// The delay before the request is sent again after a transport error,
+    /// or `None` if it must not be retried
+    pub(crate) fn after_error(&self, method: &Method, attempt: u32, error: &reqwest::Error) -> Option<Duration> {
+        if attempt >= self.max_attempts {
+            return None;
+        }
+
+        if error.is_connect() || self.methods.contains(method) {
+            return Some(self.backoff(attempt));
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::is_rate_limited;
+    use hyper::header::HeaderValue;
+
+    fn policy() -> RetryPolicy {
+        RetryPolicy {
+            jitter: false,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn exponential_backoff() {
+        let policy = policy();
+        assert_eq!(Duration::from_millis(500), policy.backoff(1));
+        assert_eq!(Duration::from_millis(1000), policy.backoff(2));
+        assert_eq!(Duration::from_millis(2000), policy.backoff(3));
+        assert_eq!(Duration::from_secs(30), policy.backoff(20));
+        assert_eq!(Duration::from_secs(30), policy.backoff(100));
+    }
+
+    #[test]
+    fn jitter_stays_in_bounds() {
+        let policy = RetryPolicy::default();
+        for _ in 0..100 {
+            let delay = policy.backoff(2);
+            assert!(delay >= Duration::from_millis(500) && delay <= Duration::from_millis(1000));
+        }
+    }
+
+    #[test]
+    fn transient_statuses_only_for_idempotent_methods() {
+        let policy = policy();
+        let headers = HeaderMap::new();
+        assert!(policy.after_response(&Method::GET, 1, StatusCode::BAD_GATEWAY, &headers, false).is_some());
+        assert!(policy.after_response(&Method::PUT, 1, StatusCode::BAD_GATEWAY, &headers, false).is_some());
+        assert!(policy.after_response(&Method::POST, 1, StatusCode::BAD_GATEWAY, &headers, false).is_none());
+        assert!(policy.after_response(&Method::GET, 1, StatusCode::NOT_FOUND, &headers, false).is_none());
+        assert!(policy.after_response(&Method::GET, 3, StatusCode::BAD_GATEWAY, &headers, false).is_none());
+    }
+
+    #[test]
+    fn rate_limit_rejections_for_all_methods() {
+        let policy = policy();
+        let after = |method, status, headers: &HeaderMap, message| {
+            let rate_limited = is_rate_limited(status, headers, message);
+            policy.after_response(method, 1, status, headers, rate_limited)
+        };
+        let mut headers = HeaderMap::new();
+        headers.insert("retry-after", HeaderValue::from_static("7"));
+        assert_eq!(Some(Duration::from_secs(7)), after(&Method::POST, StatusCode::FORBIDDEN, &headers, ""));
+        assert_eq!(
+            Some(SECONDARY_LIMIT_WAIT),
+            after(&Method::PATCH, StatusCode::TOO_MANY_REQUESTS, &HeaderMap::new(), "")
+        );
+        // secondary limits are often only named in the message
+        assert_eq!(
+            Some(SECONDARY_LIMIT_WAIT),
+            after(&Method::POST, StatusCode::FORBIDDEN, &HeaderMap::new(), "You have exceeded a secondary rate limit")
+        );
+        assert!(after(&Method::POST, StatusCode::FORBIDDEN, &HeaderMap::new(), "Resource not accessible").is_none());
+    }
+
+    #[test]
+    fn none_never_retries() {
+        let policy = RetryPolicy::none();
+        assert!(policy.after_response(&Method::GET, 1, StatusCode::BAD_GATEWAY, &HeaderMap::new(), false).is_none());
+    }
+}
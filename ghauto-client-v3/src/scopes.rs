@@ -0,0 +1,150 @@
+//! OAuth scopes of classic tokens. GitHub reports the granted scopes in
+//! `X-OAuth-Scopes`; fine grained tokens and installation tokens have no
+//! scopes and send no such header.
+
+use hyper::HeaderMap;
+use std::sync::{OnceLock, RwLock};
+
+use crate::errors::{Error, Result};
+
+/// Scopes that include other scopes, e.g. `repo` grants `public_repo`
+const IMPLIED: &[(&str, &[&str])] = &[
+    ("repo", &["public_repo", "repo:status", "repo_deployment", "repo:invite", "security_events"]),
+    ("admin:org", &["write:org", "manage_runners:org"]),
+    ("write:org", &["read:org"]),
+    ("admin:public_key", &["write:public_key"]),
+    ("write:public_key", &["read:public_key"]),
+    ("admin:repo_hook", &["write:repo_hook"]),
+    ("write:repo_hook", &["read:repo_hook"]),
+    ("admin:gpg_key", &["write:gpg_key"]),
+    ("write:gpg_key", &["read:gpg_key"]),
+    ("user", &["read:user", "user:email", "user:follow"]),
+    ("write:packages", &["read:packages"]),
+    ("write:discussion", &["read:discussion"]),
+];
+
+/// The scopes granted to the token, `None` if the response does not
+/// report them
+pub fn granted(head: &HeaderMap) -> Option<Vec<String>> {
+    head.get("x-oauth-scopes")
+        .and_then(|s| s.to_str().ok())
+        .map(|s| {
+            s.split(',')
+                .map(str::trim)
+                .filter(|s| !s.is_empty())
+                .map(String::from)
+                .collect()
+        })
+}
+
+/// Whether `scope` is granted directly or through a broader scope
+pub fn includes(granted: &[String], scope: &str) -> bool {
+    granted.iter().any(|g| g == scope || implies(g, scope))
+}
+
+fn implies(broader: &str, scope: &str) -> bool {
+    IMPLIED
+        .iter()
+        .filter(|(b, _)| *b == broader)
+        .flat_map(|(_, implied)| implied.iter())
+        .any(|i| *i == scope || implies(i, scope))
+}
+
+/// The required scopes that are not granted
+pub fn missing<'a>(granted: &[String], required: &[&'a str]) -> Vec<&'a str> {
+    required.iter().filter(|r| !includes(granted, r)).cloned().collect()
+}
+
+/// The scopes a client requires, checked against the first response that
+/// reports the granted scopes instead of asking for them up front. Once
+/// they are found lacking, requests fail without being sent.
+#[derive(Debug, Default)]
+pub(crate) struct ScopeCheck {
+    required: RwLock<Vec<&'static str>>,
+    missing: OnceLock<Option<(Vec<String>, Vec<String>)>>,
+}
+
+impl ScopeCheck {
+    pub(crate) fn require(&self, scopes: &[&'static str]) {
+        self.required.write().unwrap().extend_from_slice(scopes);
+    }
+
+    /// Fails if an earlier response showed that scopes are missing
+    pub(crate) fn before_request(&self) -> Result<()> {
+        match self.missing.get() {
+            Some(Some((missing, granted))) => Err(Error::MissingScopes {
+                missing: missing.clone(),
+                granted: granted.clone(),
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Check the scopes reported by a response, only the first report
+    /// counts. Responses without a report, e.g. for fine grained tokens,
+    /// are let through.
+    pub(crate) fn after_response(&self, head: &HeaderMap) -> Result<()> {
+        let required = self.required.read().unwrap();
+        if required.is_empty() || self.missing.get().is_some() {
+            return self.before_request();
+        }
+        if let Some(granted) = granted(head) {
+            let lacking: Vec<String> = missing(&granted, &required).into_iter().map(String::from).collect();
+            let _ = self.missing.set(if lacking.is_empty() { None } else { Some((lacking, granted)) });
+        }
+        self.before_request()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hyper::header::HeaderValue;
+
+    #[test]
+    fn parses_header() {
+        let mut head = HeaderMap::new();
+        assert_eq!(None, granted(&head));
+
+        head.insert("x-oauth-scopes", HeaderValue::from_static("repo, read:org"));
+        assert_eq!(Some(vec!["repo".to_string(), "read:org".to_string()]), granted(&head));
+
+        head.insert("x-oauth-scopes", HeaderValue::from_static(""));
+        assert_eq!(Some(Vec::new()), granted(&head));
+    }
+
+    #[test]
+    fn broader_scopes_include_narrower() {
+        let granted = vec!["repo".to_string(), "admin:org".to_string()];
+        assert!(includes(&granted, "public_repo"));
+        assert!(includes(&granted, "read:org"));
+        assert!(!includes(&granted, "user:email"));
+        assert_eq!(vec!["user:email", "delete_repo"], missing(&granted, &["repo", "user:email", "delete_repo", "read:org"]));
+    }
+
+    #[test]
+    fn first_report_of_scopes_counts() {
+        let mut head = HeaderMap::new();
+        let check = ScopeCheck::default();
+        head.insert("x-oauth-scopes", HeaderValue::from_static("public_repo"));
+        assert!(check.after_response(&head).is_ok());
+
+        check.require(&["repo"]);
+        assert!(check.after_response(&HeaderMap::new()).is_ok());
+        match check.after_response(&head) {
+            Err(Error::MissingScopes { missing, granted }) => {
+                assert_eq!(vec!["repo"], missing);
+                assert_eq!(vec!["public_repo"], granted);
+            }
+            other => panic!("unexpected {:?}", other.map(|_| ())),
+        }
+        assert!(check.before_request().is_err());
+
+        let check = ScopeCheck::default();
+        check.require(&["repo"]);
+        head.insert("x-oauth-scopes", HeaderValue::from_static("repo"));
+        assert!(check.after_response(&head).is_ok());
+        head.insert("x-oauth-scopes", HeaderValue::from_static(""));
+        assert!(check.after_response(&head).is_ok());
+    }
+}
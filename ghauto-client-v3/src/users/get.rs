@@ -1,9 +1,18 @@
 imports!();
 use crate::client::GetQueryBuilder;
+use crate::params::Affiliation;
+use crate::util::validate_owner;
 
 new_type!(
     Emails
+    Memberships
+    MembershipsOrgs
+    Orgs
     User
+    UserRepos
+    Users
+    Username
+    UsernameRepos
 );
 
 from!(
@@ -11,13 +20,54 @@ from!(
         -> User = "user"
     @User
         -> Emails = "emails"
+    @User
+        -> Orgs = "orgs"
+    @User
+        -> Memberships = "memberships"
+    @User
+        -> UserRepos = "repos"
+    @Memberships
+        -> MembershipsOrgs = "orgs"
+    @GetQueryBuilder
+        -> Users = "users"
+    @Users
+        => Username
+    @Username
+        -> UsernameRepos = "repos"
 );
 
 impl_macro!(
     @User
         |=> emails -> Emails
+        |=> orgs -> Orgs
+        |=> memberships -> Memberships
+        |=> repos -> UserRepos
+        |
+    @Memberships
+        |=> orgs -> MembershipsOrgs
         |
+    @Users
+        |
+        |=> username -> Username = username_str where validate_owner
+    @Username
+        |=> repos -> UsernameRepos
+        |
+);
+
+query_params!(
+    @UserRepos
+        |&> affiliation: Affiliation = "affiliation"
+        |&> per_page: u32 = "per_page"
+    @UsernameRepos
+        |&> per_page: u32 = "per_page"
 );
 
 exec!(Emails);
+exec!(MembershipsOrgs);
+exec!(Orgs);
 exec!(User);
+exec!(UserRepos);
+exec!(Username);
+exec!(UsernameRepos);
+paginate!(UserRepos);
+paginate!(UsernameRepos);
@@ -1,14 +1,188 @@
 use reqwest::Url;
 
+use crate::errors::{Error, Result};
+
 
 /// Add an extra subdirectory to the end of the url. This utilizes
 /// Hyper's more generic Uri type. We've set it up to act as a Url.
-pub fn url_join(url: &Url, path: &str) -> Result<Url, Box<dyn std::error::Error + Send + Sync + 'static>> {
+pub fn url_join(url: &Url, path: &str) -> Result<Url> {
     let url_str = url.to_string();
     if url_str.ends_with("/") {
         return Ok(url.join(path)?);
     } else {
         let u = url_str + "/" + path;
-        return Ok(reqwest::Url::parse(&u)?);
+        return Ok(Url::parse(&u)?);
+    }
+}
+
+/// Append a value as a single path segment. Reserved characters, `/`
+/// included, are percent-encoded, so user input cannot change the endpoint.
+pub fn url_push_segment(url: &Url, segment: &str) -> Result<Url> {
+    let mut joined = url.clone();
+    joined
+        .path_segments_mut()
+        .map_err(|_| Error::InvalidRequest(format!("{} cannot be a base url", url)))?
+        .pop_if_empty()
+        .push(segment);
+    Ok(joined)
+}
+
+/// Append a `/` separated path like `docs/read me.md` or `heads/feature/x`,
+/// percent-encoding each of its segments. Empty segments are skipped.
+pub fn url_push_path(url: &Url, path: &str) -> Result<Url> {
+    let mut joined = url.clone();
+    joined
+        .path_segments_mut()
+        .map_err(|_| Error::InvalidRequest(format!("{} cannot be a base url", url)))?
+        .pop_if_empty()
+        .extend(path.split('/').filter(|s| !s.is_empty()));
+    Ok(joined)
+}
+
+/// Check a user or organisation name against GitHub's rules: at most 39
+/// alphanumeric characters or hyphens, not starting or ending with a
+/// hyphen. GitHub only allows single hyphens for new accounts, older ones
+/// may have consecutive hyphens. Enterprise servers, i.e. any API but
+/// `api.github.com`, also allow `_`, as in the `name_shortcode` of their
+/// managed users.
+pub fn validate_owner(api_url: &Url, name: &str) -> Result<()> {
+    let invalid = |reason: &str| {
+        Err(Error::InvalidName {
+            name: name.to_string(),
+            reason: reason.to_string(),
+        })
+    };
+
+    if name.is_empty() {
+        return invalid("owner must not be empty");
+    }
+    if name.len() > 39 {
+        return invalid("owner must not be longer than 39 characters");
+    }
+    if name.starts_with('-') || name.ends_with('-') {
+        return invalid("owner must not start or end with a hyphen");
+    }
+    if api_url.host_str() != Some("api.github.com") {
+        if !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+            return invalid("owner may only contain alphanumeric characters, hyphens or underscores");
+        }
+    } else if !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+        return invalid("owner may only contain alphanumeric characters or hyphens");
+    }
+    Ok(())
+}
+
+/// Check a repository name against GitHub's rules: at most 100 characters
+/// out of alphanumerics, `-`, `_` and `.`, and neither `.` nor `..`. The
+/// rules are the same for every API.
+pub fn validate_repo(_api_url: &Url, name: &str) -> Result<()> {
+    let invalid = |reason: &str| {
+        Err(Error::InvalidName {
+            name: name.to_string(),
+            reason: reason.to_string(),
+        })
+    };
+
+    if name.is_empty() {
+        return invalid("repository name must not be empty");
+    }
+    if name.len() > 100 {
+        return invalid("repository name must not be longer than 100 characters");
+    }
+    if name == "." || name == ".." {
+        return invalid("repository name must not be `.` or `..`");
+    }
+    if !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.') {
+        return invalid("repository name may only contain alphanumeric characters, `-`, `_` or `.`");
+    }
+    Ok(())
+}
+
+/// Resolve the API root for a host. `github.com` is served from
+/// `api.github.com`, a GitHub Enterprise Server serves its REST API
+/// under `/api/v3`. A host may carry a scheme (`http://localhost:8080`),
+/// otherwise https is assumed.
+pub fn api_url_for_host(host: &str) -> Result<Url> {
+    let host = host.trim_end_matches('/');
+    if host == "github.com" || host == "https://github.com" {
+        return Ok(Url::parse(crate::client::GITHUB_API_URL)?);
+    }
+
+    let base = if host.contains("://") {
+        host.to_string()
+    } else {
+        format!("https://{}", host)
+    };
+    Ok(Url::parse(&format!("{}/api/v3", base))?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn api_url_for_github_com() {
+        let url = api_url_for_host("github.com").unwrap();
+        assert_eq!("https://api.github.com/", url.as_str());
+    }
+
+    #[test]
+    fn api_url_for_enterprise_host() {
+        let url = api_url_for_host("ghe.example.com").unwrap();
+        assert_eq!("https://ghe.example.com/api/v3", url.as_str());
+
+        let url = api_url_for_host("http://localhost:8080/").unwrap();
+        assert_eq!("http://localhost:8080/api/v3", url.as_str());
+    }
+
+    #[test]
+    fn url_join_keeps_api_path() {
+        let base = Url::parse("https://ghe.example.com/api/v3").unwrap();
+        let url = url_join(&base, "repos").unwrap();
+        assert_eq!("https://ghe.example.com/api/v3/repos", url.as_str());
+    }
+
+    #[test]
+    fn path_segments_are_encoded() {
+        let base = Url::parse("https://api.github.com/repos/o/r/contents").unwrap();
+        let url = url_push_path(&base, "docs/read me #1.md").unwrap();
+        assert_eq!("https://api.github.com/repos/o/r/contents/docs/read%20me%20%231.md", url.as_str());
+
+        let base = Url::parse("https://api.github.com/repos/o/r/labels").unwrap();
+        let url = url_push_segment(&base, "a/b?c").unwrap();
+        assert_eq!("https://api.github.com/repos/o/r/labels/a%2Fb%3Fc", url.as_str());
+
+        let base = Url::parse("https://api.github.com/").unwrap();
+        let url = url_push_segment(&base, "repos").unwrap();
+        assert_eq!("https://api.github.com/repos", url.as_str());
+    }
+
+    #[test]
+    fn owner_names() {
+        let github = Url::parse(crate::client::GITHUB_API_URL).unwrap();
+        assert!(validate_owner(&github, "octocat").is_ok());
+        assert!(validate_owner(&github, "my-org-2").is_ok());
+        assert!(validate_owner(&github, "old--org").is_ok());
+        assert!(validate_owner(&github, "").is_err());
+        assert!(validate_owner(&github, "-octocat").is_err());
+        assert!(validate_owner(&github, "octo/cat").is_err());
+        assert!(validate_owner(&github, "octo_cat").is_err());
+        assert!(validate_owner(&github, &"a".repeat(40)).is_err());
+
+        let enterprise = Url::parse("https://ghe.example.com/api/v3").unwrap();
+        assert!(validate_owner(&enterprise, "octocat_acme").is_ok());
+        assert!(validate_owner(&enterprise, "octo/cat").is_err());
+        assert!(validate_owner(&enterprise, "-octocat").is_err());
+    }
+
+    #[test]
+    fn repo_names() {
+        let github = Url::parse(crate::client::GITHUB_API_URL).unwrap();
+        assert!(validate_repo(&github, "hello-world").is_ok());
+        assert!(validate_repo(&github, "my_repo.rs").is_ok());
+        assert!(validate_repo(&github, ".github").is_ok());
+        assert!(validate_repo(&github, "..").is_err());
+        assert!(validate_repo(&github, "hello world").is_err());
+        assert!(validate_repo(&github, "../admin").is_err());
     }
 }
@@ -1,54 +1,502 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::convert::TryFrom;
 use std::io;
+use std::path::{Path, PathBuf};
+use serde::de::{DeserializeOwned, Deserializer, Error as _};
+use serde::Deserialize;
 use toml::Value;
-use std::path::PathBuf;
+use toml::value::Table;
+use url::Url;
 
-use crate::file::config_dir;
+use crate::document::{merge, merge_table, parse, profile_table, retain_profiles};
+use crate::file::{config_dir, read_str, write_config_dir, WriteOptions};
+use crate::problem::{to_error, Problem, Segment};
+use crate::secret::resolve_profile;
 
 pub use io::Result;
 
-#[derive(Debug)]
+#[derive(Clone, Debug, Default, Deserialize)]
 pub struct UserName(pub String);
-#[derive(Debug)]
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(from = "Option<String>")]
 pub struct UserEmail(pub Option<String>);
-#[derive(Debug)]
+#[derive(Clone, Debug, Deserialize)]
 pub struct Org(pub String);
-#[derive(Debug)]
+#[derive(Clone, Debug, Deserialize)]
 pub struct Name(pub String);
-#[derive(Debug)]
+#[derive(Clone, Debug, Deserialize)]
 pub struct Regex(pub String);
-#[derive(Debug)]
+#[derive(Clone, Debug, Deserialize)]
+pub struct Tag(pub String);
+/// The members of a group, as `org/name`
+#[derive(Clone, Debug, Deserialize)]
+#[serde(try_from = "Vec<String>")]
+pub struct Group(pub Vec<String>);
+#[derive(Clone, Debug, Deserialize)]
 pub struct ClonePath(pub String);
+/// The host of a GitHub Enterprise Server, e.g. `github.example.com` or
+/// `http://localhost:8080`
+#[derive(Clone, Debug, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Host(pub String);
+#[derive(Clone, Debug, Deserialize)]
+#[serde(try_from = "String")]
+pub struct ApiUrl(pub String);
+#[derive(Clone, Debug, Deserialize)]
+#[serde(try_from = "i64")]
+pub struct Jobs(pub usize);
 
-#[derive(Debug)]
+/// Retry settings of a profile; unset values fall back to the client defaults
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct Retry {
+    max_attempts: Option<u32>,
+    initial_backoff_ms: Option<u64>,
+    max_backoff_ms: Option<u64>,
+    jitter: Option<bool>,
+    #[serde(default, deserialize_with = "statuses")]
+    statuses: Option<Vec<u16>>,
+    #[serde(default, deserialize_with = "methods")]
+    methods: Option<Vec<String>>,
+}
+
+/// Settings of a repository entry; unset values fall back to the profile
+#[derive(Clone, Debug, Default)]
+pub struct RepositorySettings {
+    default_branch: Option<String>,
+    reviewers: Option<Vec<String>>,
+    team_reviewers: Option<Vec<String>>,
+    assignees: Option<Vec<String>>,
+    labels_file: Option<String>,
+    clone_url: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(try_from = "RepositoryEntry")]
 pub struct Repository {
     org: Org,
     name: Option<Name>,
     regex: Option<Regex>,
+    tags: Vec<Tag>,
+    settings: RepositorySettings,
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug, Default, Deserialize)]
 pub struct Repositories(pub Vec<Repository>);
 
-#[derive(Debug)]
+#[derive(Clone, Debug, Deserialize)]
 pub struct Configuration {
+    #[serde(default)]
     user_name: UserName,
+    #[serde(default)]
     user_email: UserEmail,
     clone_path: ClonePath,
+    host: Option<Host>,
+    api_url: Option<ApiUrl>,
+    retry: Option<Retry>,
+    jobs: Option<Jobs>,
+    #[serde(default)]
     repositories: Repositories,
+    #[serde(default)]
+    groups: BTreeMap<String, Group>,
+    default_branch: Option<String>,
+    reviewers: Option<Vec<String>>,
+    team_reviewers: Option<Vec<String>>,
+    assignees: Option<Vec<String>>,
+    labels_file: Option<String>,
 }
 
+#[derive(Default)]
 pub struct BardoConfig {
     profiles: HashMap<String, Configuration>,
+    warnings: Vec<Problem>,
 }
 
 // Structure config
 // [default]
 // clone_path = "/Users/seka/projects/mttrbit/bardo-repos"
-// repositories = [
+// user_name = "octocat"                            # optional
+// user_email = "octocat@github.com"                # optional
+// host = "github.example.com"                      # optional, GitHub Enterprise Server
+// api_url = "https://github.example.com/api/v3"    # optional, overrides host
+// jobs = 4                                         # optional, repositories processed in parallel
+// default_branch = "main"                          # optional, defaults to the branch set on GitHub
+// reviewers = ["octocat"]                          # optional, also team_reviewers and assignees
+// labels_file = "/path/to/labels.toml"             # optional
+// repositories = [                                 # optional, defaults to none
 //   {org = "crvshlab", name = "test"}
-// , {org = "crvshlab", regex = "nodejs-*"}
+// , {org = "crvshlab", regex = "nodejs-.*", tags = ["node"]}  # matches whole names; tags are optional, for --tag
+// , {org = "crvshlab", name = "legacy", default_branch = "master", clone_url = "https://example.com/legacy.git"}
 // ]
+//
+// [default.groups]                                 # optional, for --group
+// node = ["crvshlab/test", "crvshlab/nodejs-api"]
+//
+// [default.retry]                                  # optional
+// max_attempts = 3
+// initial_backoff_ms = 500
+// max_backoff_ms = 30000
+// jitter = true
+// statuses = [500, 502, 503, 504]
+// methods = ["GET", "HEAD", "OPTIONS", "PUT", "DELETE"]
+
+/// A `repositories` entry as written in the file
+#[derive(Deserialize)]
+struct RepositoryEntry {
+    org: String,
+    name: Option<String>,
+    regex: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+    default_branch: Option<String>,
+    reviewers: Option<Vec<String>>,
+    team_reviewers: Option<Vec<String>>,
+    assignees: Option<Vec<String>>,
+    labels_file: Option<String>,
+    clone_url: Option<String>,
+}
+
+impl TryFrom<RepositoryEntry> for Repository {
+    type Error = String;
+
+    fn try_from(entry: RepositoryEntry) -> std::result::Result<Self, Self::Error> {
+        if entry.name.is_some() == entry.regex.is_some() {
+            return Err("set either 'name' or 'regex'".to_string());
+        }
+        let mut repository = Self::new(Org(entry.org), entry.name.map(Name), entry.regex.map(Regex));
+        repository.tags = entry.tags.into_iter().map(Tag).collect();
+        repository.settings = RepositorySettings {
+            default_branch: entry.default_branch,
+            reviewers: entry.reviewers,
+            team_reviewers: entry.team_reviewers,
+            assignees: entry.assignees,
+            labels_file: entry.labels_file,
+            clone_url: entry.clone_url,
+        };
+        Ok(repository)
+    }
+}
+
+impl TryFrom<Vec<String>> for Group {
+    type Error = String;
+
+    fn try_from(members: Vec<String>) -> std::result::Result<Self, Self::Error> {
+        let invalid = members.iter().find(|m| match m.split_once('/') {
+            Some((org, name)) => org.is_empty() || name.is_empty() || name.contains('/'),
+            None => true,
+        });
+        match invalid {
+            Some(m) => Err(format!("'{}' is no repository, expected 'org/name'", m)),
+            None => Ok(Group(members)),
+        }
+    }
+}
+
+impl TryFrom<String> for Host {
+    type Error = String;
+
+    fn try_from(host: String) -> std::result::Result<Self, Self::Error> {
+        let host = Host(host);
+        if !has_reference(&host.0) {
+            host.web_url()?;
+        }
+        Ok(host)
+    }
+}
+
+impl TryFrom<String> for ApiUrl {
+    type Error = String;
+
+    fn try_from(api_url: String) -> std::result::Result<Self, Self::Error> {
+        if !has_reference(&api_url) {
+            http_url(&api_url)?;
+        }
+        Ok(ApiUrl(api_url))
+    }
+}
+
+/// Whether `value` still holds a `${NAME}` reference. Such a value is
+/// checked once `read_config` has resolved it, `validate_config` and the
+/// `config` commands read the file without resolving.
+fn has_reference(value: &str) -> bool {
+    value.contains("${")
+}
+
+/// `url` if it is an absolute `http` or `https` url
+fn http_url(url: &str) -> std::result::Result<Url, String> {
+    let parsed = Url::parse(url).map_err(|e| format!("'{}' is no url: {}", url, e))?;
+    match (parsed.scheme(), parsed.host_str()) {
+        ("http", Some(_)) | ("https", Some(_)) => Ok(parsed),
+        _ => Err(format!("'{}' is no http or https url", url)),
+    }
+}
+
+impl TryFrom<i64> for Jobs {
+    type Error = &'static str;
+
+    fn try_from(jobs: i64) -> std::result::Result<Self, Self::Error> {
+        if jobs > 0 {
+            Ok(Jobs(jobs as usize))
+        } else {
+            Err("must be a positive number")
+        }
+    }
+}
+
+impl From<Option<String>> for UserEmail {
+    fn from(email: Option<String>) -> Self {
+        UserEmail(email.filter(|e| !e.is_empty()))
+    }
+}
+
+fn statuses<'de, D>(deserializer: D) -> std::result::Result<Option<Vec<u16>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let statuses = Vec::<u16>::deserialize(deserializer)?;
+    match statuses.iter().find(|s| **s < 100 || **s >= 600) {
+        Some(s) => Err(D::Error::custom(format!("{} is no HTTP status", s))),
+        None => Ok(Some(statuses)),
+    }
+}
+
+fn methods<'de, D>(deserializer: D) -> std::result::Result<Option<Vec<String>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let methods = Vec::<String>::deserialize(deserializer)?;
+    Ok(Some(methods.iter().map(|m| m.to_uppercase()).collect()))
+}
+
+/// Deserialize `value`, collecting the keys that are not known and the
+/// error, if any, as problems of `profile`
+fn deserialize<T>(profile: Option<&str>, value: Value) -> (Option<T>, Vec<Problem>)
+where
+    T: DeserializeOwned,
+{
+    let mut problems = Vec::new();
+    let result = {
+        let mut unknown_key = |path: serde_ignored::Path| {
+            let mut segments = Vec::new();
+            ignored_segments(&path, &mut segments);
+            problems.push(Problem::unknown_key(profile, segments));
+        };
+        serde_path_to_error::deserialize(serde_ignored::Deserializer::new(value, &mut unknown_key))
+    };
+
+    match result {
+        Ok(t) => (Some(t), problems),
+        Err(e) => {
+            let path = e
+                .path()
+                .iter()
+                .filter_map(|s| match s {
+                    serde_path_to_error::Segment::Seq { index } => Some(Segment::Index(*index)),
+                    serde_path_to_error::Segment::Map { key } => Some(Segment::Key(key.clone())),
+                    _ => None,
+                })
+                .collect();
+            let message = e.into_inner().to_string();
+            // the path names the key
+            let message = match message.find(" for key `") {
+                Some(i) => &message[..i],
+                None => &message[..],
+            };
+            problems.push(Problem::error(profile, path, message));
+            (None, problems)
+        }
+    }
+}
+
+fn ignored_segments(path: &serde_ignored::Path, segments: &mut Vec<Segment>) {
+    match path {
+        serde_ignored::Path::Root => {}
+        serde_ignored::Path::Seq { parent, index } => {
+            ignored_segments(parent, segments);
+            segments.push(Segment::Index(*index));
+        }
+        serde_ignored::Path::Map { parent, key } => {
+            ignored_segments(parent, segments);
+            segments.push(Segment::Key(key.clone()));
+        }
+        serde_ignored::Path::Some { parent }
+        | serde_ignored::Path::NewtypeStruct { parent }
+        | serde_ignored::Path::NewtypeVariant { parent } => ignored_segments(parent, segments),
+    }
+}
+
+/// Deserialize `value`, failing with the error naming the key
+fn from_value<T>(value: Value) -> Result<T>
+where
+    T: DeserializeOwned,
+{
+    let (t, problems) = deserialize(None, value);
+    t.ok_or_else(|| to_error(&problems))
+}
+
+impl Host {
+    /// The root of the web interface: `https://` is added unless the host
+    /// has a scheme, a trailing `/` is removed
+    pub fn web_url(&self) -> std::result::Result<Url, String> {
+        let host = self.0.trim_end_matches('/');
+        if host.contains("://") {
+            http_url(host)
+        } else {
+            http_url(&format!("https://{}", host))
+        }
+    }
+}
+
+impl ApiUrl {
+    /// The root of the web interface serving this API: `api.github.com`
+    /// belongs to `github.com`, a GitHub Enterprise Server serves its API
+    /// under `/api/v3`
+    pub fn web_url(&self) -> std::result::Result<Url, String> {
+        let mut url = http_url(&self.0)?;
+        if url.host_str() == Some("api.github.com") {
+            return http_url("https://github.com");
+        }
+        let path = url.path().trim_end_matches('/').trim_end_matches("/api/v3").to_string();
+        url.set_path(&path);
+        Ok(url)
+    }
+}
+
+impl Retry {
+
+    pub fn max_attempts(&self) -> Option<u32> {
+        self.max_attempts
+    }
+
+    pub fn initial_backoff_ms(&self) -> Option<u64> {
+        self.initial_backoff_ms
+    }
+
+    pub fn max_backoff_ms(&self) -> Option<u64> {
+        self.max_backoff_ms
+    }
+
+    pub fn jitter(&self) -> Option<bool> {
+        self.jitter
+    }
+
+    pub fn statuses(&self) -> Option<&Vec<u16>> {
+        self.statuses.as_ref()
+    }
+
+    pub fn methods(&self) -> Option<&Vec<String>> {
+        self.methods.as_ref()
+    }
+
+    pub fn read_from<F>(reader: F) -> Result<Self>
+    where
+        F: Fn() -> Result<Value>,
+    {
+        reader().and_then(from_value)
+    }
+
+    pub fn to_toml(&self) -> Table {
+        let mut table = Table::new();
+        if let Some(n) = self.max_attempts {
+            table.insert("max_attempts".to_string(), Value::Integer(n.into()));
+        }
+        if let Some(ms) = self.initial_backoff_ms {
+            table.insert("initial_backoff_ms".to_string(), Value::Integer(ms as i64));
+        }
+        if let Some(ms) = self.max_backoff_ms {
+            table.insert("max_backoff_ms".to_string(), Value::Integer(ms as i64));
+        }
+        if let Some(jitter) = self.jitter {
+            table.insert("jitter".to_string(), Value::Boolean(jitter));
+        }
+        if let Some(statuses) = &self.statuses {
+            let statuses = statuses.iter().map(|s| Value::Integer((*s).into())).collect();
+            table.insert("statuses".to_string(), Value::Array(statuses));
+        }
+        if let Some(methods) = &self.methods {
+            let methods = methods.iter().map(|m| Value::String(m.clone())).collect();
+            table.insert("methods".to_string(), Value::Array(methods));
+        }
+        table
+    }
+}
+
+impl RepositorySettings {
+    pub fn default_branch(&self) -> Option<&str> {
+        self.default_branch.as_deref()
+    }
+
+    pub fn reviewers(&self) -> Option<&Vec<String>> {
+        self.reviewers.as_ref()
+    }
+
+    pub fn team_reviewers(&self) -> Option<&Vec<String>> {
+        self.team_reviewers.as_ref()
+    }
+
+    pub fn assignees(&self) -> Option<&Vec<String>> {
+        self.assignees.as_ref()
+    }
+
+    pub fn labels_file(&self) -> Option<&str> {
+        self.labels_file.as_deref()
+    }
+
+    pub fn clone_url(&self) -> Option<&str> {
+        self.clone_url.as_deref()
+    }
+
+    pub fn set_default_branch(&mut self, default_branch: Option<String>) {
+        self.default_branch = default_branch;
+    }
+
+    pub fn set_reviewers(&mut self, reviewers: Option<Vec<String>>) {
+        self.reviewers = reviewers;
+    }
+
+    pub fn set_team_reviewers(&mut self, team_reviewers: Option<Vec<String>>) {
+        self.team_reviewers = team_reviewers;
+    }
+
+    pub fn set_assignees(&mut self, assignees: Option<Vec<String>>) {
+        self.assignees = assignees;
+    }
+
+    pub fn set_labels_file(&mut self, labels_file: Option<String>) {
+        self.labels_file = labels_file;
+    }
+
+    pub fn set_clone_url(&mut self, clone_url: Option<String>) {
+        self.clone_url = clone_url;
+    }
+
+    /// The keys that are set, as written to a `repositories` entry
+    pub fn to_toml(&self) -> Table {
+        let string = |s: &String| Value::String(s.clone());
+        let list = |l: &Vec<String>| Value::Array(l.iter().map(string).collect());
+
+        let mut table = Table::new();
+        if let Some(branch) = &self.default_branch {
+            table.insert("default_branch".to_string(), string(branch));
+        }
+        if let Some(reviewers) = &self.reviewers {
+            table.insert("reviewers".to_string(), list(reviewers));
+        }
+        if let Some(team_reviewers) = &self.team_reviewers {
+            table.insert("team_reviewers".to_string(), list(team_reviewers));
+        }
+        if let Some(assignees) = &self.assignees {
+            table.insert("assignees".to_string(), list(assignees));
+        }
+        if let Some(labels_file) = &self.labels_file {
+            table.insert("labels_file".to_string(), string(labels_file));
+        }
+        if let Some(clone_url) = &self.clone_url {
+            table.insert("clone_url".to_string(), string(clone_url));
+        }
+        table
+    }
+}
+
 impl Repository {
 
     pub fn new(org: Org, name: Option<Name>, regex: Option<Regex>) -> Self {
@@ -56,6 +504,8 @@ impl Repository {
             org: org,
             name: name,
             regex: regex,
+            tags: Vec::new(),
+            settings: RepositorySettings::default(),
         }
     }
 
@@ -83,30 +533,50 @@ impl Repository {
         &mut self.regex
     }
 
+    pub fn tags(&self) -> &Vec<Tag> {
+        &self.tags
+    }
+
+    pub fn tags_mut(&mut self) -> &mut Vec<Tag> {
+        &mut self.tags
+    }
+
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t.0 == tag)
+    }
+
+    /// The settings of the entry itself, see `Configuration::settings_of`
+    /// for the ones falling back to the profile
+    pub fn settings(&self) -> &RepositorySettings {
+        &self.settings
+    }
+
+    pub fn settings_mut(&mut self) -> &mut RepositorySettings {
+        &mut self.settings
+    }
+
     pub fn read_from<F>(reader: F) -> Result<Self>
     where
         F: Fn() -> Result<Value>,
     {
-        reader().and_then(|repo| {
-            let str_org = repo["org"].as_str().expect("field 'org' is missing");
-            let org = Org(str_org.to_string());
-
-            let opt_name = repo.get("name");
-            let opt_regex = repo.get("regex");
-
-            match opt_name.xor(opt_regex) {
-                Some(_) => {
-                    let name = opt_name.map(|f| Name(f.as_str().unwrap().to_string()));
-                    let regex = opt_regex.map(|f| Regex(f.as_str().unwrap().to_string()));
-                    Ok(Self {
-                        org: org,
-                        name: name,
-                        regex: regex,
-                    })
-                },
-                None => Err(io::Error::new(io::ErrorKind::InvalidData, "set either 'name' or 'regex'")),
-            }
-        })
+        reader().and_then(from_value)
+    }
+
+    pub fn to_toml(&self) -> Value {
+        let mut table = Table::new();
+        table.insert("org".to_string(), Value::String(self.org.0.clone()));
+        if let Some(name) = &self.name {
+            table.insert("name".to_string(), Value::String(name.0.clone()));
+        }
+        if let Some(regex) = &self.regex {
+            table.insert("regex".to_string(), Value::String(regex.0.clone()));
+        }
+        if !self.tags.is_empty() {
+            let tags = self.tags.iter().map(|t| Value::String(t.0.clone())).collect();
+            table.insert("tags".to_string(), Value::Array(tags));
+        }
+        table.extend(self.settings.to_toml());
+        Value::Table(table)
     }
 }
 
@@ -115,20 +585,7 @@ impl Repositories {
     where
         F: Fn() -> Result<Value>,
     {
-        reader().and_then(|repositories| {
-            let repos = repositories.as_array().expect("field 'repositories' is missing");
-            let mut vec_repos = Vec::with_capacity(repos.len());
-
-            for (i, r) in repos.iter().enumerate() {
-                let res = Repository::read_from(|| Ok(r.clone())).map(|r| vec_repos.insert(i, r));
-
-                if res.is_err() {
-                    return Err(res.unwrap_err());
-                }
-            }
-
-            Ok(Repositories(vec_repos))
-        })
+        reader().and_then(from_value)
     }
 
     pub fn add(&mut self, repository: Repository) {
@@ -136,8 +593,46 @@ impl Repositories {
     }
 }
 
+impl Group {
+    pub fn contains(&self, org: &str, name: &str) -> bool {
+        self.0.iter().any(|m| m.split_once('/') == Some((org, name)))
+    }
+}
+
+/// The keys `Configuration::set` changes
+pub const SETTABLE_KEYS: &[&str] = &[
+    "clone_path",
+    "user_name",
+    "user_email",
+    "host",
+    "api_url",
+    "jobs",
+    "default_branch",
+    "labels_file",
+];
+
 impl Configuration {
 
+    /// A profile without repositories
+    pub fn new(clone_path: ClonePath) -> Self {
+        Self {
+            user_name: UserName::default(),
+            user_email: UserEmail::default(),
+            clone_path: clone_path,
+            host: None,
+            api_url: None,
+            retry: None,
+            jobs: None,
+            repositories: Repositories::default(),
+            groups: BTreeMap::new(),
+            default_branch: None,
+            reviewers: None,
+            team_reviewers: None,
+            assignees: None,
+            labels_file: None,
+        }
+    }
+
     pub fn user_name(&self) -> &UserName {
         &self.user_name
     }
@@ -150,6 +645,32 @@ impl Configuration {
         &self.clone_path
     }
 
+    pub fn host(&self) -> Option<&Host> {
+        self.host.as_ref()
+    }
+
+    pub fn api_url(&self) -> Option<&ApiUrl> {
+        self.api_url.as_ref()
+    }
+
+    /// The root of the web interface of the profile, taken from `host` or
+    /// else from `api_url`, and `https://github.com` if it sets neither
+    pub fn web_url(&self) -> std::result::Result<Url, String> {
+        match (&self.host, &self.api_url) {
+            (Some(host), _) => host.web_url(),
+            (None, Some(api_url)) => api_url.web_url(),
+            (None, None) => http_url("https://github.com"),
+        }
+    }
+
+    pub fn retry(&self) -> Option<&Retry> {
+        self.retry.as_ref()
+    }
+
+    pub fn jobs(&self) -> Option<&Jobs> {
+        self.jobs.as_ref()
+    }
+
     pub fn repositories(&self) -> &Vec<Repository> {
         &self.repositories.0
     }
@@ -158,30 +679,129 @@ impl Configuration {
         &mut self.repositories
     }
 
+    pub fn groups(&self) -> &BTreeMap<String, Group> {
+        &self.groups
+    }
+
+    pub fn groups_mut(&mut self) -> &mut BTreeMap<String, Group> {
+        &mut self.groups
+    }
+
+    /// The defaults of the profile for the settings of its repositories,
+    /// a profile has no `clone_url`
+    pub fn repository_defaults(&self) -> RepositorySettings {
+        RepositorySettings {
+            default_branch: self.default_branch.clone(),
+            reviewers: self.reviewers.clone(),
+            team_reviewers: self.team_reviewers.clone(),
+            assignees: self.assignees.clone(),
+            labels_file: self.labels_file.clone(),
+            clone_url: None,
+        }
+    }
+
+    pub fn set_repository_defaults(&mut self, defaults: RepositorySettings) {
+        self.default_branch = defaults.default_branch;
+        self.reviewers = defaults.reviewers;
+        self.team_reviewers = defaults.team_reviewers;
+        self.assignees = defaults.assignees;
+        self.labels_file = defaults.labels_file;
+    }
+
+    /// The settings of `repository`, each one it does not set is taken
+    /// from the profile
+    pub fn settings_of(&self, repository: &Repository) -> RepositorySettings {
+        let settings = repository.settings().clone();
+        RepositorySettings {
+            default_branch: settings.default_branch.or_else(|| self.default_branch.clone()),
+            reviewers: settings.reviewers.or_else(|| self.reviewers.clone()),
+            team_reviewers: settings.team_reviewers.or_else(|| self.team_reviewers.clone()),
+            assignees: settings.assignees.or_else(|| self.assignees.clone()),
+            labels_file: settings.labels_file.or_else(|| self.labels_file.clone()),
+            clone_url: settings.clone_url,
+        }
+    }
+
+    /// Read a profile. Optional keys that are missing get their defaults,
+    /// unknown keys are ignored.
     pub fn read_from<F>(reader: F) -> Result<Self>
     where
         F: Fn() -> Result<Value>,
     {
-        reader().and_then(|config| {
-            let user_name = config["user_name"].as_str().expect("field 'user_name' is missing");
-            let user_email = match config.get("user_email") {
-                Some(s) if !s.as_str().unwrap_or("").is_empty() => Some(s.as_str().unwrap().to_owned()),
-                _ => None,
-            };
-            let clone_path = config["clone_path"].as_str().expect("field 'clone_path' is missing");
-            let repositories = config.get("repositories").expect("field 'repositories' is missing");
-
-            match Repositories::read_from(|| Ok(repositories.clone())) {
-                Ok(repos) => Ok(Self {
-                    user_name: UserName(user_name.to_string()),
-                    user_email: UserEmail(user_email),
-                    clone_path: ClonePath(clone_path.to_string()),
-                    repositories: repos,
-
-                }),
-                Err(err) => Err(err),
+        reader().and_then(from_value)
+    }
+
+    /// Set one of the `SETTABLE_KEYS` to `value`, which is checked like
+    /// the value in the config file. An empty value removes the key,
+    /// except for the required `clone_path`.
+    pub fn set(&mut self, key: &str, value: &str) -> Result<()> {
+        let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidInput, message);
+        if !SETTABLE_KEYS.contains(&key) {
+            return Err(invalid(format!("'{}' cannot be set, use one of {}", key, SETTABLE_KEYS.join(", "))));
+        }
+
+        let mut table = self.to_toml();
+        if value.is_empty() {
+            if key == "clone_path" {
+                return Err(invalid("'clone_path' cannot be empty".to_string()));
             }
-        })
+            table.remove(key);
+        } else if key == "jobs" {
+            let jobs = value.parse().map_err(|_| invalid("'jobs' must be a positive number".to_string()))?;
+            table.insert(key.to_string(), Value::Integer(jobs));
+        } else if key == "host" || key == "api_url" {
+            // every later command would fail on an invalid url
+            let checked = match key {
+                "host" => Host::try_from(value.to_string()).map(|_| ()),
+                _ => ApiUrl::try_from(value.to_string()).map(|_| ()),
+            };
+            checked.map_err(|e| invalid(format!("'{}': {}", key, e)))?;
+            table.insert(key.to_string(), Value::String(value.to_string()));
+        } else {
+            table.insert(key.to_string(), Value::String(value.to_string()));
+        }
+
+        *self = from_value(Value::Table(table))?;
+        Ok(())
+    }
+
+    /// The profile as written to the config file, `retry` as sub-table
+    pub fn to_toml(&self) -> Table {
+        let mut table = Table::new();
+        if !self.user_name.0.is_empty() {
+            table.insert("user_name".to_string(), Value::String(self.user_name.0.clone()));
+        }
+        if let Some(email) = &self.user_email.0 {
+            table.insert("user_email".to_string(), Value::String(email.clone()));
+        }
+        table.insert("clone_path".to_string(), Value::String(self.clone_path.0.clone()));
+        if let Some(host) = &self.host {
+            table.insert("host".to_string(), Value::String(host.0.clone()));
+        }
+        if let Some(api_url) = &self.api_url {
+            table.insert("api_url".to_string(), Value::String(api_url.0.clone()));
+        }
+        if let Some(jobs) = &self.jobs {
+            table.insert("jobs".to_string(), Value::Integer(jobs.0 as i64));
+        }
+        let repositories = self.repositories.0.iter().map(Repository::to_toml).collect();
+        table.insert("repositories".to_string(), Value::Array(repositories));
+        if !self.groups.is_empty() {
+            let groups = self
+                .groups
+                .iter()
+                .map(|(name, group)| {
+                    let members = group.0.iter().map(|m| Value::String(m.clone())).collect();
+                    (name.clone(), Value::Array(members))
+                })
+                .collect();
+            table.insert("groups".to_string(), Value::Table(groups));
+        }
+        table.extend(self.repository_defaults().to_toml());
+        if let Some(retry) = &self.retry {
+            table.insert("retry".to_string(), Value::Table(retry.to_toml()));
+        }
+        table
     }
 }
 
@@ -190,28 +810,114 @@ impl BardoConfig {
         &self.profiles
     }
 
+    /// Keys of the config file bardo does not know, they are ignored
+    pub fn warnings(&self) -> &[Problem] {
+        &self.warnings
+    }
+
+    /// Read all profiles, failing with the errors of all of them
     pub fn read_from<F>(reader: F) -> Result<Self>
     where
         F: Fn() -> Result<Value>,
     {
-        reader().map(|toml| {
-            let mut map: HashMap<String, Configuration> = HashMap::new();
-            for (k, v) in toml.as_table().as_ref().expect("file has invalid format").iter() {
-                let _ = Configuration::read_from(|| Ok(v.clone())).map(|creds| map.insert(k.to_string(), creds));
-            }
+        let (config, problems) = Self::check(reader()?);
+        config.with_problems(problems)
+    }
 
-            Self {
-                profiles: map,
+    /// Read all profiles that are valid and return the problems of all
+    /// profiles instead of stopping at the first
+    pub fn check(toml: Value) -> (Self, Vec<Problem>) {
+        let mut profiles = HashMap::new();
+        let mut problems = Vec::new();
+        match toml {
+            Value::Table(table) => {
+                for (name, section) in table {
+                    let (configuration, found) = deserialize(Some(&name), section);
+                    problems.extend(found);
+                    if let Some(configuration) = configuration {
+                        profiles.insert(name, configuration);
+                    }
+                }
             }
-        })
+            _ => problems.push(Problem::error(None, Vec::new(), "file has invalid format")),
+        }
+
+        let config = Self {
+            profiles: profiles,
+            warnings: Vec::new(),
+        };
+        (config, problems)
+    }
+
+    fn with_problems(mut self, problems: Vec<Problem>) -> Result<Self> {
+        if problems.iter().any(Problem::is_error) {
+            return Err(to_error(&problems));
+        }
+        self.warnings = problems;
+        Ok(self)
+    }
+
+    pub fn get_profiles_mut(&mut self) -> &mut HashMap<String, Configuration> {
+        &mut self.profiles
     }
 
     pub fn write_to<F>(&self, writer: F) -> Result<()>
     where
         F: Fn(&BardoConfig) -> Result<()>,
     {
-        writer(&self)
+        writer(self)
     }
+
+    /// Merge the profiles into `document`, the current content of the
+    /// config file. Comments and keys bardo does not know are kept,
+    /// profiles that were removed are dropped.
+    pub fn to_toml_string(&self, document: &str) -> Result<String> {
+        let mut document = parse(document)?;
+        retain_profiles(&mut document, |p| self.profiles.contains_key(p));
+
+        let mut names: Vec<&String> = self.profiles.keys().collect();
+        names.sort();
+        for name in names {
+            let section = self.profiles[name].to_toml();
+            let table = profile_table(&mut document, name);
+            merge(table, &section, PROFILE_KEYS);
+            merge_table(table, "retry", section.get("retry").and_then(Value::as_table), RETRY_KEYS);
+        }
+        Ok(document.to_string())
+    }
+}
+
+/// The keys of a profile in the order they are added to the config file,
+/// `retry` is merged separately
+const PROFILE_KEYS: &[&str] = &[
+    "user_name",
+    "user_email",
+    "clone_path",
+    "host",
+    "api_url",
+    "jobs",
+    "default_branch",
+    "reviewers",
+    "team_reviewers",
+    "assignees",
+    "labels_file",
+    "repositories",
+    "groups",
+];
+const RETRY_KEYS: &[&str] = &["max_attempts", "initial_backoff_ms", "max_backoff_ms", "jitter", "statuses", "methods"];
+
+/// A writer for `BardoConfig::write_to` that replaces the config file
+/// atomically, e.g. `config.write_to(|c| write_config(c, &WriteOptions::new().backup(true)))`
+pub fn write_config(config: &BardoConfig, options: &WriteOptions) -> Result<()> {
+    let path = config_file().ok_or(io::Error::new(io::ErrorKind::NotFound, "cannot locate config file"))?;
+    let document = match read_str(&path) {
+        Ok(document) => document,
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e),
+    };
+
+    write_config_dir()?;
+    options.write(&path, config.to_toml_string(&document)?)
 }
 
 // Structure config
@@ -219,12 +925,68 @@ impl BardoConfig {
 // clone_path = "/Users/seka/projects/mttrbit/bardo-repos"
 // repositories = [
 //   {org = "crvshlab", name = "test"}
-// , {org = "crvshlab", regex = "nodejs-*"}
+// , {org = "crvshlab", regex = "nodejs-.*"}
 // ]
 pub fn config_file() -> Option<PathBuf> {
     config_dir().map(|h| h.join("config"))
 }
 
+/// Read the config file at `path` as it is written, keeping `${NAME}`
+/// references, to change and write it back with `write_config`
+pub fn load_config(path: &Path) -> Result<BardoConfig> {
+    let content = read_str(path)?;
+    let toml = parse_config(path, &content).map_err(|p| to_error(&[*p]))?;
+    let (config, problems) = BardoConfig::check(toml);
+    config.with_problems(located(problems, path, &content))
+}
+
+/// Read the config file at `path` with the `${NAME}` references of
+/// `profile` resolved. Fails with all errors, each with its line and
+/// column; unknown keys end up in `BardoConfig::warnings`.
+pub fn read_config(path: &Path, profile: &str) -> Result<BardoConfig> {
+    let content = read_str(path)?;
+    let toml = parse_config(path, &content).map_err(|p| to_error(&[*p]))?;
+    let toml = resolve_profile(toml, profile)
+        .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path.display(), e)))?;
+
+    let (config, problems) = BardoConfig::check(toml);
+    config.with_problems(located(problems, path, &content))
+}
+
+/// All problems of the config file at `path`: syntax errors, invalid
+/// values and unknown keys. `${NAME}` references are not resolved.
+pub fn validate_config(path: &Path) -> Result<Vec<Problem>> {
+    let content = read_str(path)?;
+    match parse_config(path, &content) {
+        Ok(toml) => Ok(located(BardoConfig::check(toml).1, path, &content)),
+        Err(problem) => Ok(vec![*problem]),
+    }
+}
+
+fn parse_config(path: &Path, content: &str) -> std::result::Result<Value, Box<Problem>> {
+    content.parse::<Value>().map_err(|e| {
+        let message = e.to_string();
+        // the position is part of the problem
+        let message = match message.find(" at line ") {
+            Some(i) => &message[..i],
+            None => &message[..],
+        };
+        let mut problem = Problem::error(None, Vec::new(), message);
+        problem.locate(path, content);
+        Box::new(match e.line_col() {
+            Some((line, col)) => problem.at(line, col),
+            None => problem,
+        })
+    })
+}
+
+fn located(mut problems: Vec<Problem>, path: &Path, content: &str) -> Vec<Problem> {
+    for problem in problems.iter_mut() {
+        problem.locate(path, content);
+    }
+    problems
+}
+
 #[cfg(test)]
 mod config_tests {
     use super::*;
@@ -261,7 +1023,7 @@ mod config_tests {
             repositories = [
               {org = "crvshlab", name="repo1"},
               {org = "crvshlab", name="repo2"},
-              {org = "crvshlab", regex="node-*"},
+              {org = "crvshlab", regex="node-.*"},
             ]
         "#;
 
@@ -273,6 +1035,167 @@ mod config_tests {
         assert_eq!("repo1".to_string(), config.repositories.0[0].name().unwrap().0);
     }
 
+    #[test]
+    fn test_configuration_enterprise_host() {
+        let toml_str = r#"
+            user_name = "octocat"
+            clone_path = "/path"
+            host = "github.example.com"
+            api_url = "https://github.example.com/api/v3"
+            repositories = []
+        "#;
+
+        let reader = || read_bytes(toml_str.as_bytes());
+        let config = Configuration::read_from(reader).expect("invalid format");
+        assert_eq!("github.example.com", config.host().unwrap().0);
+        assert_eq!("https://github.example.com/api/v3", config.api_url().unwrap().0);
+        assert!(config.jobs().is_none());
+        assert_eq!("https://github.example.com/", config.host().unwrap().web_url().unwrap().as_str());
+        assert_eq!("https://github.example.com/", config.web_url().unwrap().as_str());
+    }
+
+    #[test]
+    fn test_web_url_of_api_url() {
+        let web_url = |api_url: &str| ApiUrl(api_url.to_string()).web_url().unwrap().to_string();
+        assert_eq!("https://github.com/", web_url("https://api.github.com"));
+        assert_eq!("https://github.example.com/", web_url("https://github.example.com/api/v3/"));
+        assert_eq!("http://localhost:8080/", web_url("http://localhost:8080/api/v3"));
+
+        let toml_str = r#"
+            clone_path = "/path"
+            api_url = "https://github.example.com/api/v3"
+            repositories = []
+        "#;
+        let config = Configuration::read_from(|| read_bytes(toml_str.as_bytes())).expect("invalid format");
+        assert_eq!("https://github.example.com/", config.web_url().unwrap().as_str());
+
+        let toml_str = r#"
+            clone_path = "/path"
+            host = "http://localhost:8080/"
+            repositories = []
+        "#;
+        let config = Configuration::read_from(|| read_bytes(toml_str.as_bytes())).expect("invalid format");
+        assert_eq!("http://localhost:8080/", config.web_url().unwrap().as_str());
+    }
+
+    #[test]
+    fn test_referenced_api_url_is_checked_when_resolved() {
+        let dir = std::env::temp_dir().join(format!("bardo-referenced-api-url-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config");
+        std::fs::write(
+            &path,
+            "[default]\nclone_path = \"/path\"\napi_url = \"${BARDO_TEST_API_URL}\"\n",
+        )
+        .unwrap();
+
+        let problems = validate_config(&path).unwrap();
+        assert!(problems.is_empty(), "{:?}", problems);
+
+        std::env::set_var("BARDO_TEST_API_URL", "https://github.example.com/api/v3");
+        let config = read_config(&path, "default").unwrap();
+        assert_eq!("https://github.example.com/api/v3", config.get_profiles()["default"].api_url().unwrap().0);
+
+        std::env::set_var("BARDO_TEST_API_URL", "github.example.com");
+        let error = read_config(&path, "default").err().unwrap();
+        assert!(error.to_string().contains("'api_url': 'github.example.com' is no url"), "{}", error);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_invalid_host_and_api_url_are_located() {
+        let toml_str = r#"[default]
+clone_path = "/path"
+host = "github example.com"
+
+[second]
+clone_path = "/path"
+api_url = "ftp://github.example.com/api/v3"
+"#;
+
+        let (config, problems) = BardoConfig::check(read_bytes(toml_str.as_bytes()).unwrap());
+        assert!(config.get_profiles().is_empty());
+        let mut problems: Vec<String> = problems
+            .into_iter()
+            .map(|mut p| {
+                p.locate(Path::new("config"), toml_str);
+                p.to_string()
+            })
+            .collect();
+        problems.sort();
+        assert_eq!(
+            vec![
+                "config:3:1: profile 'default', 'host': 'https://github example.com' is no url: invalid international domain name",
+                "config:7:1: profile 'second', 'api_url': 'ftp://github.example.com/api/v3' is no http or https url",
+            ],
+            problems
+        );
+    }
+
+    #[test]
+    fn test_configuration_jobs() {
+        let toml_str = r#"
+            user_name = "octocat"
+            clone_path = "/path"
+            jobs = 8
+            repositories = []
+        "#;
+
+        let reader = || read_bytes(toml_str.as_bytes());
+        let config = Configuration::read_from(reader).expect("invalid format");
+        assert_eq!(8, config.jobs().unwrap().0);
+
+        let toml_str = r#"
+            user_name = "octocat"
+            clone_path = "/path"
+            jobs = 0
+            repositories = []
+        "#;
+
+        let reader = || read_bytes(toml_str.as_bytes());
+        assert!(Configuration::read_from(reader).is_err());
+    }
+
+    #[test]
+    fn test_configuration_retry() {
+        let toml_str = r#"
+            user_name = "octocat"
+            clone_path = "/path"
+            repositories = []
+
+            [retry]
+            max_attempts = 5
+            jitter = false
+            statuses = [502, 503]
+            methods = ["get", "PUT"]
+        "#;
+
+        let reader = || read_bytes(toml_str.as_bytes());
+        let config = Configuration::read_from(reader).expect("invalid format");
+        let retry = config.retry().unwrap();
+        assert_eq!(Some(5), retry.max_attempts());
+        assert_eq!(None, retry.initial_backoff_ms());
+        assert_eq!(Some(false), retry.jitter());
+        assert_eq!(Some(&vec![502, 503]), retry.statuses());
+        assert_eq!(Some(&vec!["GET".to_string(), "PUT".to_string()]), retry.methods());
+    }
+
+    #[test]
+    fn test_configuration_bad_retry() {
+        let toml_str = r#"
+            user_name = "octocat"
+            clone_path = "/path"
+            repositories = []
+
+            [retry]
+            max_attempts = "many"
+        "#;
+
+        let reader = || read_bytes(toml_str.as_bytes());
+        assert_eq!(true, Configuration::read_from(reader).is_err());
+    }
+
     #[test]
     fn test_bad_configuration_missing_opts() {
         let toml_str = r#"
@@ -301,6 +1224,202 @@ mod config_tests {
         assert_eq!(true, config.is_err());
     }
 
+    #[test]
+    fn test_check_reports_all_problems() {
+        let toml_str = r#"[default]
+clone_path = "/path"
+repositories = [
+  {org = "crvshlab", name = "repo1", tag = "node"},
+]
+jobs = 0
+
+[second]
+clone_pth = "/path"
+
+[third]
+clone_path = "/path"
+"#;
+
+        let (config, problems) = BardoConfig::check(read_bytes(toml_str.as_bytes()).unwrap());
+        assert_eq!(vec!["third"], config.get_profiles().keys().collect::<Vec<_>>());
+        assert!(config.get_profiles()["third"].repositories().is_empty());
+
+        let mut problems: Vec<String> = problems
+            .into_iter()
+            .map(|mut p| {
+                p.locate(Path::new("config"), toml_str);
+                p.to_string()
+            })
+            .collect();
+        problems.sort();
+        assert_eq!(
+            vec![
+                "config:4:38: profile 'default', 'repositories[0].tag': unknown key, it is ignored",
+                "config:6:1: profile 'default', 'jobs': must be a positive number",
+                "config:8:2: profile 'second': missing field `clone_path`",
+                "config:9:1: profile 'second', 'clone_pth': unknown key, it is ignored",
+            ],
+            problems
+        );
+
+        assert!(BardoConfig::read_from(|| read_bytes(toml_str.as_bytes())).is_err());
+    }
+
+    #[test]
+    fn test_unknown_keys_are_warnings() {
+        let toml_str = r#"
+            [default]
+            clone_path = "/path"
+            editor = "vim"
+        "#;
+
+        let config = BardoConfig::read_from(|| read_bytes(toml_str.as_bytes())).expect("invalid format");
+        assert_eq!(1, config.warnings().len());
+        assert_eq!(false, config.warnings()[0].is_error());
+    }
+
+    #[test]
+    fn test_write_configuration() {
+        let toml_str = r#"# bardo profiles
+[default]
+user_name = "octocat"
+clone_path = "/path" # absolute
+editor = "vim"
+repositories = [
+  {org = "crvshlab", name = "repo1"},
+]
+
+[default.retry]
+max_attempts = 5
+
+[gone]
+user_name = "octocat"
+clone_path = "/gone"
+repositories = []
+"#;
+
+        let reader = || read_bytes(toml_str.as_bytes());
+        let mut config = BardoConfig::read_from(reader).expect("invalid format");
+        config.get_profiles_mut().remove("gone");
+        let default = config.get_profiles_mut().get_mut("default").unwrap();
+        default.jobs = Some(Jobs(2));
+        default.retry.as_mut().unwrap().jitter = Some(false);
+        default.repositories_mut().add(Repository::new(
+            Org("crvshlab".to_string()),
+            Some(Name("repo2".to_string())),
+            None,
+        ));
+
+        assert_eq!(
+            r#"# bardo profiles
+[default]
+user_name = "octocat"
+clone_path = "/path" # absolute
+editor = "vim"
+repositories = [
+  { org = "crvshlab", name = "repo1" },
+  { org = "crvshlab", name = "repo2" },
+]
+jobs = 2
+
+[default.retry]
+max_attempts = 5
+jitter = false
+"#,
+            config.to_toml_string(toml_str).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_configuration_groups_and_tags() {
+        let toml_str = r#"
+            clone_path = "/path"
+            repositories = [
+              {org = "crvshlab", name = "api", tags = ["node", "service"]},
+              {org = "crvshlab", regex = "^rust-"},
+            ]
+
+            [groups]
+            backend = ["crvshlab/api", "crvshlab/rust-core"]
+        "#;
+
+        let reader = || read_bytes(toml_str.as_bytes());
+        let config = Configuration::read_from(reader).expect("invalid format");
+        assert!(config.repositories()[0].has_tag("service"));
+        assert!(config.repositories()[1].tags().is_empty());
+        let backend = &config.groups()["backend"];
+        assert!(backend.contains("crvshlab", "rust-core"));
+        assert!(!backend.contains("crvshlab", "web"));
+
+        let toml_str = r#"
+            [default]
+            clone_path = "/path"
+
+            [default.groups]
+            backend = ["crvshlab/api", "api"]
+        "#;
+
+        let (_, problems) = BardoConfig::check(read_bytes(toml_str.as_bytes()).unwrap());
+        assert_eq!(
+            "profile 'default', 'groups.backend': 'api' is no repository, expected 'org/name'",
+            problems[0].to_string()
+        );
+    }
+
+    #[test]
+    fn test_repository_settings_fall_back_to_profile() {
+        let toml_str = r#"
+            clone_path = "/path"
+            default_branch = "main"
+            reviewers = ["octocat"]
+            repositories = [
+              {org = "crvshlab", name = "legacy", default_branch = "master", clone_url = "https://example.com/legacy.git"},
+              {org = "crvshlab", name = "api", reviewers = [], assignees = ["hubot"]},
+            ]
+        "#;
+
+        let reader = || read_bytes(toml_str.as_bytes());
+        let config = Configuration::read_from(reader).expect("invalid format");
+        let legacy = config.settings_of(&config.repositories()[0]);
+        assert_eq!(Some("master"), legacy.default_branch());
+        assert_eq!(Some(&vec!["octocat".to_string()]), legacy.reviewers());
+        assert_eq!(Some("https://example.com/legacy.git"), legacy.clone_url());
+
+        let api = config.settings_of(&config.repositories()[1]);
+        assert_eq!(Some("main"), api.default_branch());
+        assert_eq!(Some(&Vec::new()), api.reviewers());
+        assert_eq!(Some(&vec!["hubot".to_string()]), api.assignees());
+        assert_eq!(None, api.clone_url());
+    }
+
+    #[test]
+    fn test_set_configuration_keys() {
+        let mut config = Configuration::new(ClonePath("/path".to_string()));
+        config.set("jobs", "3").unwrap();
+        config.set("default_branch", "main").unwrap();
+        config.set("clone_path", "/other").unwrap();
+        assert_eq!(3, config.jobs().unwrap().0);
+        assert_eq!(Some("main"), config.repository_defaults().default_branch());
+        assert_eq!("/other", config.clone_path().0);
+
+        config.set("default_branch", "").unwrap();
+        assert_eq!(None, config.repository_defaults().default_branch());
+
+        assert!(config.set("jobs", "0").is_err());
+        assert!(config.set("jobs", "many").is_err());
+        assert!(config.set("clone_path", "").is_err());
+        assert!(config.set("repositories", "[]").is_err());
+        assert_eq!(3, config.jobs().unwrap().0);
+
+        config.set("host", "https://github.example.com/").unwrap();
+        config.set("api_url", "http://localhost:8080/api/v3").unwrap();
+        let e = config.set("host", "github example.com").unwrap_err();
+        assert_eq!("'host': 'https://github example.com' is no url: invalid international domain name", e.to_string());
+        assert!(config.set("api_url", "localhost:8080").is_err());
+        assert_eq!("https://github.example.com/", config.host().unwrap().0);
+        assert_eq!("http://localhost:8080/api/v3", config.api_url().unwrap().0);
+    }
+
     #[test]
     fn test_add_repo_to_configuration() {
         let toml_str = r#"
@@ -308,7 +1427,7 @@ mod config_tests {
             repositories = [
               {org = "crvshlab", name="repo1"},
               {org = "crvshlab", name="repo2"},
-              {org = "crvshlab", regex="node-*"},
+              {org = "crvshlab", regex="node-.*"},
             ]
         "#;
 
@@ -369,7 +1488,7 @@ mod config_tests {
 // clone_path = "/Users/seka/projects/mttrbit/bardo-repos"
 // repositories = [
 //   {org = "crvshlab", name = "test"}
-// , {org = "crvshlab", regex = "nodejs-*"}
+// , {org = "crvshlab", regex = "nodejs-.*"}
 // ]
 // "#;
 
@@ -1,9 +1,8 @@
 use std::io;
-use std::path::PathBuf;
 
-use crate::credentials::{BardoCredentials, credentials_file};
-use crate::config::{BardoConfig, config_file};
-use crate::file::read_toml;
+use crate::credentials::{AccessToken, BardoCredentials, Credentials, read_credentials};
+use crate::config::{BardoConfig, Configuration, config_file, read_config};
+use crate::secret::resolve_profile;
 
 pub use std::io::Result;
 
@@ -11,6 +10,7 @@ pub struct BardoContext {
     profile: String,
     credentials: BardoCredentials,
     config: BardoConfig,
+    access_token: Option<AccessToken>,
 }
 
 impl BardoContext {
@@ -31,27 +31,43 @@ impl BardoContext {
         &self.config
     }
 
+    /// The config of the selected profile, `None` if the config file has
+    /// no such profile
+    pub fn section(&self) -> Option<&Configuration> {
+        self.config.get_profiles().get(&self.profile)
+    }
+
+    /// The token of the profile, read from the credentials or printed by
+    /// `bardo_access_token_command`. `None` for GitHub Apps and OAuth Apps
+    /// that are not logged in yet.
+    pub fn access_token(&self) -> Option<&AccessToken> {
+        self.access_token.as_ref()
+    }
+
+    /// Read the credentials and the config. Secrets are resolved for the
+    /// selected profile only: `${NAME}` references in both files and the
+    /// token command.
     pub fn init(profile: &str) -> Result<Self> {
-        let toml_reader = |buf: PathBuf| read_toml(buf.as_path());
-
-        let creds_reader = || credentials_file()
-            .ok_or(io::Error::new(io::ErrorKind::InvalidData, "cannot read credentials file"))
-            .and_then(toml_reader);
-
-        let config_reader = || config_file()
-            .ok_or(io::Error::new(io::ErrorKind::InvalidData, "cannot read config file"))
-            .and_then(toml_reader);
-
-        let credentials: Result<BardoCredentials> = BardoCredentials::read_from(creds_reader);
-        let config: Result<BardoConfig> = BardoConfig::read_from(config_reader);
-
-        match (credentials, config) {
-            (Ok(a), Ok(b)) => Ok(Self {
-                credentials: a,
-                config: b,
-                profile: profile.to_string(),
-            }),
-            _ => Err(io::Error::new(io::ErrorKind::InvalidData, "error while initializing context")),
-        }
+        let creds_reader = || read_credentials().and_then(|toml| resolve_profile(toml, profile));
+
+        let config_path = config_file().ok_or(io::Error::new(io::ErrorKind::InvalidData, "cannot read config file"))?;
+
+        let credentials: BardoCredentials = BardoCredentials::read_profile(creds_reader, profile)?;
+        let config: BardoConfig = read_config(&config_path, profile)?;
+
+        let access_token = match credentials.profiles().get(profile) {
+            Some(Credentials::Command { command }) => Some(command.run().map_err(|e| {
+                io::Error::new(e.kind(), format!("credentials of profile '{}': {}", profile, e))
+            })?),
+            Some(c) => c.access_token().map(|t| AccessToken(t.0.clone())),
+            None => None,
+        };
+
+        Ok(Self {
+            credentials: credentials,
+            config: config,
+            profile: profile.to_string(),
+            access_token: access_token,
+        })
     }
 }
@@ -1,87 +1,313 @@
 use std::collections::HashMap;
+use std::fmt;
 use std::io;
 use std::path::PathBuf;
+use toml::value::{Table, Value};
 
-use crate::file::config_dir;
+use crate::crypt::{decrypt, encrypt, passphrase};
+use crate::document::{merge, parse, profile_table, retain_profiles, set};
+use crate::file::{config_dir, read, read_bytes, write_config_dir, write_private, WriteOptions};
 
 pub use io::Result;
 
 pub struct ClientId(pub String);
 pub struct ClientSecret(pub String);
-#[derive(Debug)]
 pub struct AccessToken(pub String);
+pub struct AppId(pub u64);
+pub struct InstallationId(pub u64);
+pub struct PrivateKeyPath(pub PathBuf);
+pub struct TokenCommand(pub String);
+
+impl fmt::Debug for AccessToken {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // tokens must not end up in logs
+        write!(f, "AccessToken(***)")
+    }
+}
 
-pub struct Credentials {
-    client_id: ClientId,
-    client_secret: ClientSecret,
-    access_token: Option<AccessToken>,
+/// The ways a profile can authenticate. The kind is derived from the keys
+/// set in the profile, see `Credentials::read_from`.
+pub enum Credentials {
+    /// `bardo_access_token`, e.g. a personal access token
+    Token { access_token: AccessToken },
+    /// `bardo_client_id` of an OAuth App, with the token obtained through
+    /// `bardo auth login` once logged in
+    OAuth {
+        client_id: ClientId,
+        client_secret: Option<ClientSecret>,
+        access_token: Option<AccessToken>,
+    },
+    /// `bardo_app_id`, `bardo_private_key_path` and `bardo_installation_id`
+    /// of a GitHub App installation
+    App {
+        app_id: AppId,
+        private_key_path: PrivateKeyPath,
+        installation_id: InstallationId,
+    },
+    /// `bardo_access_token_command`, a shell command printing the token
+    Command { command: TokenCommand },
 }
 
+#[derive(Default)]
 pub struct BardoCredentials {
     profiles: HashMap<String, Credentials>,
+    warnings: Vec<String>,
 }
 
 impl Clone for Credentials {
     fn clone(&self) -> Self {
-        Self {
-            client_id: ClientId(self.client_id.0.clone()),
-            client_secret: ClientSecret(self.client_secret.0.clone()),
-            access_token: match &self.access_token {
-                Some(o) => Some(AccessToken(o.0.clone())),
-                _ => None,
+        match self {
+            Credentials::Token { access_token } => Credentials::Token {
+                access_token: AccessToken(access_token.0.clone()),
+            },
+            Credentials::OAuth {
+                client_id,
+                client_secret,
+                access_token,
+            } => Credentials::OAuth {
+                client_id: ClientId(client_id.0.clone()),
+                client_secret: client_secret.as_ref().map(|s| ClientSecret(s.0.clone())),
+                access_token: access_token.as_ref().map(|t| AccessToken(t.0.clone())),
+            },
+            Credentials::App {
+                app_id,
+                private_key_path,
+                installation_id,
+            } => Credentials::App {
+                app_id: AppId(app_id.0),
+                private_key_path: PrivateKeyPath(private_key_path.0.clone()),
+                installation_id: InstallationId(installation_id.0),
+            },
+            Credentials::Command { command } => Credentials::Command {
+                command: TokenCommand(command.0.clone()),
             },
         }
     }
 }
 
+fn invalid<S: Into<String>>(message: S) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
+
+/// A non empty string value, `None` if the key is not set
+fn get_str<'a>(section: &'a Value, key: &str) -> Result<Option<&'a str>> {
+    match section.get(key) {
+        None => Ok(None),
+        Some(Value::String(s)) if !s.trim().is_empty() => Ok(Some(s)),
+        Some(Value::String(_)) => Err(invalid(format!("'{}' is empty", key))),
+        Some(_) => Err(invalid(format!("'{}' must be a string", key))),
+    }
+}
+
+/// A positive integer value, `None` if the key is not set
+fn get_u64(section: &Value, key: &str) -> Result<Option<u64>> {
+    match section.get(key) {
+        None => Ok(None),
+        Some(Value::Integer(i)) if *i > 0 => Ok(Some(*i as u64)),
+        Some(_) => Err(invalid(format!("'{}' must be a positive integer", key))),
+    }
+}
+
+fn require<T>(value: Option<T>, key: &str, kind: &str) -> Result<T> {
+    value.ok_or_else(|| invalid(format!("'{}' is missing, it is required for {}", key, kind)))
+}
+
+const APP_KEYS: &[&str] = &["bardo_app_id", "bardo_private_key_path", "bardo_installation_id"];
+const OAUTH_KEYS: &[&str] = &["bardo_client_id", "bardo_client_secret"];
+
 impl Credentials {
-    fn new(client_id: ClientId, client_secret: ClientSecret, access_token: Option<AccessToken>) -> Self {
-        Self {
-            client_id: client_id,
-            client_secret: client_secret,
-            access_token: access_token,
+    /// A short name of the kind for messages
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Credentials::Token { .. } => "an access token",
+            Credentials::OAuth { .. } => "an OAuth App",
+            Credentials::App { .. } => "a GitHub App",
+            Credentials::Command { .. } => "a token command",
         }
     }
 
-    pub fn client_id(&self) -> &ClientId {
-        &self.client_id
+    pub fn client_id(&self) -> Option<&ClientId> {
+        match self {
+            Credentials::OAuth { client_id, .. } => Some(client_id),
+            _ => None,
+        }
     }
 
-    pub fn client_secret(&self) -> &ClientSecret {
-        &self.client_secret
+    pub fn client_secret(&self) -> Option<&ClientSecret> {
+        match self {
+            Credentials::OAuth { client_secret, .. } => client_secret.as_ref(),
+            _ => None,
+        }
     }
 
+    /// The stored token, GitHub Apps and token commands have none
     pub fn access_token(&self) -> Option<&AccessToken> {
-        self.access_token.as_ref()
+        match self {
+            Credentials::Token { access_token } => Some(access_token),
+            Credentials::OAuth { access_token, .. } => access_token.as_ref(),
+            _ => None,
+        }
     }
 
-    pub fn access_token_mut(&mut self) -> &mut Option<AccessToken> {
-        &mut self.access_token
+    /// Store a token obtained through a login. Profiles whose token is
+    /// not stored, GitHub Apps and token commands, refuse it.
+    pub fn set_access_token(&mut self, token: AccessToken) -> Result<()> {
+        match self {
+            Credentials::Token { access_token } => *access_token = token,
+            Credentials::OAuth { access_token, .. } => *access_token = Some(token),
+            _ => return Err(invalid(format!("{} does not store an access token", self.kind()))),
+        }
+        Ok(())
     }
 
+    /// Read one profile. The kind is taken from the keys that are set:
+    /// `bardo_app_id` and the other GitHub App keys, `bardo_access_token_command`,
+    /// `bardo_client_id` or only `bardo_access_token`. Keys of different
+    /// kinds must not be mixed.
     pub fn read_from<F>(reader: F) -> Result<Self>
     where
-        F: Fn() -> Result<toml::Value>,
+        F: Fn() -> Result<Value>,
     {
-        reader().map(|section| {
-            let str_client_id = section["bardo_client_id"].as_str().expect("field 'bardo_client_id' is missing");
-            let str_client_secret = section["bardo_client_secret"].as_str().expect("field 'bardo_client_secret' is missing");
-            let client_id = ClientId(str_client_id.to_string());
-            let client_secret = ClientSecret(str_client_secret.to_string());
-            let access_token = section.get("bardo_access_token").map(|f| AccessToken(f.as_str().unwrap().to_string()));
-            Self {
-                client_id: client_id,
-                client_secret: client_secret,
-                access_token: access_token,
+        let section = reader()?;
+        if !section.is_table() {
+            return Err(invalid("credentials must be a table"));
+        }
+        let has = |keys: &[&str]| keys.iter().any(|k| section.get(*k).is_some());
+
+        let access_token = get_str(&section, "bardo_access_token")?.map(|t| AccessToken(t.to_string()));
+        let command = get_str(&section, "bardo_access_token_command")?;
+
+        if has(APP_KEYS) {
+            if has(OAUTH_KEYS) || access_token.is_some() || command.is_some() {
+                return Err(invalid("GitHub App keys cannot be combined with a token or an OAuth App"));
             }
-        })
+            let kind = "a GitHub App";
+            return Ok(Credentials::App {
+                app_id: AppId(require(get_u64(&section, "bardo_app_id")?, "bardo_app_id", kind)?),
+                private_key_path: PrivateKeyPath(PathBuf::from(require(
+                    get_str(&section, "bardo_private_key_path")?,
+                    "bardo_private_key_path",
+                    kind,
+                )?)),
+                installation_id: InstallationId(require(
+                    get_u64(&section, "bardo_installation_id")?,
+                    "bardo_installation_id",
+                    kind,
+                )?),
+            });
+        }
+
+        if let Some(command) = command {
+            if access_token.is_some() {
+                return Err(invalid("set either 'bardo_access_token' or 'bardo_access_token_command'"));
+            }
+            return Ok(Credentials::Command {
+                command: TokenCommand(command.to_string()),
+            });
+        }
+
+        if has(OAUTH_KEYS) {
+            let client_id = require(get_str(&section, "bardo_client_id")?, "bardo_client_id", "an OAuth App")?;
+            return Ok(Credentials::OAuth {
+                client_id: ClientId(client_id.to_string()),
+                // logins through the device flow need no secret
+                client_secret: get_str(&section, "bardo_client_secret")?.map(|s| ClientSecret(s.to_string())),
+                access_token: access_token,
+            });
+        }
+
+        match access_token {
+            Some(access_token) => Ok(Credentials::Token {
+                access_token: access_token,
+            }),
+            None => Err(invalid(
+                "no credentials, set 'bardo_access_token', 'bardo_access_token_command', 'bardo_client_id' or 'bardo_app_id'",
+            )),
+        }
     }
 
     pub fn write_to<F>(&self, writer: F) -> Result<()>
     where
         F: Fn(&Credentials) -> Result<()>,
     {
-        writer(&self)
+        writer(self)
+    }
+
+    /// Like `to_toml`, with the values of secrets replaced by `***`, for
+    /// printing
+    pub fn redacted(&self) -> Table {
+        let mut table = self.to_toml();
+        for (key, value) in table.iter_mut() {
+            if SECRET_KEYS.contains(&key.as_str()) {
+                *value = Value::String("***".to_string());
+            }
+        }
+        table
+    }
+
+    /// The keys of the kind as written to the credentials file
+    pub fn to_toml(&self) -> Table {
+        let mut table = Table::new();
+        let mut put = |key: &str, value: Value| {
+            table.insert(key.to_string(), value);
+        };
+        match self {
+            Credentials::Token { access_token } => put("bardo_access_token", Value::String(access_token.0.clone())),
+            Credentials::OAuth {
+                client_id,
+                client_secret,
+                access_token,
+            } => {
+                put("bardo_client_id", Value::String(client_id.0.clone()));
+                if let Some(secret) = client_secret {
+                    put("bardo_client_secret", Value::String(secret.0.clone()));
+                }
+                if let Some(token) = access_token {
+                    put("bardo_access_token", Value::String(token.0.clone()));
+                }
+            }
+            Credentials::App {
+                app_id,
+                private_key_path,
+                installation_id,
+            } => {
+                put("bardo_app_id", Value::Integer(app_id.0 as i64));
+                put(
+                    "bardo_private_key_path",
+                    Value::String(private_key_path.0.to_string_lossy().into_owned()),
+                );
+                put("bardo_installation_id", Value::Integer(installation_id.0 as i64));
+            }
+            Credentials::Command { command } => put("bardo_access_token_command", Value::String(command.0.clone())),
+        }
+        table
+    }
+}
+
+impl TokenCommand {
+    /// Run the command through `sh` and read the token from its output.
+    /// The output is never part of an error, it may contain the secret.
+    pub fn run(&self) -> Result<AccessToken> {
+        let output = std::process::Command::new("sh")
+            .arg("-c")
+            .arg(&self.0)
+            .stdin(std::process::Stdio::inherit())
+            .stderr(std::process::Stdio::inherit())
+            .output()
+            .map_err(|e| io::Error::new(e.kind(), format!("cannot run '{}': {}", self.0, e)))?;
+        if !output.status.success() {
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                format!("'{}' failed with {}", self.0, output.status),
+            ));
+        }
+
+        let token = String::from_utf8(output.stdout)
+            .map_err(|_| invalid(format!("'{}' printed no valid UTF-8", self.0)))?;
+        match token.lines().next().map(str::trim) {
+            Some(token) if !token.is_empty() => Ok(AccessToken(token.to_string())),
+            _ => Err(invalid(format!("'{}' printed no token", self.0))),
+        }
     }
 }
 
@@ -91,39 +317,252 @@ impl BardoCredentials {
         &self.profiles
     }
 
+    /// Profiles of the credentials file that are not valid and were left
+    /// out by `read_profile`
+    pub fn warnings(&self) -> &[String] {
+        &self.warnings
+    }
+
+    /// Read all profiles, failing with the name of the first profile that
+    /// is not valid
     pub fn read_from<F>(reader: F) -> Result<Self>
     where
-        F: Fn() -> Result<toml::Value>,
+        F: Fn() -> Result<Value>,
     {
-        reader().map(|toml| {
-            let mut map: HashMap<String, Credentials> = HashMap::new();
-            for (k, v) in toml.as_table().as_ref().expect("file has invalid format").iter() {
-                let _ = Credentials::read_from(|| Ok(v.clone())).map(|creds| map.insert(k.to_string(), creds));
-            }
-           
-            Self {
-                profiles: map,
+        Self::read_profiles(reader, None)
+    }
+
+    /// Read the profiles for a command running on `profile`. Only an
+    /// invalid `profile` fails, other invalid profiles are left out and
+    /// reported in `warnings`.
+    pub fn read_profile<F>(reader: F, profile: &str) -> Result<Self>
+    where
+        F: Fn() -> Result<Value>,
+    {
+        Self::read_profiles(reader, Some(profile))
+    }
+
+    fn read_profiles<F>(reader: F, selected: Option<&str>) -> Result<Self>
+    where
+        F: Fn() -> Result<Value>,
+    {
+        let toml = reader()?;
+        let table = toml.as_table().ok_or_else(|| invalid("credentials file has invalid format"))?;
+
+        let mut map: HashMap<String, Credentials> = HashMap::new();
+        let mut warnings = Vec::new();
+        for (k, v) in table.iter() {
+            match Credentials::read_from(|| Ok(v.clone())) {
+                Ok(creds) => {
+                    map.insert(k.to_string(), creds);
+                }
+                Err(e) if selected.is_some_and(|p| p != k) => {
+                    warnings.push(format!("credentials of profile '{}' are ignored: {}", k, e))
+                }
+                Err(e) => return Err(io::Error::new(e.kind(), format!("credentials of profile '{}': {}", k, e))),
             }
+        }
+
+        Ok(Self {
+            profiles: map,
+            warnings: warnings,
         })
     }
 
+    pub fn profiles_mut(&mut self) -> &mut HashMap<String, Credentials> {
+        &mut self.profiles
+    }
+
     pub fn write_to<F>(&self, writer: F) -> Result<()>
     where
         F: Fn(&BardoCredentials) -> Result<()>,
     {
-        writer(&self)
+        writer(self)
+    }
+
+    /// Merge the profiles into `document`, the current content of the
+    /// credentials file. Keys of other kinds are removed from a profile,
+    /// comments and unknown keys are kept.
+    pub fn to_toml_string(&self, document: &str) -> Result<String> {
+        let mut document = parse(document)?;
+        retain_profiles(&mut document, |p| self.profiles.contains_key(p));
+
+        let mut names: Vec<&String> = self.profiles.keys().collect();
+        names.sort();
+        for name in names {
+            merge(profile_table(&mut document, name), &self.profiles[name].to_toml(), CREDENTIAL_KEYS);
+        }
+        Ok(document.to_string())
     }
 }
 
+/// The keys holding secrets, `Credentials::redacted` hides their values
+const SECRET_KEYS: &[&str] = &["bardo_client_secret", "bardo_access_token"];
+
+/// All keys of all kinds, in the order they are added to the file
+const CREDENTIAL_KEYS: &[&str] = &[
+    "bardo_client_id",
+    "bardo_client_secret",
+    "bardo_access_token",
+    "bardo_access_token_command",
+    "bardo_app_id",
+    "bardo_private_key_path",
+    "bardo_installation_id",
+];
+
 // Structure credentials
 // [default]
-// bardo_github_client_id =
-// bardo_github_client_secret =
-// bardo_github_access_token =
+// bardo_access_token =
+//
+// [oauth]
+// bardo_client_id =
+// bardo_client_secret =
+// bardo_access_token =
+//
+// [app]
+// bardo_app_id =
+// bardo_private_key_path =
+// bardo_installation_id =
+//
+// [command]
+// bardo_access_token_command =
 pub fn credentials_file() -> Option<PathBuf> {
     config_dir().map(|h| h.join("credentials"))
 }
 
+/// The credentials file encrypted with `bardo auth encrypt`, which is used
+/// instead of `credentials_file()` when it exists
+pub fn encrypted_credentials_file() -> Option<PathBuf> {
+    config_dir().map(|h| h.join("credentials.enc"))
+}
+
+fn locate(path: Option<PathBuf>) -> Result<PathBuf> {
+    path.ok_or(io::Error::new(io::ErrorKind::NotFound, "cannot locate credentials file"))
+}
+
+/// The content of the credentials file, decrypted if it is encrypted
+fn read_credentials_str() -> Result<String> {
+    let encrypted = locate(encrypted_credentials_file())?;
+    let data = if !encrypted.exists() {
+        read(locate(credentials_file())?)?
+    } else if locate(credentials_file())?.exists() {
+        return Err(invalid(format!(
+            "found an encrypted and a plain credentials file, remove one of them from {}",
+            encrypted.parent().map(|p| p.display().to_string()).unwrap_or_default()
+        )));
+    } else {
+        decrypt(&read(&encrypted)?, &passphrase(false)?)?
+    };
+    String::from_utf8(data).map_err(|_| invalid("credentials file encoding is not UTF-8"))
+}
+
+/// Read the credentials, decrypting them if they are encrypted
+pub fn read_credentials() -> Result<Value> {
+    read_bytes(read_credentials_str()?.as_bytes())
+}
+
+/// Replace the credentials file where `read_credentials` found it,
+/// encrypted if it was. It is always private.
+fn store_credentials(content: &str, options: &WriteOptions) -> Result<()> {
+    write_config_dir()?;
+    let options = options.private(true);
+    let encrypted = locate(encrypted_credentials_file())?;
+    if encrypted.exists() {
+        options.write(encrypted, encrypt(content.as_bytes(), &passphrase(false)?)?)
+    } else {
+        options.write(locate(credentials_file())?, content)
+    }
+}
+
+fn read_credentials_or_empty() -> Result<String> {
+    match read_credentials_str() {
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(String::new()),
+        content => content,
+    }
+}
+
+/// A writer for `BardoCredentials::write_to` that replaces the credentials
+/// file atomically, keeping it encrypted if it is
+pub fn write_credentials(credentials: &BardoCredentials, options: &WriteOptions) -> Result<()> {
+    let document = read_credentials_or_empty()?;
+    store_credentials(&credentials.to_toml_string(&document)?, options)
+}
+
+/// Store the client id and the access token obtained with it in a profile
+/// of the credentials file. Other profiles and keys are kept, the file is
+/// created if it does not exist yet.
+pub fn write_access_token(profile: &str, client_id: &ClientId, token: &AccessToken) -> Result<()> {
+    let document = read_credentials_or_empty()?;
+    store_credentials(&with_access_token(&document, profile, client_id, token)?, &WriteOptions::new())
+}
+
+/// Fail for a profile of the credentials file that cannot store a token
+/// obtained by a login, a GitHub App or a token command. The file is read
+/// as it is, so invalid profiles do not get in the way.
+pub fn check_login_profile(profile: &str) -> Result<()> {
+    login_refused(&read_credentials_or_empty()?, profile)
+}
+
+fn login_refused(document: &str, profile: &str) -> Result<()> {
+    let toml = read_bytes(document.as_bytes())?;
+    let section = match toml.get(profile) {
+        Some(section) => section,
+        None => return Ok(()),
+    };
+    let has = |keys: &[&str]| keys.iter().any(|k| section.get(*k).is_some());
+    let kind = if has(APP_KEYS) {
+        "a GitHub App"
+    } else if has(&["bardo_access_token_command"]) {
+        "a token command"
+    } else {
+        return Ok(());
+    };
+    Err(invalid(format!(
+        "profile '{}' authenticates with {}, logging in only works for OAuth Apps",
+        profile, kind
+    )))
+}
+
+/// Replace the plain credentials file with an encrypted one. Returns the
+/// path of the encrypted file.
+pub fn encrypt_credentials() -> Result<PathBuf> {
+    let plain = locate(credentials_file())?;
+    let encrypted = locate(encrypted_credentials_file())?;
+    if encrypted.exists() {
+        return Err(invalid(format!("{} already exists", encrypted.display())));
+    }
+
+    let data = read(&plain)?;
+    read_bytes(&data)?;
+    write_private(&encrypted, encrypt(&data, &passphrase(true)?)?)?;
+    std::fs::remove_file(&plain)?;
+    Ok(encrypted)
+}
+
+/// Replace the encrypted credentials file with a plain one. Returns the
+/// path of the plain file.
+pub fn decrypt_credentials() -> Result<PathBuf> {
+    let plain = locate(credentials_file())?;
+    let encrypted = locate(encrypted_credentials_file())?;
+    if plain.exists() {
+        return Err(invalid(format!("{} already exists", plain.display())));
+    }
+
+    let data = decrypt(&read(&encrypted)?, &passphrase(false)?)?;
+    write_private(&plain, data)?;
+    std::fs::remove_file(&encrypted)?;
+    Ok(plain)
+}
+
+fn with_access_token(document: &str, profile: &str, client_id: &ClientId, token: &AccessToken) -> Result<String> {
+    login_refused(document, profile)?;
+    let mut document = parse(document)?;
+    let section = profile_table(&mut document, profile);
+    set(section, "bardo_client_id", &Value::String(client_id.0.clone()));
+    set(section, "bardo_access_token", &Value::String(token.0.clone()));
+    Ok(document.to_string())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -145,11 +584,26 @@ mod tests {
         });
 
         let creds = Credentials::read_from(reader).expect("credentials not parsed");
-        assert_eq!("client_id".to_string(), creds.client_id().0);
-        assert_eq!("client_secret".to_string(), creds.client_secret().0);
+        assert_eq!("client_id".to_string(), creds.client_id().unwrap().0);
+        assert_eq!("client_secret".to_string(), creds.client_secret().unwrap().0);
         assert_eq!(false, creds.access_token().is_none());
     }
 
+    #[test]
+    fn redacted_hides_secrets() {
+        let toml_str = r#"
+            bardo_client_id = "client_id"
+            bardo_client_secret = "client_secret"
+            bardo_access_token = "access_token"
+        "#;
+
+        let creds = Credentials::read_from(|| read_bytes(toml_str.as_bytes())).expect("credentials not parsed");
+        let redacted = creds.redacted();
+        assert_eq!("client_id", redacted["bardo_client_id"].as_str().unwrap());
+        assert_eq!("***", redacted["bardo_client_secret"].as_str().unwrap());
+        assert_eq!("***", redacted["bardo_access_token"].as_str().unwrap());
+    }
+
     #[test]
     fn read_from_full() {
         let toml_str = r#"
@@ -174,25 +628,242 @@ mod tests {
         assert_eq!(true, config.profiles.get("foo").unwrap().access_token().is_none());
     }
 
+    #[test]
+    fn read_from_without_secret() {
+        let toml_str = r#"
+            [default]
+            bardo_client_id = "client_id"
+            bardo_access_token = "access_token"
+        "#;
+
+        let reader = || read_bytes(toml_str.as_bytes()).map(|toml| toml["default"].clone());
+
+        let creds = Credentials::read_from(reader).expect("credentials not parsed");
+        assert!(creds.client_secret().is_none());
+        assert_eq!("access_token", creds.access_token().unwrap().0);
+    }
+
+    fn read_section(toml_str: &str) -> Result<Credentials> {
+        Credentials::read_from(|| read_bytes(toml_str.as_bytes()))
+    }
+
+    #[test]
+    fn read_kinds() {
+        let token = read_section(r#"bardo_access_token = "access_token""#).unwrap();
+        assert_eq!("access_token", token.access_token().unwrap().0);
+        assert!(token.client_id().is_none());
+
+        let command = read_section(r#"bardo_access_token_command = "pass show github""#).unwrap();
+        match command {
+            Credentials::Command { command } => assert_eq!("pass show github", command.0),
+            _ => panic!("expected a token command"),
+        }
+
+        let app = read_section(
+            r#"
+            bardo_app_id = 1234
+            bardo_private_key_path = "/keys/bot.pem"
+            bardo_installation_id = 42
+        "#,
+        )
+        .unwrap();
+        match app {
+            Credentials::App {
+                app_id,
+                private_key_path,
+                installation_id,
+            } => {
+                assert_eq!(1234, app_id.0);
+                assert_eq!(PathBuf::from("/keys/bot.pem"), private_key_path.0);
+                assert_eq!(42, installation_id.0);
+            }
+            _ => panic!("expected a GitHub App"),
+        }
+    }
+
+    #[test]
+    fn read_invalid_kinds() {
+        let error = |toml_str| read_section(toml_str).err().expect("credentials are invalid").to_string();
+
+        assert!(error("").contains("no credentials"));
+        assert!(error(r#"bardo_access_token = """#).contains("'bardo_access_token' is empty"));
+        assert!(error(r#"bardo_client_secret = "secret""#).contains("'bardo_client_id' is missing"));
+        assert!(error(r#"bardo_app_id = 1234"#).contains("'bardo_private_key_path' is missing, it is required for a GitHub App"));
+        assert!(error(
+            r#"
+            bardo_app_id = "1234"
+            bardo_private_key_path = "/keys/bot.pem"
+            bardo_installation_id = 42
+        "#
+        )
+        .contains("'bardo_app_id' must be a positive integer"));
+        assert!(error(
+            r#"
+            bardo_app_id = 1234
+            bardo_private_key_path = "/keys/bot.pem"
+            bardo_installation_id = 42
+            bardo_access_token = "access_token"
+        "#
+        )
+        .contains("cannot be combined"));
+        assert!(error(
+            r#"
+            bardo_access_token = "access_token"
+            bardo_access_token_command = "pass show github"
+        "#
+        )
+        .contains("either"));
+    }
+
+    #[test]
+    fn read_from_full_names_invalid_profile() {
+        let toml_str = r#"
+            [default]
+            bardo_access_token = "access_token"
+
+            [broken]
+            bardo_app_id = 1234
+        "#;
+
+        let error = BardoCredentials::read_from(|| read_bytes(toml_str.as_bytes())).err().unwrap();
+        assert!(error.to_string().starts_with("credentials of profile 'broken'"));
+
+        let error = BardoCredentials::read_profile(|| read_bytes(toml_str.as_bytes()), "broken").err().unwrap();
+        assert!(error.to_string().starts_with("credentials of profile 'broken'"));
+    }
+
+    #[test]
+    fn read_profile_ignores_other_invalid_profiles() {
+        let toml_str = r#"
+            [default]
+            bardo_access_token = "access_token"
+
+            [broken]
+            bardo_app_id = 1234
+        "#;
+
+        let creds = BardoCredentials::read_profile(|| read_bytes(toml_str.as_bytes()), "default").unwrap();
+        assert!(creds.profiles().contains_key("default"));
+        assert!(!creds.profiles().contains_key("broken"));
+        assert_eq!(1, creds.warnings().len());
+        assert!(creds.warnings()[0].starts_with("credentials of profile 'broken' are ignored"));
+    }
+
+    #[test]
+    fn access_token_is_redacted() {
+        let token = AccessToken("s3cr3t".to_string());
+        assert_eq!("Some(AccessToken(***))", format!("{:?}", Some(token)));
+    }
+
+    #[test]
+    fn token_command() {
+        let token = TokenCommand("printf 'secret\\nsecond line'".to_string()).run().unwrap();
+        assert_eq!("secret", token.0);
+
+        // the output may be the secret, so it is not part of the error
+        let error = TokenCommand("printf tok; printf en; exit 3".to_string()).run().err().unwrap();
+        assert!(!error.to_string().contains("token"));
+        assert!(TokenCommand("true".to_string()).run().is_err());
+    }
+
+    #[test]
+    fn with_access_token_keeps_other_keys() {
+        let toml_str = r#"
+            [default]
+            bardo_client_id = "old_id"
+            bardo_client_secret = "client_secret"
+            bardo_access_token = "old_token"
+
+            [foo]
+            bardo_client_id = "client_id"
+        "#;
+        let document = with_access_token(toml_str, "default", &ClientId("id".to_string()), &AccessToken("token".to_string())).unwrap();
+        let toml = read_bytes(document.as_bytes()).unwrap();
+        assert_eq!("id", toml["default"]["bardo_client_id"].as_str().unwrap());
+        assert_eq!("client_secret", toml["default"]["bardo_client_secret"].as_str().unwrap());
+        assert_eq!("token", toml["default"]["bardo_access_token"].as_str().unwrap());
+        assert_eq!("client_id", toml["foo"]["bardo_client_id"].as_str().unwrap());
+
+        let document = with_access_token(&document, "bar", &ClientId("id".to_string()), &AccessToken("token".to_string())).unwrap();
+        let toml = read_bytes(document.as_bytes()).unwrap();
+        assert_eq!("token", toml["bar"]["bardo_access_token"].as_str().unwrap());
+    }
+
+    #[test]
+    fn with_access_token_refuses_apps_and_commands() {
+        let toml_str = r#"
+            [bot]
+            bardo_app_id = 1
+
+            [pass]
+            bardo_access_token_command = "pass show github"
+
+            [token]
+            bardo_access_token = "old_token"
+        "#;
+        let login = |profile: &str| {
+            with_access_token(toml_str, profile, &ClientId("id".to_string()), &AccessToken("token".to_string()))
+        };
+        assert!(login("bot").err().unwrap().to_string().contains("a GitHub App"));
+        assert!(login("pass").err().unwrap().to_string().contains("a token command"));
+        assert!(login("token").is_ok());
+        assert!(login("new").is_ok());
+    }
+
+    #[test]
+    fn to_toml_string_replaces_keys_of_other_kinds() {
+        let toml_str = r#"# tokens
+[default]
+bardo_client_id = "id" # the OAuth App
+bardo_access_token = "old"
+
+[bot]
+bardo_app_id = 1
+bardo_private_key_path = "/bot.pem"
+bardo_installation_id = 2
+"#;
+        let mut creds = BardoCredentials::read_from(|| read_bytes(toml_str.as_bytes())).unwrap();
+        creds.profiles_mut().insert(
+            "bot".to_string(),
+            Credentials::Command {
+                command: TokenCommand("pass show bot".to_string()),
+            },
+        );
+        creds.profiles_mut().get_mut("default").unwrap().set_access_token(AccessToken("new".to_string())).unwrap();
+
+        assert_eq!(
+            r#"# tokens
+[default]
+bardo_client_id = "id" # the OAuth App
+bardo_access_token = "new"
+
+[bot]
+bardo_access_token_command = "pass show bot"
+"#,
+            creds.to_toml_string(toml_str).unwrap()
+        );
+    }
+
     #[test]
     fn write_to() {
 
         let mut map: HashMap<String, Credentials> = HashMap::new();
         map.insert(
             "default".to_string(),
-            Credentials {
+            Credentials::OAuth {
                 client_id: ClientId("id".to_string()),
-                client_secret: ClientSecret("secret".to_string()),
+                client_secret: Some(ClientSecret("secret".to_string())),
                 access_token: None,
             }
         );
         let config = BardoCredentials {
             profiles: map,
+            warnings: Vec::new(),
         };
 
         config.write_to(|c| {
             let creds = c.profiles.get("default").unwrap();
-            let str = format!("{}:{}:{:?}", creds.client_id().0, creds.client_secret().0, creds.access_token());
+            let str = format!("{}:{}:{:?}", creds.client_id().unwrap().0, creds.client_secret().unwrap().0, creds.access_token());
             assert_eq!("id:secret:None", str);
             Ok(())
         }).expect("write_to panicked");
@@ -205,23 +876,24 @@ mod tests {
         let mut map: HashMap<String, Credentials> = HashMap::new();
         map.insert(
             "default".to_string(),
-            Credentials {
+            Credentials::OAuth {
                 client_id: ClientId("id".to_string()),
-                client_secret: ClientSecret("secret".to_string()),
+                client_secret: Some(ClientSecret("secret".to_string())),
                 access_token: None,
             }
         );
         let mut config = BardoCredentials {
             profiles: map,
+            warnings: Vec::new(),
         };
 
         let creds = config.profiles.get_mut("default").unwrap();
 
-        *creds.access_token_mut() = Some(AccessToken("token".to_string()));
+        creds.set_access_token(AccessToken("token".to_string())).expect("token not set");
        
         config.write_to(|c| {
             let creds = c.profiles.get("default").unwrap();
-            let str = format!("{}:{}:{}", creds.client_id().0, creds.client_secret().0, creds.access_token().unwrap().0);
+            let str = format!("{}:{}:{}", creds.client_id().unwrap().0, creds.client_secret().unwrap().0, creds.access_token().unwrap().0);
             assert_eq!("id:secret:token", str);
             Ok(())
         }).expect("write_to panicked");
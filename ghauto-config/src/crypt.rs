@@ -0,0 +1,167 @@
+//! Encryption of the credentials file at rest. The key is derived from a
+//! passphrase with scrypt and the file is sealed with ChaCha20-Poly1305.
+//! The passphrase is read from `BARDO_PASSPHRASE` or prompted for.
+//!
+//! Layout of an encrypted file, the parameters are authenticated as well:
+//! `MAGIC | log_n | r | p | salt | nonce | ciphertext`
+
+use chacha20poly1305::aead::{Aead, NewAead, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use rand::RngCore;
+use scrypt::{scrypt, ScryptParams};
+
+use std::env;
+use std::io;
+use std::sync::Mutex;
+
+pub use io::Result;
+
+const MAGIC: &[u8] = b"bardo-encrypted-v1\n";
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+const HEADER_LEN: usize = MAGIC.len() + 1 + 4 + 4 + SALT_LEN;
+
+/// scrypt work factor, 2^15 iterations with r = 8 take 32 MiB
+const LOG_N: u8 = 15;
+const R: u32 = 8;
+const P: u32 = 1;
+
+/// The environment variable the passphrase is taken from before prompting
+pub const PASSPHRASE_ENV: &str = "BARDO_PASSPHRASE";
+
+/// Whether `data` is an encrypted file
+pub fn is_encrypted(data: &[u8]) -> bool {
+    data.starts_with(MAGIC)
+}
+
+fn invalid<S: Into<String>>(message: S) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
+
+fn derive_key(passphrase: &str, salt: &[u8], log_n: u8, r: u32, p: u32) -> Result<[u8; 32]> {
+    let params = ScryptParams::new(log_n, r, p).map_err(|_| invalid("invalid scrypt parameters"))?;
+    let mut key = [0u8; 32];
+    scrypt(passphrase.as_bytes(), salt, &params, &mut key).map_err(|_| invalid("cannot derive key"))?;
+    Ok(key)
+}
+
+/// Encrypt `plaintext` with a key derived from `passphrase`
+pub fn encrypt(plaintext: &[u8], passphrase: &str) -> Result<Vec<u8>> {
+    encrypt_with(plaintext, passphrase, LOG_N)
+}
+
+fn encrypt_with(plaintext: &[u8], passphrase: &str, log_n: u8) -> Result<Vec<u8>> {
+    let mut salt = [0u8; SALT_LEN];
+    let mut nonce = [0u8; NONCE_LEN];
+    rand::thread_rng().fill_bytes(&mut salt);
+    rand::thread_rng().fill_bytes(&mut nonce);
+
+    let mut data = Vec::with_capacity(HEADER_LEN + NONCE_LEN + plaintext.len() + 16);
+    data.extend_from_slice(MAGIC);
+    data.push(log_n);
+    data.extend_from_slice(&R.to_be_bytes());
+    data.extend_from_slice(&P.to_be_bytes());
+    data.extend_from_slice(&salt);
+
+    let key = derive_key(passphrase, &salt, log_n, R, P)?;
+    let cipher = ChaCha20Poly1305::new(&Key::from(key));
+    let payload = Payload {
+        msg: plaintext,
+        aad: &data,
+    };
+    let ciphertext = cipher
+        .encrypt(&Nonce::from(nonce), payload)
+        .map_err(|_| invalid("encryption failed"))?;
+
+    data.extend_from_slice(&nonce);
+    data.extend_from_slice(&ciphertext);
+    Ok(data)
+}
+
+/// Decrypt a file written by `encrypt`
+pub fn decrypt(data: &[u8], passphrase: &str) -> Result<Vec<u8>> {
+    if !is_encrypted(data) || data.len() < HEADER_LEN + NONCE_LEN {
+        return Err(invalid("not an encrypted credentials file"));
+    }
+
+    let mut at = MAGIC.len();
+    let log_n = data[at];
+    at += 1;
+    let mut word = [0u8; 4];
+    word.copy_from_slice(&data[at..at + 4]);
+    let r = u32::from_be_bytes(word);
+    at += 4;
+    word.copy_from_slice(&data[at..at + 4]);
+    let p = u32::from_be_bytes(word);
+    at += 4;
+    let salt = &data[at..HEADER_LEN];
+
+    let key = derive_key(passphrase, salt, log_n, r, p)?;
+    let cipher = ChaCha20Poly1305::new(&Key::from(key));
+    let payload = Payload {
+        msg: &data[HEADER_LEN + NONCE_LEN..],
+        aad: &data[..HEADER_LEN],
+    };
+    let mut nonce = [0u8; NONCE_LEN];
+    nonce.copy_from_slice(&data[HEADER_LEN..HEADER_LEN + NONCE_LEN]);
+    cipher
+        .decrypt(&Nonce::from(nonce), payload)
+        .map_err(|_| invalid("wrong passphrase or corrupted credentials file"))
+}
+
+static PASSPHRASE: Mutex<Option<String>> = Mutex::new(None);
+
+/// The passphrase from `BARDO_PASSPHRASE` or, if it is not set, from a
+/// prompt on the terminal. With `confirm` a prompted passphrase has to be
+/// entered twice. It is kept for the rest of the process, so it is asked
+/// for at most once.
+pub fn passphrase(confirm: bool) -> Result<String> {
+    let mut cached = PASSPHRASE.lock().unwrap();
+    if let Some(passphrase) = cached.as_ref() {
+        return Ok(passphrase.clone());
+    }
+
+    let passphrase = match env::var(PASSPHRASE_ENV) {
+        Ok(passphrase) => passphrase,
+        Err(_) => {
+            let passphrase = rpassword::read_password_from_tty(Some("Passphrase for the credentials: "))?;
+            if confirm && rpassword::read_password_from_tty(Some("Repeat the passphrase: "))? != passphrase {
+                return Err(invalid("the passphrases do not match"));
+            }
+            passphrase
+        }
+    };
+    if passphrase.is_empty() {
+        return Err(invalid("the passphrase is empty"));
+    }
+
+    *cached = Some(passphrase.clone());
+    Ok(passphrase)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let data = encrypt_with(b"[default]\nbardo_access_token = \"s3cr3t\"\n", "passphrase", 4).unwrap();
+        assert!(is_encrypted(&data));
+        assert!(!String::from_utf8_lossy(&data).contains("s3cr3t"));
+
+        let plaintext = decrypt(&data, "passphrase").unwrap();
+        assert_eq!(b"[default]\nbardo_access_token = \"s3cr3t\"\n".to_vec(), plaintext);
+    }
+
+    #[test]
+    fn rejects_wrong_passphrase_and_tampering() {
+        let mut data = encrypt_with(b"secret", "passphrase", 4).unwrap();
+        assert!(decrypt(&data, "wrong").is_err());
+
+        // the parameters are authenticated
+        data[MAGIC.len()] = 5;
+        assert!(decrypt(&data, "passphrase").is_err());
+
+        assert!(decrypt(b"[default]", "passphrase").is_err());
+    }
+}
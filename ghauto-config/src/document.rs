@@ -0,0 +1,210 @@
+//! Writing profiles back into the credentials and config files. New values
+//! are merged into the parsed file instead of replacing it, so comments,
+//! formatting, unknown keys and `${NAME}` references of values that did not
+//! change survive.
+
+use std::env;
+use std::io;
+use toml::value::{Table, Value};
+use toml_edit::{Array, DocumentMut, InlineTable, Item, TableLike};
+
+use crate::secret::interpolate;
+
+pub use io::Result;
+
+/// Parse the content of a file, an empty string is an empty document
+pub fn parse(content: &str) -> Result<DocumentMut> {
+    content
+        .parse::<DocumentMut>()
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("file content is no valid toml: {}", e)))
+}
+
+/// Remove the profiles `keep` returns false for
+pub fn retain_profiles<F>(document: &mut DocumentMut, keep: F)
+where
+    F: Fn(&str) -> bool,
+{
+    document.as_table_mut().retain(|profile, _| keep(profile));
+}
+
+/// The table of `profile`, it is appended if it does not exist yet
+pub fn profile_table<'d>(document: &'d mut DocumentMut, profile: &str) -> &'d mut dyn TableLike {
+    let item = document.as_table_mut().entry(profile).or_insert_with(toml_edit::table);
+    if !item.is_table_like() {
+        *item = toml_edit::table();
+    }
+    item.as_table_like_mut().expect("profile is a table")
+}
+
+/// Set the `known` keys of `table` to the values in `section` and remove
+/// those missing in `section`. Other keys are kept.
+pub fn merge(table: &mut dyn TableLike, section: &Table, known: &[&str]) {
+    for key in known {
+        match section.get(*key) {
+            Some(value) => set(table, key, value),
+            None => {
+                table.remove(key);
+            }
+        }
+    }
+}
+
+/// Merge `section` into the sub-table `key` of `table` like `merge`, the
+/// sub-table is removed if `section` is `None`
+pub fn merge_table(table: &mut dyn TableLike, key: &str, section: Option<&Table>, known: &[&str]) {
+    let section = match section {
+        Some(section) => section,
+        None => {
+            table.remove(key);
+            return;
+        }
+    };
+    if !table.get(key).is_some_and(Item::is_table_like) {
+        table.insert(key, toml_edit::table());
+    }
+    if let Some(sub) = table.get_mut(key).and_then(Item::as_table_like_mut) {
+        merge(sub, section, known);
+    }
+}
+
+/// Set `key` unless it already holds `value`, possibly as `${NAME}`
+/// reference. A replaced value keeps its comments.
+pub fn set(table: &mut dyn TableLike, key: &str, value: &Value) {
+    if table.get(key).and_then(from_item).is_some_and(|old| same(old, value)) {
+        return;
+    }
+    match table.get_mut(key).and_then(Item::as_value_mut) {
+        Some(old) => {
+            let decor = old.decor().clone();
+            *old = to_edit(value);
+            *old.decor_mut() = decor;
+        }
+        None => {
+            table.insert(key, Item::Value(to_edit(value)));
+        }
+    }
+}
+
+fn same(old: Value, new: &Value) -> bool {
+    old == *new || interpolate(old, &|name| env::var(name).ok()).ok().as_ref() == Some(new)
+}
+
+fn from_item(item: &Item) -> Option<Value> {
+    match item {
+        Item::None => None,
+        Item::Value(v) => Some(from_edit(v)),
+        Item::Table(t) => Some(Value::Table(
+            t.iter().filter_map(|(k, v)| from_item(v).map(|v| (k.to_string(), v))).collect(),
+        )),
+        Item::ArrayOfTables(a) => Some(Value::Array(a.iter().filter_map(|t| from_item(&Item::Table(t.clone()))).collect())),
+    }
+}
+
+fn from_edit(value: &toml_edit::Value) -> Value {
+    match value {
+        toml_edit::Value::String(s) => Value::String(s.value().clone()),
+        toml_edit::Value::Integer(i) => Value::Integer(*i.value()),
+        toml_edit::Value::Float(f) => Value::Float(*f.value()),
+        toml_edit::Value::Boolean(b) => Value::Boolean(*b.value()),
+        toml_edit::Value::Datetime(d) => {
+            let datetime = d.value().to_string();
+            datetime.parse().map(Value::Datetime).unwrap_or(Value::String(datetime))
+        }
+        toml_edit::Value::Array(a) => Value::Array(a.iter().map(from_edit).collect()),
+        toml_edit::Value::InlineTable(t) => Value::Table(t.iter().map(|(k, v)| (k.to_string(), from_edit(v))).collect()),
+    }
+}
+
+/// Tables become inline tables; arrays of tables, like `repositories`,
+/// get one entry per line
+fn to_edit(value: &Value) -> toml_edit::Value {
+    match value {
+        Value::String(s) => s.as_str().into(),
+        Value::Integer(i) => (*i).into(),
+        Value::Float(f) => (*f).into(),
+        Value::Boolean(b) => (*b).into(),
+        Value::Datetime(d) => {
+            let datetime = d.to_string();
+            datetime.parse().unwrap_or_else(|_| datetime.into())
+        }
+        Value::Array(a) => {
+            let mut array: Array = a.iter().map(to_edit).collect();
+            if a.iter().any(Value::is_table) {
+                for v in array.iter_mut() {
+                    v.decor_mut().set_prefix("\n  ");
+                }
+                array.set_trailing_comma(true);
+                array.set_trailing("\n");
+            }
+            toml_edit::Value::Array(array)
+        }
+        Value::Table(t) => {
+            let table: InlineTable = t.iter().map(|(k, v)| (k.as_str(), to_edit(v))).collect();
+            toml_edit::Value::InlineTable(table)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file::read_bytes;
+
+    fn section(toml: &str) -> Table {
+        read_bytes(toml.as_bytes()).unwrap().as_table().unwrap().clone()
+    }
+
+    #[test]
+    fn keeps_comments_and_unknown_keys() {
+        let mut document = parse(
+            r#"# my profiles
+[default]
+clone_path = "/old" # where to clone
+custom = 1
+jobs = 2
+"#,
+        )
+        .unwrap();
+
+        let table = profile_table(&mut document, "default");
+        merge(table, &section(r#"clone_path = "/new""#), &["clone_path", "jobs"]);
+        assert_eq!(
+            r#"# my profiles
+[default]
+clone_path = "/new" # where to clone
+custom = 1
+"#,
+            document.to_string()
+        );
+    }
+
+    #[test]
+    fn keeps_unchanged_references() {
+        env::set_var("BARDO_DOCUMENT_TEST_TOKEN", "secret");
+        let mut document = parse("[default]\ntoken = \"${BARDO_DOCUMENT_TEST_TOKEN}\"\n").unwrap();
+
+        set(profile_table(&mut document, "default"), "token", &Value::String("secret".to_string()));
+        assert_eq!("[default]\ntoken = \"${BARDO_DOCUMENT_TEST_TOKEN}\"\n", document.to_string());
+
+        set(profile_table(&mut document, "default"), "token", &Value::String("other".to_string()));
+        assert_eq!("[default]\ntoken = \"other\"\n", document.to_string());
+    }
+
+    #[test]
+    fn adds_and_removes_profiles() {
+        let mut document = parse("[old]\njobs = 1\n").unwrap();
+
+        retain_profiles(&mut document, |p| p != "old");
+        let table = profile_table(&mut document, "new");
+        merge(
+            table,
+            &section(r#"repositories = [{org = "o", name = "a"}, {org = "o", name = "b"}]"#),
+            &["repositories"],
+        );
+        merge_table(table, "retry", Some(&section("jitter = true")), &["jitter"]);
+        assert_eq!(
+            "[new]\nrepositories = [\n  { org = \"o\", name = \"a\" },\n  { org = \"o\", name = \"b\" },\n]\n\n[new.retry]\njitter = true\n",
+            document.to_string()
+        );
+    }
+}
@@ -1,7 +1,8 @@
 use std::env;
 use std::path::PathBuf;
 use std::path::Path;
-use std::fs::File;
+use std::fs;
+use std::fs::{File, OpenOptions};
 use std::io;
 use std::io::{Read,Write};
 use toml::Value;
@@ -43,24 +44,114 @@ pub fn read_toml<P: AsRef<Path>>(path: P) -> Result<Value> {
     })
 }
 
+/// How `WriteOptions::write` replaces a file
+#[derive(Clone, Copy, Debug, Default)]
+pub struct WriteOptions {
+    private: bool,
+    backup: bool,
+}
+
+impl WriteOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only the owner may read and write the file (`0600` on unix).
+    /// Otherwise the permissions of the replaced file are kept.
+    pub fn private(mut self, private: bool) -> Self {
+        self.private = private;
+        self
+    }
+
+    /// Keep the replaced file as `<name>.bak`
+    pub fn backup(mut self, backup: bool) -> Self {
+        self.backup = backup;
+        self
+    }
+
+    /// Write `data` to a temporary file next to `path` and rename it over
+    /// `path`, so readers see either the old or the new content but never
+    /// a truncated file. A symlink is followed and its target replaced.
+    pub fn write<P: AsRef<Path>, Bytes: AsRef<[u8]>>(&self, path: P, data: Bytes) -> Result<()> {
+        let path = fs::canonicalize(path.as_ref()).unwrap_or_else(|_| path.as_ref().to_path_buf());
+        let name = path
+            .file_name()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "path has no file name"))?
+            .to_string_lossy()
+            .into_owned();
+        let tmp = path.with_file_name(format!(".{}.tmp{}", name, std::process::id()));
+
+        let written = self.write_tmp(&path, &tmp, data.as_ref()).and_then(|_| {
+            if self.backup && path.exists() {
+                fs::copy(&path, path.with_file_name(format!("{}.bak", name)))?;
+            }
+            fs::rename(&tmp, &path)
+        });
+        if written.is_err() {
+            let _ = fs::remove_file(&tmp);
+        }
+        written
+    }
+
+    fn write_tmp(&self, path: &Path, tmp: &Path, data: &[u8]) -> Result<()> {
+        let mut options = OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            if self.private {
+                options.mode(0o600);
+            }
+        }
+        let mut file = options.open(tmp)?;
+        if !self.private {
+            if let Ok(meta) = fs::metadata(path) {
+                file.set_permissions(meta.permissions())?;
+            }
+        }
+        file.write_all(data)?;
+        file.sync_all()
+    }
+}
+
 /// Creates a file at the given path with contents of `Vec<u8>` or `&[u8]`, etc.
-/// Overwrites, non-atomically, if the file exists.
+/// Replaces the file atomically if it exists.
 /// The path can be `String` or `Path`
 pub fn write<P: AsRef<Path>, Bytes: AsRef<[u8]>>(path: P, data: Bytes) -> Result<()> {
-    let mut file = File::create(path)?;
-    file.write_all(data.as_ref())?;
-    Ok(())
+    WriteOptions::new().write(path, data)
 }
 
 /// Creates a file at the given path with given text contents, encoded as UTF-8.
-/// Overwrites, non-atomically, if the file exists.
+/// Replaces the file atomically if it exists.
 /// The path be `String` or `Path`.
 pub fn write_str<P: AsRef<Path>, S: AsRef<str>>(path: P, data: S) -> Result<()> {
     write(path, data.as_ref().as_bytes())
 }
 
+/// Creates a file only the owner may read and write (`0600` on unix) with
+/// contents of `Vec<u8>` or `&[u8]`. Replaces the file atomically if it
+/// exists.
+pub fn write_private<P: AsRef<Path>, Bytes: AsRef<[u8]>>(path: P, data: Bytes) -> Result<()> {
+    WriteOptions::new().private(true).write(path, data)
+}
+
+/// Creates a directory and its parents; only the owner may access the
+/// directory itself (`0700` on unix), also if it already exists.
+pub fn create_private_dir<P: AsRef<Path>>(path: P) -> Result<()> {
+    let path = path.as_ref();
+    let mut builder = fs::DirBuilder::new();
+    builder.recursive(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+        builder.mode(0o700).create(path)?;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o700))
+    }
+    #[cfg(not(unix))]
+    builder.create(path)
+}
 
-/// Common dirs
+// Common dirs
 
 pub fn home_dir() -> Option<PathBuf> { dirs_sys::home_dir() }
 
@@ -71,6 +162,15 @@ pub fn config_dir() -> Option<PathBuf> {
         .map(|h| h.join("gh"))
 }
 
+/// Directory for data that can be recreated at any time, e.g. cached
+/// API responses. Set `BARDO_CACHE_HOME` to move it.
+pub fn cache_dir() -> Option<PathBuf> {
+    env::var_os("BARDO_CACHE_HOME")
+        .and_then(dirs_sys::is_absolute_path)
+        .or_else(|| home_dir().map(|h| h.join(".cache/bardo")))
+        .map(|h| h.join("gh"))
+}
+
 /// Find project dir based on location of Cargo.toml
 pub fn project_dir() -> Option<PathBuf> {
     let buf: PathBuf = match env::current_dir().ok() {
@@ -88,18 +188,64 @@ pub fn project_dir() -> Option<PathBuf> {
     None
 }
 
-pub fn write_config_dir() {
-    config_dir().map(|buf| std::fs::create_dir_all(buf.as_path()).unwrap_or_else(|why| {
-        println!("! {:?}", why.kind());
-    }));
+/// Create the config directory unless it exists
+pub fn write_config_dir() -> Result<()> {
+    if let Some(buf) = config_dir() {
+        fs::create_dir_all(buf.as_path())?;
+    }
+    Ok(())
 }
 
 #[cfg(test)]
 mod file_tests {
     use super::*;
 
+    #[cfg(unix)]
+    #[test]
+    fn test_write_private() {
+        use std::os::unix::fs::PermissionsExt;
+        let path = env::temp_dir().join(format!("bardo-private-{}", std::process::id()));
+        write(&path, "public").unwrap();
+
+        write_private(&path, "private").unwrap();
+        assert_eq!(0o600, fs::metadata(&path).unwrap().permissions().mode() & 0o777);
+        assert_eq!("private", read_str(&path).unwrap());
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_create_private_dir() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = env::temp_dir().join(format!("bardo-private-dir-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        create_private_dir(&dir).unwrap();
+        assert_eq!(0o700, fs::metadata(&dir).unwrap().permissions().mode() & 0o777);
+        create_private_dir(dir.join("a/b")).unwrap();
+        assert_eq!(0o700, fs::metadata(dir.join("a/b")).unwrap().permissions().mode() & 0o777);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_write_atomic_with_backup() {
+        let dir = env::temp_dir().join(format!("bardo-atomic-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config");
+        write(&path, "old").unwrap();
+
+        WriteOptions::new().backup(true).write(&path, "new").unwrap();
+        assert_eq!("new", read_str(&path).unwrap());
+        assert_eq!("old", read_str(dir.join("config.bak")).unwrap());
+        let mut files: Vec<_> = fs::read_dir(&dir).unwrap().map(|e| e.unwrap().file_name()).collect();
+        files.sort();
+        assert_eq!(vec!["config", "config.bak"], files);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
     #[test]
     fn test_project_dir() {
-        assert_eq!(project_dir(), Some(PathBuf::from("/Users/seka/projects/mttrbit/bardo-github/ghauto-config")));
+        // tests run in the directory of the crate
+        assert_eq!(project_dir(), Some(PathBuf::from(env!("CARGO_MANIFEST_DIR"))));
     }
 }
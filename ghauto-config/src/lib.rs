@@ -6,10 +6,22 @@
 
 extern crate dirs_sys;
 extern crate toml;
+extern crate toml_edit;
 extern crate serde_derive;
+extern crate serde_path_to_error;
+extern crate serde_ignored;
+extern crate url;
+extern crate scrypt;
+extern crate chacha20poly1305;
+extern crate rand;
+extern crate rpassword;
 
 pub mod file;
 pub mod profile;
+pub mod secret;
+pub mod document;
+pub mod problem;
 pub mod credentials;
+pub mod crypt;
 pub mod config;
 pub mod context;
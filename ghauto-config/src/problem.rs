@@ -0,0 +1,229 @@
+//! Problems found while reading the config file. A problem names the
+//! profile and the key it refers to and, once located in the file, its
+//! line and column.
+
+use std::fmt;
+use std::io;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use toml_edit::{ImDocument, Item, TableLike, Value};
+
+/// A step of the path from a profile to a value
+#[derive(Clone, Debug, PartialEq)]
+pub enum Segment {
+    Key(String),
+    Index(usize),
+}
+
+#[derive(Clone, Debug)]
+pub struct Problem {
+    error: bool,
+    profile: Option<String>,
+    path: Vec<Segment>,
+    message: String,
+    file: Option<PathBuf>,
+    line_col: Option<(usize, usize)>,
+}
+
+impl Problem {
+    /// A value that cannot be used, the profile is not read
+    pub fn error<S: Into<String>>(profile: Option<&str>, path: Vec<Segment>, message: S) -> Self {
+        Self {
+            error: true,
+            profile: profile.map(String::from),
+            path: path,
+            message: message.into(),
+            file: None,
+            line_col: None,
+        }
+    }
+
+    /// A key bardo does not know, e.g. a typo. It is ignored.
+    pub fn unknown_key(profile: Option<&str>, path: Vec<Segment>) -> Self {
+        Self {
+            error: false,
+            ..Self::error(profile, path, "unknown key, it is ignored")
+        }
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.error
+    }
+
+    pub fn profile(&self) -> Option<&str> {
+        self.profile.as_ref().map(String::as_str)
+    }
+
+    pub fn path(&self) -> &[Segment] {
+        &self.path
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Line and column, both starting at 1, once the problem is located
+    pub fn line_col(&self) -> Option<(usize, usize)> {
+        self.line_col
+    }
+
+    /// Remember the position the problem refers to in `content`, the
+    /// content of `file`
+    pub fn locate(&mut self, file: &Path, content: &str) {
+        self.file = Some(file.to_path_buf());
+        if self.line_col.is_some() {
+            return;
+        }
+
+        let document = match ImDocument::parse(content) {
+            Ok(document) => document,
+            Err(_) => return,
+        };
+        let mut path = Vec::with_capacity(self.path.len() + 1);
+        if let Some(profile) = &self.profile {
+            path.push(Segment::Key(profile.clone()));
+        }
+        path.extend(self.path.iter().cloned());
+
+        self.line_col = table_span(document.as_table(), &path).map(|span| line_col(content, span.start));
+    }
+
+    /// Remember the position a parser reported, starting at 0
+    pub fn at(mut self, line: usize, col: usize) -> Self {
+        self.line_col = Some((line + 1, col + 1));
+        self
+    }
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(file) = &self.file {
+            write!(f, "{}:", file.display())?;
+            if let Some((line, col)) = self.line_col {
+                write!(f, "{}:{}:", line, col)?;
+            }
+            write!(f, " ")?;
+        }
+        if let Some(profile) = &self.profile {
+            write!(f, "profile '{}'", profile)?;
+            if !self.path.is_empty() {
+                write!(f, ", ")?;
+            }
+        }
+        if !self.path.is_empty() {
+            write!(f, "'{}'", display_path(&self.path))?;
+        }
+        if self.profile.is_some() || !self.path.is_empty() {
+            write!(f, ": ")?;
+        }
+        write!(f, "{}", self.message)
+    }
+}
+
+/// A path like `repositories[2].name`
+pub fn display_path(path: &[Segment]) -> String {
+    let mut s = String::new();
+    for segment in path {
+        match segment {
+            Segment::Key(key) if s.is_empty() => s.push_str(key),
+            Segment::Key(key) => {
+                s.push('.');
+                s.push_str(key);
+            }
+            Segment::Index(i) => s.push_str(&format!("[{}]", i)),
+        }
+    }
+    s
+}
+
+/// One error listing all errors among `problems`, one per line
+pub fn to_error(problems: &[Problem]) -> io::Error {
+    let errors: Vec<String> = problems.iter().filter(|p| p.is_error()).map(Problem::to_string).collect();
+    io::Error::new(io::ErrorKind::InvalidData, errors.join("\n"))
+}
+
+fn line_col(content: &str, offset: usize) -> (usize, usize) {
+    let before = &content[..offset];
+    let line = before.matches('\n').count() + 1;
+    let col = before.rsplit('\n').next().map_or(0, |l| l.chars().count()) + 1;
+    (line, col)
+}
+
+/// The key the path ends at or the value it leads to. If the path does not
+/// exist, e.g. for a missing key, the closest existing parent.
+fn table_span(table: &dyn TableLike, path: &[Segment]) -> Option<Range<usize>> {
+    match path.split_first() {
+        Some((Segment::Key(key), rest)) => {
+            let (key, item) = table.get_key_value(key)?;
+            if rest.is_empty() {
+                key.span().or_else(|| item.span())
+            } else {
+                item_span(item, rest)
+            }
+        }
+        _ => None,
+    }
+}
+
+fn item_span(item: &Item, path: &[Segment]) -> Option<Range<usize>> {
+    let span = match (item, path.first()) {
+        (_, None) => return item.span(),
+        (Item::Value(value), _) => value_span(value, path),
+        (Item::ArrayOfTables(tables), Some(Segment::Index(i))) => {
+            tables.get(*i).and_then(|t| table_span(t, &path[1..]).or_else(|| t.span()))
+        }
+        (_, Some(Segment::Key(_))) => item.as_table_like().and_then(|t| table_span(t, path)),
+        _ => None,
+    };
+    span.or_else(|| item.span())
+}
+
+fn value_span(value: &Value, path: &[Segment]) -> Option<Range<usize>> {
+    let span = match (value, path.first()) {
+        (_, None) => return value.span(),
+        (Value::InlineTable(table), Some(Segment::Key(_))) => table_span(table, path),
+        (Value::Array(array), Some(Segment::Index(i))) => array.get(*i).and_then(|v| value_span(v, &path[1..])),
+        _ => None,
+    };
+    span.or_else(|| value.span())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn locates_problems() {
+        let content = r#"[default]
+clone_path = "/path"
+repositories = [
+  {org = "crvshlab", name = "repo1"},
+  {org = "crvshlab", nmae = "repo2"},
+]
+
+[default.retry]
+jiter = true
+"#;
+        let file = Path::new("/config");
+
+        let mut problem = Problem::unknown_key(
+            Some("default"),
+            vec![Segment::Key("repositories".to_string()), Segment::Index(1), Segment::Key("nmae".to_string())],
+        );
+        problem.locate(file, content);
+        assert_eq!(Some((5, 22)), problem.line_col());
+        assert_eq!(
+            "/config:5:22: profile 'default', 'repositories[1].nmae': unknown key, it is ignored",
+            problem.to_string()
+        );
+
+        let mut problem = Problem::unknown_key(Some("default"), vec![Segment::Key("retry".to_string()), Segment::Key("jiter".to_string())]);
+        problem.locate(file, content);
+        assert_eq!(Some((9, 1)), problem.line_col());
+
+        // missing keys point to their table
+        let mut problem = Problem::error(Some("default"), vec![Segment::Key("user_name".to_string())], "missing");
+        problem.locate(file, content);
+        assert_eq!(Some((1, 1)), problem.line_col());
+    }
+}
@@ -0,0 +1,132 @@
+//! Keeping secrets out of the credentials and config files. String values
+//! may reference environment variables as `${NAME}`; they are resolved for
+//! the selected profile only, when the context is built. Errors name the
+//! variable, never its value.
+
+use std::env;
+use std::io;
+use toml::Value;
+
+pub use io::Result;
+
+/// Resolve the `${NAME}` references in all string values of `profile`,
+/// other profiles are returned as they are
+pub fn resolve_profile(mut toml: Value, profile: &str) -> Result<Value> {
+    if let Some(section) = toml.get_mut(profile) {
+        let resolved = interpolate(section.clone(), &|name| env::var(name).ok())
+            .map_err(|e| io::Error::new(e.kind(), format!("profile '{}': {}", profile, e)))?;
+        *section = resolved;
+    }
+    Ok(toml)
+}
+
+/// Replace `${NAME}` in all strings of `value` with `lookup(NAME)`. `$$`
+/// stands for a literal `$`.
+pub fn interpolate<F>(value: Value, lookup: &F) -> Result<Value>
+where
+    F: Fn(&str) -> Option<String>,
+{
+    Ok(match value {
+        Value::String(s) => Value::String(interpolate_str(&s, lookup)?),
+        Value::Array(a) => Value::Array(a.into_iter().map(|v| interpolate(v, lookup)).collect::<Result<_>>()?),
+        Value::Table(t) => Value::Table(
+            t.into_iter()
+                .map(|(k, v)| interpolate(v, lookup).map(|v| (k, v)))
+                .collect::<Result<_>>()?,
+        ),
+        v => v,
+    })
+}
+
+fn interpolate_str<F>(s: &str, lookup: &F) -> Result<String>
+where
+    F: Fn(&str) -> Option<String>,
+{
+    let mut result = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(i) = rest.find('$') {
+        result.push_str(&rest[..i]);
+        rest = &rest[i..];
+        if rest.starts_with("$$") {
+            result.push('$');
+            rest = &rest[2..];
+        } else if rest.starts_with("${") {
+            let end = rest
+                .find('}')
+                .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "unterminated '${' reference"))?;
+            let name = &rest[2..end];
+            let value = lookup(name).ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("environment variable '{}' is not set", name),
+                )
+            })?;
+            result.push_str(&value);
+            rest = &rest[end + 1..];
+        } else {
+            result.push('$');
+            rest = &rest[1..];
+        }
+    }
+    result.push_str(rest);
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file::read_bytes;
+
+    fn lookup(name: &str) -> Option<String> {
+        match name {
+            "TOKEN" => Some("s3cr3t".to_string()),
+            "HOME" => Some("/home/bardo".to_string()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn interpolates_strings() {
+        assert_eq!("s3cr3t", interpolate_str("${TOKEN}", &lookup).unwrap());
+        assert_eq!("/home/bardo/src", interpolate_str("${HOME}/src", &lookup).unwrap());
+        assert_eq!("$5 and $HOME and ${TOKEN}", interpolate_str("$5 and $HOME and $${TOKEN}", &lookup).unwrap());
+
+        let error = interpolate_str("${MISSING}", &lookup).err().unwrap().to_string();
+        assert_eq!("environment variable 'MISSING' is not set", error);
+        assert!(interpolate_str("${TOKEN", &lookup).is_err());
+    }
+
+    #[test]
+    fn interpolates_nested_values() {
+        let toml = read_bytes(
+            br#"
+            clone_path = "${HOME}/src"
+            jobs = 4
+            repositories = [{ org = "${HOME}" }]
+        "#,
+        )
+        .unwrap();
+
+        let toml = interpolate(toml, &lookup).unwrap();
+        assert_eq!("/home/bardo/src", toml["clone_path"].as_str().unwrap());
+        assert_eq!(4, toml["jobs"].as_integer().unwrap());
+        assert_eq!("/home/bardo", toml["repositories"][0]["org"].as_str().unwrap());
+    }
+
+    #[test]
+    fn resolves_selected_profile_only() {
+        let toml = read_bytes(
+            br#"
+            [default]
+            clone_path = "${BARDO_TEST_UNSET_VARIABLE}"
+            [other]
+            clone_path = "${BARDO_TEST_UNSET_VARIABLE}"
+        "#,
+        )
+        .unwrap();
+
+        let toml = resolve_profile(toml, "missing").unwrap();
+        assert_eq!("${BARDO_TEST_UNSET_VARIABLE}", toml["default"]["clone_path"].as_str().unwrap());
+        assert!(resolve_profile(toml, "other").is_err());
+    }
+}
@@ -1,13 +1,35 @@
 use clap::ArgMatches;
+use client::app::{AppAuth, AppCredentials};
+use client::cache::ResponseCache;
 use client::client::Github;
+use client::retry::RetryPolicy;
+use config::config::Retry;
 use config::context::BardoContext;
+use config::credentials::Credentials;
 use std::env;
+use std::time::Duration;
 
+use crate::commands::auth::crypt::{DecryptCommandExecutor, EncryptCommandExecutor};
+use crate::commands::auth::login::LoginCommandExecutor;
+use crate::commands::config::profile::{
+    CopyProfileCommandExecutor, CreateProfileCommandExecutor, ListProfilesCommandExecutor,
+    RemoveProfileCommandExecutor,
+};
+use crate::commands::config::repo::{
+    AddRepoCommandExecutor, ListReposCommandExecutor, RemoveRepoCommandExecutor,
+};
+use crate::commands::config::set::SetConfigCommandExecutor;
+use crate::commands::config::show::ShowConfigCommandExecutor;
+use crate::commands::config::validate::ValidateConfigCommandExecutor;
+use crate::commands::auth::status::AuthStatusCommandExecutor;
+use crate::commands::cache::PruneCacheCommandExecutor;
 use crate::commands::issues::get::GetIssuesCommandExecutor;
 use crate::commands::labels::get::GetLabelsCommand;
+use crate::commands::labels::sync::SyncLabelsCommandExecutor;
 use crate::commands::pulls::get::GetPullsCommand;
 use crate::commands::repo::clone::CloneRepoCommandExecutor;
 use crate::commands::repo::apply::ApplyCommandExecutor;
+use crate::commands::status::StatusCommandExecutor;
 use crate::commands::users::Command;
 use crate::cmd::CommandExecutor;
 
@@ -60,6 +82,73 @@ fn resolve_profile<'a>(matches: &'a ArgMatches) -> String {
         .expect("failed to resolve the profile")
 }
 
+/// Execute a command, its requests fail once a response shows that the
+/// token lacks a scope the command needs
+fn run_checked<E>(executor: E, gh: &Github, args: &Vec<Vec<&str>>)
+where
+    E: CommandExecutor,
+{
+    gh.require_scopes(executor.required_scopes());
+    executor.execute(args);
+}
+
+/// Create the client authenticated as the credentials of the profile say
+fn authenticate(context: &BardoContext, credentials: &Credentials, profile: &str) -> Result<Github, String> {
+    match (credentials, context.access_token()) {
+        (
+            Credentials::App {
+                app_id,
+                private_key_path,
+                installation_id,
+            },
+            _,
+        ) => {
+            let credentials = AppCredentials {
+                app_id: app_id.0,
+                private_key_path: private_key_path.0.clone(),
+                installation_id: installation_id.0,
+            };
+            AppAuth::new(credentials)
+                .map(Github::with_app)
+                .map_err(|e| format!("Profile '{}': {}", profile, e))
+        }
+        (_, Some(access_token)) => Ok(Github::new(&access_token.0)),
+        (_, None) => Err(format!(
+            "Profile '{}' has no access token yet, run `bardo auth login`",
+            profile
+        )),
+    }
+}
+
+fn retry_policy(retry: &Retry) -> RetryPolicy {
+    let mut policy = RetryPolicy::default();
+    if let Some(max_attempts) = retry.max_attempts() {
+        policy.max_attempts = max_attempts.max(1);
+    }
+    if let Some(ms) = retry.initial_backoff_ms() {
+        policy.initial_backoff = Duration::from_millis(ms);
+    }
+    if let Some(ms) = retry.max_backoff_ms() {
+        policy.max_backoff = Duration::from_millis(ms);
+    }
+    if let Some(jitter) = retry.jitter() {
+        policy.jitter = jitter;
+    }
+    if let Some(statuses) = retry.statuses() {
+        policy.statuses = statuses
+            .iter()
+            .filter_map(|s| http::StatusCode::from_u16(*s).ok())
+            .collect();
+    }
+    if let Some(methods) = retry.methods() {
+        policy.methods = methods
+            .iter()
+            .filter_map(|m| http::Method::from_bytes(m.as_bytes()).ok())
+            .collect();
+    }
+    policy
+}
+
 pub fn start() {
     let matches = clap_app!(
         bardo =>
@@ -67,6 +156,13 @@ pub fn start() {
             (author: "Sebastian Kaiser")
             (about: "The caretaker provides automations and more")
             (@arg PROFILE: -p --profile +takes_value +global "sets profile to use")
+            (@arg JOBS: -j --jobs +takes_value +global "number of repositories processed in parallel")
+            (@arg NO_CACHE: --("no-cache") +global "sends every request without revalidating cached responses")
+            (@arg INCLUDE_ARCHIVED: --("include-archived") +global "includes archived repositories matched by a regex entry")
+            (@arg INCLUDE_FORKS: --("include-forks") +global "includes forks matched by a regex entry")
+            (@arg GROUP: -g --group +takes_value +global "only uses the repositories of the group(s), comma separated")
+            (@arg TAG: -t --tag +takes_value +global "only uses the repositories with one of the tag(s), comma separated")
+            (@arg EXCLUDE: -x --exclude +takes_value +global "leaves out the repositories, comma separated as org/name or name")
             (@subcommand gh =>
              (about: "repository automations for Github")
              (@subcommand issue =>
@@ -78,7 +174,8 @@ pub fn start() {
                (@arg FORMAT: -f --format +takes_value "define the print format")
               )
               (@subcommand status =>
-               (about: "displays a summary of issues")
+               (about: "displays open issues and pull requests, the same as gh status")
+               (@arg REPO: -r --repo +takes_value "displays the summary of a single project")
               )
              )
              (@subcommand pr =>
@@ -90,6 +187,13 @@ pub fn start() {
              (@subcommand project =>
               (about: "helpers for dealing with projects")
              )
+             (@subcommand label =>
+              (about: "helpers for dealing with labels")
+              (@subcommand sync =>
+               (about: "creates and updates the labels of every repository as its labels_file says")
+               (@arg REPO: -r --repo +takes_value "syncs a single project")
+              )
+             )
              (@subcommand repo =>
               (about: "helpers for dealing with repositories")
               (@subcommand ls =>
@@ -126,7 +230,97 @@ pub fn start() {
               (about: "performs checks")
              )
              (@subcommand status =>
-              (about: "displays current status")
+              (about: "displays open issues and pull requests of all repositories using a single GraphQL query")
+              (@arg REPO: -r --repo +takes_value "displays the status of a single project")
+             )
+            )
+            (@subcommand auth =>
+             (about: "manages the access token of a profile")
+             (@subcommand login =>
+              (about: "obtains an access token through the device flow and stores it in the profile")
+              (@arg WEB: -w --web "uses the web flow with a local callback instead of the device flow")
+              (@arg PORT: --port +takes_value "the port of the web flow callback, defaults to 8080")
+              (@arg CLIENT_ID: --("client-id") +takes_value "the client id of the OAuth App, defaults to bardo_client_id of the profile")
+             )
+             (@subcommand status =>
+              (about: "shows the user, scopes, expiry and rate limit of the token of the profile")
+             )
+             (@subcommand encrypt =>
+              (about: "encrypts the credentials file with a passphrase")
+             )
+             (@subcommand decrypt =>
+              (about: "replaces the encrypted credentials file with a plain one")
+             )
+            )
+            (@subcommand config =>
+             (about: "manages the config file")
+             (@subcommand validate =>
+              (about: "checks the config file and prints all problems at once")
+             )
+             (@subcommand show =>
+              (about: "prints the profile and its credentials with the secrets hidden")
+             )
+             (@subcommand set =>
+              (about: "sets a key of the profile, an empty value removes it")
+              (@arg KEY: +required "one of clone_path, user_name, user_email, host, api_url, jobs, default_branch, labels_file")
+              (@arg VALUE: +required "the new value")
+              (@arg BACKUP: --backup "keeps the replaced file as config.bak")
+             )
+             (@subcommand repo =>
+              (about: "manages the repositories of the profile")
+              (@subcommand ls =>
+               (about: "lists the repositories as configured, regex entries are not expanded")
+              )
+              (@subcommand add =>
+               (about: "adds a repository to the profile")
+               (@arg REPO: +required "the repository as organization/name, or the organization with --regex")
+               (@arg REGEX: --regex +takes_value "adds the repositories of the organization matching the regex")
+               (@arg TAGS: --tags +takes_value "the tag(s) of the repository, comma separated")
+               (@arg DEFAULT_BRANCH: --("default-branch") +takes_value "the branch pull requests are opened against")
+               (@arg CLONE_URL: --("clone-url") +takes_value "the url the repository is cloned from")
+               (@arg ASSIGNEES: -a --assignees +takes_value "the assignee(s) of pull requests, comma separated")
+               (@arg REVIEWERS: --reviewers +takes_value "the reviewer(s) of pull requests, comma separated")
+               (@arg TEAM_REVIEWERS: --team_reviewers +takes_value "the team reviewer(s) of pull requests, comma separated")
+               (@arg LABELS_FILE: --("labels-file") +takes_value "the file with the labels of the repository")
+               (@arg BACKUP: --backup "keeps the replaced file as config.bak")
+              )
+              (@subcommand rm =>
+               (about: "removes a repository from the profile")
+               (@arg REPO: +required "the repository as organization/name, or the organization with --regex")
+               (@arg REGEX: --regex +takes_value "removes the regex entry of the organization")
+               (@arg BACKUP: --backup "keeps the replaced file as config.bak")
+              )
+             )
+             (@subcommand profile =>
+              (about: "manages the profiles of the config and credentials file")
+              (@subcommand ls =>
+               (about: "lists the profiles, the selected one marked with *")
+              )
+              (@subcommand create =>
+               (about: "creates a profile without repositories")
+               (@arg NAME: +required "the name of the profile")
+               (@arg CLONE_PATH: --("clone-path") +takes_value +required "the folder the repositories are cloned into")
+               (@arg BACKUP: --backup "keeps the replaced file as config.bak")
+              )
+              (@subcommand copy =>
+               (about: "copies a profile and its credentials")
+               (@arg FROM: +required "the profile to copy")
+               (@arg TO: +required "the name of the new profile")
+               (@arg BACKUP: --backup "keeps the replaced files as config.bak and credentials.bak")
+              )
+              (@subcommand rm =>
+               (about: "removes a profile and its credentials")
+               (@arg NAME: +required "the name of the profile")
+               (@arg BACKUP: --backup "keeps the replaced files as config.bak and credentials.bak")
+              )
+             )
+            )
+            (@subcommand cache =>
+             (about: "manages the cache of API responses")
+             (@subcommand prune =>
+              (about: "removes cached responses that were not used for a while")
+              (@arg OLDER_THAN: --("older-than") +takes_value "removes responses not used for this many days, defaults to 30")
+              (@arg ALL: -a --all "removes all cached responses")
              )
             )
     )
@@ -134,17 +328,6 @@ pub fn start() {
 
     let default_profile = resolve_profile(&matches);
 
-    let context = BardoContext::init(&default_profile).unwrap();
-    let access_token = &context
-        .credentials()
-        .profiles()
-        .get(&default_profile)
-        .unwrap()
-        .access_token()
-        .unwrap()
-        .0;
-    let gh = Github::new(access_token);
-
     let all_args = vec![
         "ALL",
         "REPO",
@@ -152,6 +335,7 @@ pub fn start() {
         "NAME",
         "FORMAT",
         "PROFILE",
+        "JOBS",
         "BRANCH",
         "MESSAGE",
         "COMMENT",
@@ -159,24 +343,160 @@ pub fn start() {
         "REVIEWERS",
         "TEAM_REVIEWERS",
         "CMD",
+        "OLDER_THAN",
+        "WEB",
+        "PORT",
+        "CLIENT_ID",
+        "NO_CACHE",
+        "INCLUDE_ARCHIVED",
+        "INCLUDE_FORKS",
+        "GROUP",
+        "TAG",
+        "EXCLUDE",
+        "BACKUP",
+        "KEY",
+        "VALUE",
+        "REGEX",
+        "TAGS",
+        "DEFAULT_BRANCH",
+        "CLONE_URL",
+        "LABELS_FILE",
+        "CLONE_PATH",
+        "FROM",
+        "TO",
     ];
 
+    // logging in has to work before the profile has a token, encrypting
+    // and decrypting only touch the credentials file
+    if let ("auth", Some(auth_matches)) = matches.subcommand() {
+        match auth_matches.subcommand() {
+            ("login", Some(login_matches)) => {
+                let args = get_args(login_matches, &all_args);
+                let context = match BardoContext::init(&default_profile) {
+                    Ok(context) => Some(context),
+                    Err(ref e) if e.kind() == std::io::ErrorKind::NotFound => None,
+                    Err(e) => return println!("{}", e),
+                };
+                return LoginCommandExecutor::new(default_profile, context).execute(&args);
+            }
+            ("encrypt", Some(encrypt_matches)) => {
+                return EncryptCommandExecutor.execute(&get_args(encrypt_matches, &all_args));
+            }
+            ("decrypt", Some(decrypt_matches)) => {
+                return DecryptCommandExecutor.execute(&get_args(decrypt_matches, &all_args));
+            }
+            _ => {}
+        }
+    }
+
+    // validating has to work with a broken config file, the others read and
+    // write the files as they are, without a token
+    if let ("config", Some(config_matches)) = matches.subcommand() {
+        return match config_matches.subcommand() {
+            ("validate", Some(validate_matches)) => {
+                ValidateConfigCommandExecutor.execute(&get_args(validate_matches, &all_args))
+            }
+            ("show", Some(show_matches)) => {
+                ShowConfigCommandExecutor::new(default_profile).execute(&get_args(show_matches, &all_args))
+            }
+            ("set", Some(set_matches)) => {
+                SetConfigCommandExecutor::new(default_profile).execute(&get_args(set_matches, &all_args))
+            }
+            ("repo", Some(repo_matches)) => match repo_matches.subcommand() {
+                ("ls", Some(ls_matches)) => {
+                    ListReposCommandExecutor::new(default_profile).execute(&get_args(ls_matches, &all_args))
+                }
+                ("add", Some(add_matches)) => {
+                    AddRepoCommandExecutor::new(default_profile).execute(&get_args(add_matches, &all_args))
+                }
+                ("rm", Some(rm_matches)) => {
+                    RemoveRepoCommandExecutor::new(default_profile).execute(&get_args(rm_matches, &all_args))
+                }
+                _ => println!("{}", repo_matches.usage()),
+            },
+            ("profile", Some(profile_matches)) => match profile_matches.subcommand() {
+                ("ls", Some(ls_matches)) => {
+                    ListProfilesCommandExecutor::new(default_profile).execute(&get_args(ls_matches, &all_args))
+                }
+                ("create", Some(create_matches)) => {
+                    CreateProfileCommandExecutor.execute(&get_args(create_matches, &all_args))
+                }
+                ("copy", Some(copy_matches)) => CopyProfileCommandExecutor.execute(&get_args(copy_matches, &all_args)),
+                ("rm", Some(rm_matches)) => RemoveProfileCommandExecutor.execute(&get_args(rm_matches, &all_args)),
+                _ => println!("{}", profile_matches.usage()),
+            },
+            _ => println!("{}", config_matches.usage()),
+        };
+    }
+
+    let context = match BardoContext::init(&default_profile) {
+        Ok(context) => context,
+        Err(e) => return println!("{}", e),
+    };
+    for warning in context.config().warnings() {
+        println!("warning: {}", warning);
+    }
+    for warning in context.credentials().warnings() {
+        println!("warning: {}", warning);
+    }
+    let credentials = match context.credentials().profiles().get(&default_profile) {
+        Some(credentials) => credentials,
+        None => return println!("The credentials file has no profile '{}'", default_profile),
+    };
+    let mut gh = match authenticate(&context, credentials, &default_profile) {
+        Ok(gh) => gh,
+        Err(e) => return println!("{}", e),
+    };
+    let section = match context.section() {
+        Some(section) => section,
+        None => return println!("The config file has no profile '{}'", default_profile),
+    };
+    let api_url = match (section.api_url(), section.host()) {
+        (Some(api_url), _) => gh.set_api_url(&api_url.0),
+        (None, Some(host)) => gh.set_host(&host.0),
+        (None, None) => Ok(()),
+    };
+    if let Err(e) = api_url {
+        return println!("Profile '{}': invalid 'host' or 'api_url': {}", default_profile, e);
+    }
+    if let Some(retry) = section.retry() {
+        gh.set_retry_policy(retry_policy(retry));
+    }
+    let http_cache = config::file::cache_dir().map(|dir| ResponseCache::new(dir.join("http")));
+    if !matches.is_present("NO_CACHE") {
+        gh.set_cache(http_cache.clone());
+    }
+    // shares the scope check with `gh`, the commands take ownership of it
+    let checker = gh.clone();
+
     match matches.subcommand() {
         ("gh", Some(gh_matches)) => match gh_matches.subcommand() {
             ("issue", Some(issue_matches)) => match issue_matches.subcommand() {
                 ("ls", Some(ls_matches)) => {
                     let args = get_args(ls_matches, &all_args);
-                    GetIssuesCommandExecutor::new(gh, context).execute(&args);
+                    run_checked(GetIssuesCommandExecutor::new(gh, context), &checker, &args);
+                }
+                ("status", Some(status_matches)) => {
+                    let args = get_args(status_matches, &all_args);
+                    run_checked(StatusCommandExecutor::new(gh, context), &checker, &args);
                 }
                 _ => unreachable!(),
             },
             ("pr", Some(pr_matches)) => match pr_matches.subcommand() {
                 ("ls", Some(ls_matches)) => {
                     let args = get_args(ls_matches, &all_args);
+                    checker.require_scopes(&["repo"]);
                     GetPullsCommand::new(context, gh).run(&args);
                 }
                 _ => unreachable!(),
             },
+            ("label", Some(label_matches)) => match label_matches.subcommand() {
+                ("sync", Some(sync_matches)) => {
+                    let args = get_args(sync_matches, &all_args);
+                    run_checked(SyncLabelsCommandExecutor::new(gh, context), &checker, &args);
+                }
+                _ => println!("{}", label_matches.usage()),
+            },
             ("project", Some(_project_matches)) => {
                 println!("project cmds");
             }
@@ -187,22 +507,46 @@ pub fn start() {
                 }
                 ("clone", Some(clone_matches)) => {
                     let args = get_args(clone_matches, &all_args);
-                    CloneRepoCommandExecutor::new(context).execute(&args);
+                    CloneRepoCommandExecutor::new(gh, context).execute(&args);
                 }
                 ("apply", Some(apply_matches)) => {
                     let args = get_args(apply_matches, &all_args);
-                    ApplyCommandExecutor::new(gh, context).execute(&args);
+                    run_checked(ApplyCommandExecutor::new(gh, context), &checker, &args);
                 }
                 _ => unreachable!(),
             },
             ("check", Some(_check_matches)) => {
                 println!("check cmds");
             }
+            ("status", Some(status_matches)) => {
+                let args = get_args(status_matches, &all_args);
+                run_checked(StatusCommandExecutor::new(gh, context), &checker, &args);
+            }
+            _ => unreachable!(),
+        },
+        ("auth", Some(auth_matches)) => match auth_matches.subcommand() {
+            ("status", Some(status_matches)) => {
+                let args = get_args(status_matches, &all_args);
+                AuthStatusCommandExecutor::new(gh, context).execute(&args);
+            }
+            _ => unreachable!(),
+        },
+        ("cache", Some(cache_matches)) => match cache_matches.subcommand() {
+            ("prune", Some(prune_matches)) => {
+                let args = get_args(prune_matches, &all_args);
+                PruneCacheCommandExecutor::new(http_cache).execute(&args);
+            }
             _ => unreachable!(),
         },
         ("test", Some(test_matches)) => match test_matches.subcommand() {
-            ("emails", Some(_)) => Command::new(context, gh).run(),
-            ("labels", Some(_)) => GetLabelsCommand::new(context, gh).run(),
+            ("emails", Some(_)) => {
+                checker.require_scopes(&["user:email"]);
+                Command::new(context, gh).run()
+            }
+            ("labels", Some(_)) => {
+                checker.require_scopes(&["repo"]);
+                GetLabelsCommand::new(context, gh).run()
+            }
             _ => unreachable!(),
         },
         ("", None) => println!("No subcommand was used"),
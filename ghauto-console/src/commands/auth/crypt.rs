@@ -0,0 +1,31 @@
+use crate::cmd::CommandExecutor;
+use config::credentials::{decrypt_credentials, encrypt_credentials};
+
+/// Replaces the credentials file with one encrypted with a passphrase.
+/// Runs without a context, as reading the credentials would ask for the
+/// passphrase.
+pub struct EncryptCommandExecutor;
+
+impl CommandExecutor for EncryptCommandExecutor {
+    fn execute(&self, _args: &Vec<Vec<&str>>) {
+        match encrypt_credentials() {
+            Ok(path) => println!(
+                "Encrypted the credentials to {}, commands ask for the passphrase unless BARDO_PASSPHRASE is set",
+                path.display()
+            ),
+            Err(e) => println!("Could not encrypt the credentials: {}", e),
+        }
+    }
+}
+
+/// Replaces the encrypted credentials file with a plain one
+pub struct DecryptCommandExecutor;
+
+impl CommandExecutor for DecryptCommandExecutor {
+    fn execute(&self, _args: &Vec<Vec<&str>>) {
+        match decrypt_credentials() {
+            Ok(path) => println!("Decrypted the credentials to {}", path.display()),
+            Err(e) => println!("Could not decrypt the credentials: {}", e),
+        }
+    }
+}
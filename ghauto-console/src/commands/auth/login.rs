@@ -0,0 +1,92 @@
+use crate::cmd::CommandExecutor;
+use client::gh_auth::{open_browser, OAuthApp};
+use config::context::BardoContext;
+use config::credentials::{check_login_profile, write_access_token, AccessToken, ClientId};
+
+/// The port the web flow listens on unless `--port` is given. It has to
+/// match the callback url of the OAuth App.
+const DEFAULT_CALLBACK_PORT: u16 = 8080;
+
+/// Logs in through the device flow or, with `--web`, the web flow and
+/// stores the token in the profile. Runs without a context, as there may
+/// be no credentials file yet.
+pub struct LoginCommandExecutor {
+    profile: String,
+    context: Option<BardoContext>,
+}
+
+impl LoginCommandExecutor {
+    pub fn new(profile: String, context: Option<BardoContext>) -> Self {
+        Self {
+            profile: profile,
+            context: context,
+        }
+    }
+}
+
+impl CommandExecutor for LoginCommandExecutor {
+    fn execute(&self, args: &Vec<Vec<&str>>) {
+        // the context may be missing, so the file itself is checked
+        if let Err(e) = check_login_profile(&self.profile) {
+            return println!("{}", e);
+        }
+        let credentials = self
+            .context
+            .as_ref()
+            .and_then(|c| c.credentials().profiles().get(&self.profile));
+
+        let client_id = match crate::utils::pick_client_id(args)
+            .map(String::from)
+            .or_else(|| credentials.and_then(|c| c.client_id()).map(|id| id.0.clone()))
+        {
+            Some(client_id) => client_id,
+            None => return println!("No client id found in profile '{}', pass one with --client-id", self.profile),
+        };
+        let client_secret = credentials.and_then(|c| c.client_secret()).map(|s| s.0.clone());
+
+        let mut app = OAuthApp::new(&client_id, client_secret);
+        let section = self
+            .context
+            .as_ref()
+            .and_then(|c| c.config().get_profiles().get(&self.profile));
+        if let Some(section) = section {
+            let web_url = match section.web_url() {
+                Ok(web_url) => web_url,
+                Err(e) => return println!("Profile '{}': invalid 'host' or 'api_url': {}", self.profile, e),
+            };
+            if let Err(e) = app.set_base_url(web_url.as_str()) {
+                return println!("Invalid host '{}': {}", web_url, e);
+            }
+        }
+
+        let token = if crate::utils::use_web_flow(args) {
+            let port = match crate::utils::pick_port(args).map(|p| p.parse::<u16>()) {
+                Some(Ok(port)) => port,
+                Some(Err(_)) => return println!("--port expects a port number"),
+                None => DEFAULT_CALLBACK_PORT,
+            };
+            app.web_flow(port, |url| {
+                println!("Opening {} in your browser", url);
+                open_browser(url);
+            })
+        } else {
+            app.device_flow(|code| {
+                println!("First copy your one-time code: {}", code.user_code);
+                println!("Then open {} in a browser and enter it", code.verification_uri);
+            })
+        };
+
+        let token = match token {
+            Ok(token) => token,
+            Err(e) => return println!("{}", e),
+        };
+        match write_access_token(&self.profile, &ClientId(client_id), &AccessToken(token.access_token)) {
+            Ok(_) => println!(
+                "Logged in, the token is stored in profile '{}' (scopes: {})",
+                self.profile,
+                token.scopes.join(", ")
+            ),
+            Err(e) => println!("Could not store the token: {}", e),
+        }
+    }
+}
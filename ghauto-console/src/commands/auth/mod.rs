@@ -0,0 +1,3 @@
+pub mod crypt;
+pub mod login;
+pub mod status;
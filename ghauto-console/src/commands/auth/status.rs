@@ -0,0 +1,98 @@
+use crate::cmd::CommandExecutor;
+use client::client::{Executor, Github};
+use client::headers::token_expiration;
+use client::scopes;
+use config::context::BardoContext;
+
+use chrono::{Local, TimeZone};
+use termion::{color, style};
+
+#[derive(Deserialize, Debug)]
+pub struct User {
+    login: String,
+    name: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Organization {
+    login: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Membership {
+    state: String,
+    role: String,
+    organization: Organization,
+}
+
+/// Shows who the token of the profile belongs to and what it may do
+pub struct AuthStatusCommandExecutor {
+    gh: Github,
+    context: BardoContext,
+}
+
+impl AuthStatusCommandExecutor {
+    pub fn new(gh: Github, context: BardoContext) -> Self {
+        Self {
+            gh: gh,
+            context: context,
+        }
+    }
+}
+
+impl CommandExecutor for AuthStatusCommandExecutor {
+    fn execute(&self, _args: &Vec<Vec<&str>>) {
+        let (headers, user) = match self.gh.get().user().execute::<User>() {
+            Ok((headers, _, Some(user))) => (headers, user),
+            Ok(_) => return println!("Could not read the authenticated user"),
+            Err(e) => return println!("The token of profile '{}' does not work: {}", self.context.profile(), e),
+        };
+
+        println!("");
+        println!(
+            "Logged in to {} as {}{}{}{}",
+            self.gh.get_api_url(),
+            style::Bold,
+            user.login,
+            style::Reset,
+            user.name.map(|n| format!(" ({})", n)).unwrap_or_default()
+        );
+        match scopes::granted(&headers) {
+            Some(ref granted) if granted.is_empty() => println!("Scopes:     none"),
+            Some(granted) => println!("Scopes:     {}", granted.join(", ")),
+            None => println!("Scopes:     not reported, e.g. for fine grained tokens"),
+        }
+        println!(
+            "Expires:    {}",
+            token_expiration(&headers).unwrap_or_else(|| "never".to_string())
+        );
+
+        let rate = self.gh.rate_limit();
+        if let (Some(remaining), Some(limit)) = (rate.remaining, rate.limit) {
+            let reset = rate
+                .reset
+                .and_then(|r| Local.timestamp_opt(r as i64, 0).single())
+                .map(|r| format!(", resets at {}", r.format("%H:%M:%S")))
+                .unwrap_or_default();
+            println!("Rate limit: {} of {} requests left{}", remaining, limit, reset);
+        }
+
+        match self.gh.get().user().memberships().orgs().execute::<Vec<Membership>>() {
+            Ok((_, _, memberships)) => {
+                let memberships = memberships.unwrap_or_default();
+                println!("Organizations:{}", if memberships.is_empty() { " none" } else { "" });
+                for m in memberships.iter() {
+                    println!(
+                        "  {}{}{} ({}, {})",
+                        color::Fg(color::Magenta),
+                        m.organization.login,
+                        style::Reset,
+                        m.role,
+                        m.state
+                    );
+                }
+            }
+            Err(e) => println!("Organizations: unavailable, {}", e),
+        }
+    }
+}
@@ -0,0 +1,44 @@
+use crate::cmd::CommandExecutor;
+use client::cache::ResponseCache;
+
+use std::time::Duration;
+
+/// Entries not revalidated for this many days are pruned by default
+const DEFAULT_MAX_AGE_DAYS: u64 = 30;
+
+pub struct PruneCacheCommandExecutor {
+    cache: Option<ResponseCache>,
+}
+
+impl PruneCacheCommandExecutor {
+    pub fn new(cache: Option<ResponseCache>) -> Self {
+        Self {
+            cache: cache,
+        }
+    }
+}
+
+impl CommandExecutor for PruneCacheCommandExecutor {
+    fn execute(&self, args: &Vec<Vec<&str>>) {
+        let cache = match &self.cache {
+            Some(cache) => cache,
+            None => return println!("Could not find a cache directory"),
+        };
+
+        let days = match crate::utils::pick_older_than(args).map(|d| d.parse::<u64>()) {
+            Some(Ok(days)) => days,
+            Some(Err(_)) => return println!("--older-than expects a number of days"),
+            None => DEFAULT_MAX_AGE_DAYS,
+        };
+        let max_age = if crate::utils::print_all(args) {
+            Duration::from_secs(0)
+        } else {
+            Duration::from_secs(days * 24 * 60 * 60)
+        };
+
+        match cache.prune(max_age) {
+            Ok(removed) => println!("removed {} cached responses from {}", removed, cache.dir().display()),
+            Err(e) => println!("Could not prune {}: {}", cache.dir().display(), e),
+        }
+    }
+}
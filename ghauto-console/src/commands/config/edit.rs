@@ -0,0 +1,66 @@
+//! Reading the config and credentials files to change them and writing
+//! them back. The files are read as they are written, `${NAME}`
+//! references are kept.
+
+use config::config::{config_file, load_config, write_config, BardoConfig};
+use config::credentials::{read_credentials, write_credentials, BardoCredentials};
+use config::file::WriteOptions;
+
+use std::io;
+
+/// The config file, an empty config if it does not exist yet. Errors are
+/// printed.
+pub fn load() -> Option<BardoConfig> {
+    let path = match config_file() {
+        Some(path) => path,
+        None => {
+            println!("Could not locate the config file");
+            return None;
+        }
+    };
+
+    match load_config(&path) {
+        Ok(config) => Some(config),
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => Some(BardoConfig::default()),
+        Err(e) => {
+            println!("Could not read {}: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+/// The credentials file, `None` if it does not exist
+pub fn load_credentials() -> io::Result<Option<BardoCredentials>> {
+    match BardoCredentials::read_from(read_credentials) {
+        Ok(credentials) => Ok(Some(credentials)),
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// `--backup` keeps the replaced file as `<name>.bak`
+fn options(args: &Vec<Vec<&str>>) -> WriteOptions {
+    WriteOptions::new().backup(crate::utils::backup(args))
+}
+
+/// Write the config back, returns whether it was written
+pub fn save(config: &BardoConfig, args: &Vec<Vec<&str>>) -> bool {
+    match config.write_to(|c| write_config(c, &options(args))) {
+        Ok(()) => true,
+        Err(e) => {
+            println!("Could not write the config file: {}", e);
+            false
+        }
+    }
+}
+
+/// Write the credentials back, returns whether they were written
+pub fn save_credentials(credentials: &BardoCredentials, args: &Vec<Vec<&str>>) -> bool {
+    match credentials.write_to(|c| write_credentials(c, &options(args))) {
+        Ok(()) => true,
+        Err(e) => {
+            println!("Could not write the credentials file: {}", e);
+            false
+        }
+    }
+}
@@ -0,0 +1,6 @@
+pub mod edit;
+pub mod profile;
+pub mod repo;
+pub mod set;
+pub mod show;
+pub mod validate;
@@ -0,0 +1,135 @@
+use crate::cmd::CommandExecutor;
+use crate::commands::config::edit;
+use config::config::{ClonePath, Configuration};
+
+/// Lists the profiles of the config file, the selected one marked with `*`
+pub struct ListProfilesCommandExecutor {
+    profile: String,
+}
+
+impl ListProfilesCommandExecutor {
+    pub fn new(profile: String) -> Self {
+        Self {
+            profile: profile,
+        }
+    }
+}
+
+impl CommandExecutor for ListProfilesCommandExecutor {
+    fn execute(&self, _args: &Vec<Vec<&str>>) {
+        let config = match edit::load() {
+            Some(config) => config,
+            None => return,
+        };
+
+        let mut names: Vec<&String> = config.get_profiles().keys().collect();
+        names.sort();
+        if names.is_empty() {
+            return println!("The config file has no profiles");
+        }
+        for name in names {
+            let marker = if *name == self.profile { "*" } else { " " };
+            println!("{} {}", marker, name);
+        }
+    }
+}
+
+/// Creates a profile with a clone path and no repositories
+pub struct CreateProfileCommandExecutor;
+
+impl CommandExecutor for CreateProfileCommandExecutor {
+    fn execute(&self, args: &Vec<Vec<&str>>) {
+        let name = crate::utils::pick_value(args, "NAME").unwrap();
+        let clone_path = crate::utils::pick_value(args, "CLONE_PATH").unwrap();
+        let mut config = match edit::load() {
+            Some(config) => config,
+            None => return,
+        };
+        if config.get_profiles().contains_key(name) {
+            return println!("Profile '{}' already exists", name);
+        }
+
+        config
+            .get_profiles_mut()
+            .insert(name.to_string(), Configuration::new(ClonePath(clone_path.to_string())));
+        if edit::save(&config, args) {
+            println!("Created profile '{}', run `bardo -p {} auth login` to add a token", name, name);
+        }
+    }
+}
+
+/// Copies a profile of the config file and, if there is one, its
+/// credentials
+pub struct CopyProfileCommandExecutor;
+
+impl CommandExecutor for CopyProfileCommandExecutor {
+    fn execute(&self, args: &Vec<Vec<&str>>) {
+        let from = crate::utils::pick_value(args, "FROM").unwrap();
+        let to = crate::utils::pick_value(args, "TO").unwrap();
+        let mut config = match edit::load() {
+            Some(config) => config,
+            None => return,
+        };
+        let section = match config.get_profiles().get(from) {
+            Some(section) => section.clone(),
+            None => return println!("The config file has no profile '{}'", from),
+        };
+        if config.get_profiles().contains_key(to) {
+            return println!("Profile '{}' already exists", to);
+        }
+        let mut credentials = match edit::load_credentials() {
+            Ok(credentials) => credentials,
+            Err(e) => return println!("Could not read the credentials: {}", e),
+        };
+
+        config.get_profiles_mut().insert(to.to_string(), section);
+        if !edit::save(&config, args) {
+            return;
+        }
+        let copied = credentials.as_mut().and_then(|c| {
+            let profile = c.profiles().get(from)?.clone();
+            c.profiles_mut().insert(to.to_string(), profile);
+            Some(c)
+        });
+        match copied {
+            Some(credentials) if !edit::save_credentials(credentials, args) => {}
+            Some(_) => println!("Copied profile '{}' with its credentials to '{}'", from, to),
+            None => println!("Copied profile '{}' to '{}'", from, to),
+        }
+    }
+}
+
+/// Removes a profile from the config and the credentials file
+pub struct RemoveProfileCommandExecutor;
+
+impl CommandExecutor for RemoveProfileCommandExecutor {
+    fn execute(&self, args: &Vec<Vec<&str>>) {
+        let name = crate::utils::pick_value(args, "NAME").unwrap();
+        let mut config = match edit::load() {
+            Some(config) => config,
+            None => return,
+        };
+        let mut credentials = match edit::load_credentials() {
+            Ok(credentials) => credentials,
+            Err(e) => return println!("Could not read the credentials: {}", e),
+        };
+
+        let in_config = config.get_profiles_mut().remove(name).is_some();
+        let in_credentials = credentials
+            .as_mut()
+            .is_some_and(|c| c.profiles_mut().remove(name).is_some());
+        if !in_config && !in_credentials {
+            return println!("There is no profile '{}'", name);
+        }
+
+        if in_config && !edit::save(&config, args) {
+            return;
+        }
+        if let Some(credentials) = credentials.as_ref().filter(|_| in_credentials) {
+            if !edit::save_credentials(credentials, args) {
+                return;
+            }
+        }
+        println!("Removed profile '{}'", name);
+    }
+}
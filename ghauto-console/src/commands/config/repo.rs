@@ -0,0 +1,177 @@
+use crate::cmd::CommandExecutor;
+use crate::commands::config::edit;
+use config::config::{Name, Org, Regex, Repository, Tag};
+
+/// The values of a comma separated option
+fn list(args: &Vec<Vec<&str>>, key: &str) -> Option<Vec<String>> {
+    crate::utils::pick_value(args, key).map(|v| {
+        v.split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(String::from)
+            .collect()
+    })
+}
+
+/// The entry described by `ORG/NAME` or by `ORG` and `--regex`
+fn pick_entry<'a>(args: &'a Vec<Vec<&'a str>>) -> Result<(&'a str, Option<&'a str>, Option<&'a str>), String> {
+    let repo = crate::utils::pick_value(args, "REPO").ok_or("the repository is missing")?;
+    let regex = crate::utils::pick_value(args, "REGEX");
+    match (repo.split_once('/'), regex) {
+        (Some((org, name)), None) if !org.is_empty() && !name.is_empty() => Ok((org, Some(name), None)),
+        (None, Some(regex)) if !repo.is_empty() => Ok((repo, None, Some(regex))),
+        (Some(_), Some(_)) => Err("use either ORG/NAME or ORG with --regex".to_string()),
+        _ => Err(format!("'{}' is no repository, expected ORG/NAME or ORG with --regex", repo)),
+    }
+}
+
+fn is_entry(repository: &Repository, org: &str, name: Option<&str>, regex: Option<&str>) -> bool {
+    repository.org().0 == org
+        && repository.name().map(|n| n.0.as_str()) == name
+        && repository.regex().map(|r| r.0.as_str()) == regex
+}
+
+fn describe(repository: &Repository) -> String {
+    match (repository.name(), repository.regex()) {
+        (Some(name), _) => format!("{}/{}", repository.org().0, name.0),
+        (None, Some(regex)) => format!("{} --regex '{}'", repository.org().0, regex.0),
+        (None, None) => repository.org().0.clone(),
+    }
+}
+
+/// Adds a repository to the selected profile
+pub struct AddRepoCommandExecutor {
+    profile: String,
+}
+
+impl AddRepoCommandExecutor {
+    pub fn new(profile: String) -> Self {
+        Self {
+            profile: profile,
+        }
+    }
+}
+
+impl CommandExecutor for AddRepoCommandExecutor {
+    fn execute(&self, args: &Vec<Vec<&str>>) {
+        let (org, name, regex) = match pick_entry(args) {
+            Ok(entry) => entry,
+            Err(e) => return println!("{}", e),
+        };
+        let mut config = match edit::load() {
+            Some(config) => config,
+            None => return,
+        };
+        let section = match config.get_profiles_mut().get_mut(&self.profile) {
+            Some(section) => section,
+            None => return println!("The config file has no profile '{}'", self.profile),
+        };
+        if let Some(existing) = section.repositories().iter().find(|r| is_entry(r, org, name, regex)) {
+            return println!("Profile '{}' already has {}", self.profile, describe(existing));
+        }
+
+        let mut repository = Repository::new(
+            Org(org.to_string()),
+            name.map(|n| Name(n.to_string())),
+            regex.map(|r| Regex(r.to_string())),
+        );
+        if let Some(tags) = list(args, "TAGS") {
+            *repository.tags_mut() = tags.into_iter().map(Tag).collect();
+        }
+        let settings = repository.settings_mut();
+        settings.set_default_branch(crate::utils::pick_value(args, "DEFAULT_BRANCH").map(String::from));
+        settings.set_reviewers(list(args, "REVIEWERS"));
+        settings.set_team_reviewers(list(args, "TEAM_REVIEWERS"));
+        settings.set_assignees(list(args, "ASSIGNEES"));
+        settings.set_labels_file(crate::utils::pick_value(args, "LABELS_FILE").map(String::from));
+        settings.set_clone_url(crate::utils::pick_value(args, "CLONE_URL").map(String::from));
+
+        let added = describe(&repository);
+        section.repositories_mut().add(repository);
+        if edit::save(&config, args) {
+            println!("Added {} to profile '{}'", added, self.profile);
+        }
+    }
+}
+
+/// Removes a repository from the selected profile
+pub struct RemoveRepoCommandExecutor {
+    profile: String,
+}
+
+impl RemoveRepoCommandExecutor {
+    pub fn new(profile: String) -> Self {
+        Self {
+            profile: profile,
+        }
+    }
+}
+
+impl CommandExecutor for RemoveRepoCommandExecutor {
+    fn execute(&self, args: &Vec<Vec<&str>>) {
+        let (org, name, regex) = match pick_entry(args) {
+            Ok(entry) => entry,
+            Err(e) => return println!("{}", e),
+        };
+        let mut config = match edit::load() {
+            Some(config) => config,
+            None => return,
+        };
+        let section = match config.get_profiles_mut().get_mut(&self.profile) {
+            Some(section) => section,
+            None => return println!("The config file has no profile '{}'", self.profile),
+        };
+
+        let repositories = &mut section.repositories_mut().0;
+        let before = repositories.len();
+        repositories.retain(|r| !is_entry(r, org, name, regex));
+        if repositories.len() == before {
+            return println!("Profile '{}' has no such repository", self.profile);
+        }
+        if edit::save(&config, args) {
+            println!("Removed the repository from profile '{}'", self.profile);
+        }
+    }
+}
+
+/// Lists the repositories of the selected profile as configured, regex
+/// entries are not expanded
+pub struct ListReposCommandExecutor {
+    profile: String,
+}
+
+impl ListReposCommandExecutor {
+    pub fn new(profile: String) -> Self {
+        Self {
+            profile: profile,
+        }
+    }
+}
+
+impl CommandExecutor for ListReposCommandExecutor {
+    fn execute(&self, _args: &Vec<Vec<&str>>) {
+        let config = match edit::load() {
+            Some(config) => config,
+            None => return,
+        };
+        let section = match config.get_profiles().get(&self.profile) {
+            Some(section) => section,
+            None => return println!("The config file has no profile '{}'", self.profile),
+        };
+
+        if section.repositories().is_empty() {
+            return println!("Profile '{}' has no repositories", self.profile);
+        }
+        for repository in section.repositories() {
+            let mut line = describe(repository);
+            if !repository.tags().is_empty() {
+                let tags: Vec<&str> = repository.tags().iter().map(|t| t.0.as_str()).collect();
+                line.push_str(&format!("  tags: {}", tags.join(", ")));
+            }
+            for (key, value) in repository.settings().to_toml() {
+                line.push_str(&format!("  {}: {}", key, value));
+            }
+            println!("{}", line);
+        }
+    }
+}
@@ -0,0 +1,37 @@
+use crate::cmd::CommandExecutor;
+use crate::commands::config::edit;
+
+/// Sets a key of the selected profile, an empty value removes it
+pub struct SetConfigCommandExecutor {
+    profile: String,
+}
+
+impl SetConfigCommandExecutor {
+    pub fn new(profile: String) -> Self {
+        Self {
+            profile: profile,
+        }
+    }
+}
+
+impl CommandExecutor for SetConfigCommandExecutor {
+    fn execute(&self, args: &Vec<Vec<&str>>) {
+        let key = crate::utils::pick_value(args, "KEY").unwrap();
+        let value = crate::utils::pick_value(args, "VALUE").unwrap();
+        let mut config = match edit::load() {
+            Some(config) => config,
+            None => return,
+        };
+        let section = match config.get_profiles_mut().get_mut(&self.profile) {
+            Some(section) => section,
+            None => return println!("The config file has no profile '{}'", self.profile),
+        };
+
+        if let Err(e) = section.set(key, value) {
+            return println!("{}", e);
+        }
+        if edit::save(&config, args) {
+            println!("Set {} of profile '{}'", key, self.profile);
+        }
+    }
+}
@@ -0,0 +1,55 @@
+use crate::cmd::CommandExecutor;
+use crate::commands::config::edit;
+use config::config::BardoConfig;
+
+use termion::{color, style};
+
+/// Prints the selected profile as written to the config file and its
+/// credentials with the secrets hidden
+pub struct ShowConfigCommandExecutor {
+    profile: String,
+}
+
+impl ShowConfigCommandExecutor {
+    pub fn new(profile: String) -> Self {
+        Self {
+            profile: profile,
+        }
+    }
+}
+
+impl CommandExecutor for ShowConfigCommandExecutor {
+    fn execute(&self, _args: &Vec<Vec<&str>>) {
+        let config = match edit::load() {
+            Some(config) => config,
+            None => return,
+        };
+        let section = match config.get_profiles().get(&self.profile) {
+            Some(section) => section.clone(),
+            None => return println!("The config file has no profile '{}'", self.profile),
+        };
+
+        let mut shown = BardoConfig::default();
+        shown.get_profiles_mut().insert(self.profile.clone(), section);
+        match shown.to_toml_string("") {
+            Ok(toml) => print!("{}", toml),
+            Err(e) => return println!("Could not print the profile: {}", e),
+        }
+
+        println!();
+        let credentials = match edit::load_credentials() {
+            Ok(Some(credentials)) => credentials,
+            Ok(None) => return println!("{}# no credentials file{}", style::Faint, style::Reset),
+            Err(e) => return println!("Could not read the credentials: {}", e),
+        };
+        match credentials.profiles().get(&self.profile) {
+            Some(credentials) => {
+                println!("{}# credentials ({}){}", color::Fg(color::Green), credentials.kind(), style::Reset);
+                for (key, value) in credentials.redacted() {
+                    println!("{} = {}", key, value);
+                }
+            }
+            None => println!("{}# no credentials for this profile{}", style::Faint, style::Reset),
+        }
+    }
+}
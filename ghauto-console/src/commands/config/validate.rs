@@ -0,0 +1,40 @@
+use crate::cmd::CommandExecutor;
+use config::config::{config_file, validate_config};
+
+use termion::{color, style};
+
+/// Checks the config file and prints all problems at once. Runs without a
+/// context, which refuses to read a config file with errors.
+pub struct ValidateConfigCommandExecutor;
+
+impl CommandExecutor for ValidateConfigCommandExecutor {
+    fn execute(&self, _args: &Vec<Vec<&str>>) {
+        let path = match config_file() {
+            Some(path) => path,
+            None => return println!("Could not locate the config file"),
+        };
+        let problems = match validate_config(&path) {
+            Ok(problems) => problems,
+            Err(e) => return println!("Could not read {}: {}", path.display(), e),
+        };
+
+        for problem in problems.iter() {
+            if problem.is_error() {
+                println!("{}error{}: {}", color::Fg(color::Red), style::Reset, problem);
+            } else {
+                println!("{}warning{}: {}", color::Fg(color::Yellow), style::Reset, problem);
+            }
+        }
+
+        let errors = problems.iter().filter(|p| p.is_error()).count();
+        let warnings = problems.len() - errors;
+        if problems.is_empty() {
+            println!("{} is valid", path.display());
+        } else {
+            println!("{} errors, {} warnings", errors, warnings);
+        }
+        if errors > 0 {
+            std::process::exit(1);
+        }
+    }
+}
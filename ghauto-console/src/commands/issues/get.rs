@@ -1,17 +1,27 @@
-use crate::cmd::{Command, IterableCommand, HttpResponse, ResultIterator, PrintStd};
-use crate::cmd::CommandExecutor;
-use crate::commands::repo::get::{GetRepoCmd, Repository};
+use crate::cmd::{CommandExecutor, PrintStd};
+use crate::commands::repo::get::Repository;
 use crate::display::FmtDuration;
-use client::client::{Executor, Github, Result};
+use client::asynchronous::{block_on, AsyncExecutor, BoxFuture};
+use client::client::{Github, Result};
+use client::params::State;
 use config::context::BardoContext;
 
 use chrono::{DateTime, Duration, Utc};
+use futures_util::future::{try_join, try_join_all};
+use futures_util::stream::{self, StreamExt};
+use http::{HeaderMap, StatusCode};
 use itertools::Itertools;
 use prettytable::{format, Table};
 use std::convert::TryInto;
 use std::fmt::{Display, Formatter, Result as FmtResult};
 use termion::{color, style};
 
+/// The number of issues GitHub returns per page by default
+const PAGE_SIZE: usize = 30;
+
+/// The number of issues fetched per page with `--all`, the most GitHub allows
+const ALL_PAGE_SIZE: u32 = 100;
+
 #[derive(Deserialize, Debug)]
 pub struct IssueLabel {
     name: String,
@@ -72,31 +82,47 @@ impl<'a> GetIssuesCommand<'a> {
     }
 }
 
-impl<'a> Command<GetIssuesCommandResult> for GetIssuesCommand<'a> {
-    fn execute(&self) -> Result<GetIssuesCommandResult> {
-        let cmd: FetchOpenIssuesCmd = FetchOpenIssuesCmd::new(&self.gh, self.org, self.name);
-        let (_, _, repo_res) = GetRepoCmd(&self.gh, self.org, self.name).execute().unwrap();
-        let repo: Repository = repo_res.unwrap();
-        let full_name = repo.full_name();
-        let num_total_issues = *repo.open_issue_count();
-        println!("");
+impl<'a> GetIssuesCommand<'a> {
+    /// Fetch the repository and the first page of issues at the same time.
+    /// With `--all` the remaining pages follow at once, their number is
+    /// known from the open issue count of the repository.
+    async fn execute(&self) -> Result<GetIssuesCommandResult> {
+        let per_page = if self.b_print_all { ALL_PAGE_SIZE } else { PAGE_SIZE as u32 };
+        let repo = self.gh.get().repos().owner(self.org).repo(self.name).execute_async::<Repository>();
+        let ((_, _, repo), (_, _, first)) = try_join(repo, self.fetch_page(per_page, 1)).await?;
 
-        let mut iter = cmd.execute_iter().into_iter();
+        let full_name = repo
+            .as_ref()
+            .map(|r| r.full_name().to_string())
+            .unwrap_or_else(|| format!("{}/{}", self.org, self.name));
+        let num_total_issues = repo.map(|r| *r.open_issue_count()).unwrap_or(0);
+        let mut issues = first.unwrap_or_default();
 
         if self.b_print_all == false {
-            let (_, _, res) = iter.next().unwrap().unwrap();
-            let issues = res.unwrap();
             let fetched_issues = issues.len().try_into().unwrap();
-            return Ok(GetIssuesCommandResult(full_name.to_string(), issues, num_total_issues, Some(fetched_issues)));
-        } else {
-            let mut issues_mut = Vec::with_capacity(num_total_issues.try_into().unwrap());
-            for next in iter {
-                let (_, _, res) = next.unwrap();
-                issues_mut.append(res.unwrap().as_mut());
-            }
+            return Ok(GetIssuesCommandResult(full_name, issues, num_total_issues, Some(fetched_issues)));
+        }
 
-            return Ok(GetIssuesCommandResult(full_name.to_string(), issues_mut, num_total_issues, None));
+        // GitHub counts pull requests as issues, so does the issues endpoint
+        let pages = (num_total_issues + per_page - 1) / per_page;
+        let rest = try_join_all((2..=pages).map(|page| self.fetch_page(per_page, page))).await?;
+        for (_, _, page) in rest {
+            issues.extend(page.unwrap_or_default());
         }
+        Ok(GetIssuesCommandResult(full_name, issues, num_total_issues, None))
+    }
+
+    fn fetch_page(&self, per_page: u32, page: u32) -> BoxFuture<'a, Result<(HeaderMap, StatusCode, Option<Vec<Issue>>)>> {
+        self.gh
+            .get()
+            .repos()
+            .owner(self.org)
+            .repo(self.name)
+            .issues()
+            .state(State::Open)
+            .per_page(per_page)
+            .page(&page.to_string())
+            .execute_async::<Vec<Issue>>()
     }
 }
 
@@ -116,23 +142,38 @@ impl GetIssuesCommandExecutor {
 
 impl<'a> CommandExecutor for GetIssuesCommandExecutor {
 
+    fn required_scopes(&self) -> &'static [&'static str] {
+        &["repo"]
+    }
+
     fn execute(&self, args: &Vec<Vec<&str>>) {
         let profile = self.context.profile();
-        let section = &self.context.config().get_profiles()[profile];
-        let repositories = section.repositories();
+        let section = match self.context.section() {
+            Some(section) => section,
+            None => return println!("The config file has no profile '{}'", profile),
+        };
         let print_all = crate::utils::print_all(args);
-        let maybe_repo = crate::utils::pick_repo(args);
+        let jobs = crate::utils::pick_jobs(args, section);
+
+        let repositories = crate::utils::pick_repositories(&self.gh, profile, section, args);
 
-        repositories
-            .iter()
-            .filter(|r| crate::utils::maybe_filter_repo(r, &maybe_repo))
-            .for_each(|repo| match (repo.org(), repo.name()) {
-                (o, Some(n)) => {match GetIssuesCommand::new(&self.gh, &o.0, &n.0, print_all).execute() {
-                    Ok(res) => res.to_std_out(),
-                    Err(_) => (),
-                }},
-                (_, _) => (),
-            });
+        // up to `jobs` repositories are fetched at the same time, the results
+        // keep the order of the configuration
+        let fetch_all = stream::iter(repositories.iter())
+            .map(|(o, n)| async move { GetIssuesCommand::new(&self.gh, o, n, print_all).execute().await })
+            .buffered(jobs)
+            .collect::<Vec<Result<GetIssuesCommandResult>>>();
+        let results = match block_on(fetch_all) {
+            Ok(results) => results,
+            Err(e) => return println!("Could not start the async runtime: {}", e),
+        };
+
+        for ((o, n), res) in repositories.iter().zip(results) {
+            match res {
+                Ok(res) => res.to_std_out(),
+                Err(e) => println!("Could not fetch issues of {}/{}: {}", o, n, e),
+            }
+        }
     }
 }
 
@@ -215,42 +256,3 @@ impl<'a> PrintStd for Issues<'a> {
         table.printstd();
     }
 }
-
-pub struct FetchOpenIssuesCmd<'a> {
-    gh: &'a Github,
-    owner: &'a str,
-    name: &'a str,
-}
-
-impl<'a> FetchOpenIssuesCmd<'a> {
-    fn new(gh: &'a Github, owner: &'a str, name: &'a str) -> Self {
-        Self {
-            gh: gh,
-            owner: owner,
-            name: name,
-        }
-    }
-}
-
-impl<'a> IterableCommand<Vec<Issue>> for FetchOpenIssuesCmd<'a> {
-    fn execute_iter(&self) -> ResultIterator<Vec<Issue>> {
-        fn call<'a>(
-            gh: &'a Github,
-            owner: &'a str,
-            name: &'a str,
-        ) -> Box<dyn Fn(&str) -> Result<HttpResponse<Vec<Issue>>> + 'a> {
-            Box::new(move |page| {
-                gh.get()
-                    .repos()
-                    .owner(owner)
-                    .repo(name)
-                    .issues()
-                    .page(page)
-                    .execute::<Vec<Issue>>()
-            })
-        }
-
-        let t = call(self.gh, self.owner, self.name);
-        ResultIterator::new(t, Some("1".to_string()))
-    }
-}
@@ -1,4 +1,5 @@
-use client::client::{Executor, Github};
+use client::client::Github;
+use client::pagination::Paginate;
 use config::context::BardoContext;
 
 pub struct GetLabelsCommand {
@@ -15,16 +16,20 @@ impl GetLabelsCommand {
     }
 
     pub fn run(&self) {
-        let res = self
+        let labels = self
             .gh
             .get()
             .repos()
             .owner("crvshlab")
             .repo("ciot-backoffice")
             .labels()
-            .execute::<toml::Value>()
-            .unwrap();
+            .paginate::<serde_json::Value>();
 
-        println!("{:#?}", res);
+        for label in labels {
+            match label {
+                Ok(l) => println!("{:#?}", l),
+                Err(e) => println!("Could not fetch labels: {}", e),
+            }
+        }
     }
 }
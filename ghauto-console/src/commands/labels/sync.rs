@@ -0,0 +1,189 @@
+use crate::cmd::CommandExecutor;
+use client::client::{Executor, Github, Result};
+use client::pagination::Paginate;
+use config::context::BardoContext;
+
+use std::collections::HashMap;
+
+/// A label as written to a `labels_file`:
+///
+/// ```toml
+/// [[labels]]
+/// name = "bug"
+/// color = "d73a4a"
+/// description = "Something isn't working"
+/// ```
+#[derive(Deserialize, Debug, Clone)]
+pub struct Label {
+    name: String,
+    color: String,
+    #[serde(default)]
+    description: Option<String>,
+}
+
+impl Label {
+    /// The color as GitHub reports it, without `#` and in lower case
+    fn color(&self) -> String {
+        self.color.trim_start_matches('#').to_lowercase()
+    }
+
+    fn body(&self) -> serde_json::Value {
+        serde_json::json!({"name": self.name, "color": self.color(), "description": self.description.clone().unwrap_or_default()})
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct LabelsFile {
+    #[serde(default)]
+    labels: Vec<Label>,
+}
+
+/// Read the labels of a `labels_file`
+pub fn read_labels(path: &str) -> std::result::Result<Vec<Label>, String> {
+    let toml = config::file::read_toml(path).map_err(|e| format!("could not read {}: {}", path, e))?;
+    toml.try_into::<LabelsFile>()
+        .map(|f| f.labels)
+        .map_err(|e| format!("{} is no labels file: {}", path, e))
+}
+
+#[derive(Deserialize, Debug)]
+struct ExistingLabel {
+    name: String,
+    color: String,
+    description: Option<String>,
+}
+
+/// How many labels of a repository were created and updated
+#[derive(Default)]
+struct Synced {
+    created: usize,
+    updated: usize,
+}
+
+/// Creates the labels of the file that a repository lacks and updates the
+/// color and description of the ones that differ. Labels the file does not
+/// name are left alone.
+struct SyncLabelsCommand<'a> {
+    gh: &'a Github,
+    org: &'a str,
+    name: &'a str,
+    labels: &'a [Label],
+}
+
+impl<'a> SyncLabelsCommand<'a> {
+    fn execute(&self) -> Result<Synced> {
+        // label names are case insensitive
+        let existing: HashMap<String, ExistingLabel> = self
+            .gh
+            .get()
+            .repos()
+            .owner(self.org)
+            .repo(self.name)
+            .labels()
+            .paginate::<ExistingLabel>()
+            .map(|l| l.map(|l| (l.name.to_lowercase(), l)))
+            .collect::<Result<_>>()?;
+
+        let mut synced = Synced::default();
+        for label in self.labels {
+            match existing.get(&label.name.to_lowercase()) {
+                None => {
+                    self.gh
+                        .post(label.body())
+                        .repos()
+                        .owner(self.org)
+                        .repo(self.name)
+                        .labels()
+                        .execute::<serde_json::Value>()?;
+                    synced.created += 1;
+                }
+                Some(e)
+                    if e.name != label.name
+                        || e.color.to_lowercase() != label.color()
+                        || e.description.as_deref().unwrap_or("") != label.description.as_deref().unwrap_or("") =>
+                {
+                    // `name` addresses the label in the path, `new_name` renames it
+                    let mut body = label.body();
+                    if let Some(name) = body.as_object_mut().and_then(|b| b.remove("name")) {
+                        body["new_name"] = name;
+                    }
+                    self.gh
+                        .patch(body)
+                        .repos()
+                        .owner(self.org)
+                        .repo(self.name)
+                        .labels()
+                        .labelname(&e.name)
+                        .execute::<serde_json::Value>()?;
+                    synced.updated += 1;
+                }
+                Some(_) => (),
+            }
+        }
+        Ok(synced)
+    }
+}
+
+/// Syncs the labels of every repository with the `labels_file` of the
+/// repository, or of the profile if the entry sets none
+pub struct SyncLabelsCommandExecutor {
+    gh: Github,
+    context: BardoContext,
+}
+
+impl SyncLabelsCommandExecutor {
+    pub fn new(gh: Github, context: BardoContext) -> Self {
+        Self {
+            gh: gh,
+            context: context,
+        }
+    }
+}
+
+impl CommandExecutor for SyncLabelsCommandExecutor {
+    fn required_scopes(&self) -> &'static [&'static str] {
+        &["repo"]
+    }
+
+    fn execute(&self, args: &Vec<Vec<&str>>) {
+        let profile = self.context.profile();
+        let section = match self.context.section() {
+            Some(section) => section,
+            None => return println!("The config file has no profile '{}'", profile),
+        };
+        let jobs = crate::utils::pick_jobs(args, section);
+        let repositories = crate::utils::pick_repositories(&self.gh, profile, section, args);
+
+        // every file is read once, however many repositories share it
+        let mut files: HashMap<String, std::result::Result<Vec<Label>, String>> = HashMap::new();
+        let mut work = Vec::new();
+        for (o, n) in repositories.iter() {
+            match crate::utils::repository_settings(section, o, n).labels_file() {
+                Some(path) => {
+                    files.entry(path.to_string()).or_insert_with(|| read_labels(path));
+                    work.push((o, n, path.to_string()));
+                }
+                None => println!("{}/{} has no labels_file, skipped", o, n),
+            }
+        }
+
+        let results = crate::utils::map_parallel(&work, jobs, |(o, n, path)| match &files[path] {
+            Ok(labels) => SyncLabelsCommand {
+                gh: &self.gh,
+                org: o,
+                name: n,
+                labels: labels,
+            }
+            .execute()
+            .map_err(|e| e.to_string()),
+            Err(e) => Err(e.clone()),
+        });
+
+        for ((o, n, _), res) in work.iter().zip(results) {
+            match res {
+                Ok(synced) => println!("{}/{}: {} created, {} updated", o, n, synced.created, synced.updated),
+                Err(e) => println!("Could not sync the labels of {}/{}: {}", o, n, e),
+            }
+        }
+    }
+}
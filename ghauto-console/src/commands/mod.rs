@@ -1,5 +1,9 @@
+pub mod auth;
+pub mod cache;
+pub mod config;
 pub mod users;
 pub mod labels;
 pub mod issues;
 pub mod pulls;
 pub mod repo;
+pub mod status;
@@ -1,11 +1,16 @@
-use crate::cmd::{Command, IterableCommand, HttpResponse, ResultIterator, PrintStd};
+use crate::cmd::{Command, PrintStd};
 use crate::commands::repo::get::{GetRepoCmd, Repository};
-use client::client::{Executor, Github, Result};
+use client::client::{Github, Result};
+use client::pagination::{Paginate, Paginator};
+use client::params::State;
 use config::context::BardoContext;
 
 use prettytable::{format, Table};
 use termion::{color, style};
 
+/// The number of pull requests GitHub returns per page by default
+const PAGE_SIZE: usize = 30;
+
 #[derive(Deserialize, Debug)]
 pub struct Head {
     label: String,
@@ -49,31 +54,35 @@ impl GetPullsCommand {
             }
         }
         if print_single_repo {
-            self.get_pulls(org, name, print_all);
+            self.print_pulls(org, name, print_all);
         } else {
             let profile = self.context.profile();
-            let repositories = self.context.config().get_profiles()[profile].repositories();
-            for r in repositories.iter() {
-                match (r.org(), r.name()) {
-                    (o, Some(n)) => self.get_pulls(&o.0, &n.0, print_all),
-                    (_, _) => (),
-                };
+            let section = match self.context.section() {
+                Some(section) => section,
+                None => return println!("The config file has no profile '{}'", profile),
+            };
+            for (o, n) in crate::utils::pick_repositories(&self.gh, profile, section, args) {
+                self.print_pulls(&o, &n, print_all);
             }
         }
     }
 
-    fn get_pulls(&self, org: &str, name: &str, b_print_all: bool) {
+    fn print_pulls(&self, org: &str, name: &str, b_print_all: bool) {
+        if let Err(e) = self.get_pulls(org, name, b_print_all) {
+            println!("Could not fetch pull requests of {}/{}: {}", org, name, e);
+        }
+    }
+
+    fn get_pulls(&self, org: &str, name: &str, b_print_all: bool) -> Result<()> {
         let cmd: FetchOpenPullsCmd = FetchOpenPullsCmd::new(&self.gh, org, name);
-        let (_, _, repo_res) = GetRepoCmd(&self.gh, org, name).execute().unwrap();
+        let (_, _, repo_res) = GetRepoCmd(&self.gh, org, name).execute()?;
         let repo: Repository = repo_res.unwrap();
         let full_name = repo.full_name();
-        let mut pulls_mut: Vec<Pull>;
-
-        let mut iter = cmd.execute_iter().into_iter();
+        let pulls_mut: Vec<Pull>;
 
         if b_print_all == false {
-            let (_, _, res) = iter.next().unwrap().unwrap();
-            pulls_mut = res.unwrap();
+            // the first page only
+            pulls_mut = cmd.execute(PAGE_SIZE as u32).take(PAGE_SIZE).collect::<Result<Vec<Pull>>>()?;
             let num_fetched_pulls = pulls_mut.len();
             if num_fetched_pulls > 0 {
                 println!(
@@ -85,11 +94,7 @@ impl GetPullsCommand {
                 println!("There are no open pull requests in {}", full_name);
             }
         } else {
-            pulls_mut = Vec::new();
-            for next in iter {
-                let (_, _, res) = next.unwrap();
-                pulls_mut.append(res.unwrap().as_mut());
-            }
+            pulls_mut = cmd.execute(100).collect::<Result<Vec<Pull>>>()?;
 
             println!(
                 "Showing {} open pull requests in {}",
@@ -100,6 +105,7 @@ impl GetPullsCommand {
         }
 
         pulls_mut.to_std_out();
+        Ok(())
     }
 }
 
@@ -165,25 +171,16 @@ impl<'a> FetchOpenPullsCmd<'a> {
     }
 }
 
-impl<'a> IterableCommand<Vec<Pull>> for FetchOpenPullsCmd<'a> {
-    fn execute_iter(&self) -> ResultIterator<Vec<Pull>> {
-        fn call<'a>(
-            gh: &'a Github,
-            owner: &'a str,
-            name: &'a str,
-        ) -> Box<dyn Fn(&str) -> Result<HttpResponse<Vec<Pull>>> + 'a> {
-            Box::new(move |page| {
-                gh.get()
-                    .repos()
-                    .owner(owner)
-                    .repo(name)
-                    .pulls()
-                    .page(page)
-                    .execute::<Vec<Pull>>()
-            })
-        }
-
-        let t = call(self.gh, self.owner, self.name);
-        ResultIterator::new(t, Some("1".to_string()))
+impl<'a> FetchOpenPullsCmd<'a> {
+    fn execute(&self, per_page: u32) -> Paginator<'a, Pull> {
+        self.gh
+            .get()
+            .repos()
+            .owner(self.owner)
+            .repo(self.name)
+            .pulls()
+            .state(State::Open)
+            .per_page(per_page)
+            .paginate::<Pull>()
     }
 }
@@ -1,6 +1,6 @@
 use crate::cmd::CommandExecutor;
 use crate::cmd::{Command, HttpResponse};
-use crate::commands::repo::get::{GetLatestCommitCmd, Sha};
+use crate::commands::repo::get::{GetLatestCommitCmd, GetRepoCmd, Sha};
 use crate::commands::repo::post::{
     AddAssigneesToPrCommand, AddReviewersToPrCommand, CreatePrCommand, CreatePrResponse,
 };
@@ -17,6 +17,7 @@ pub struct ApplyCommand<'a> {
     branch: &'a str,
     message: &'a str,
     comment: &'a str,
+    default_branch: Option<&'a str>,
     assignees: &'a Option<Vec<&'a str>>,
     reviewers: &'a Option<Vec<&'a str>>,
     team_reviewers: &'a Option<Vec<&'a str>>,
@@ -32,6 +33,7 @@ impl<'a> ApplyCommand<'a> {
         branch: &'a str,
         message: &'a str,
         comment: &'a str,
+        default_branch: Option<&'a str>,
         assignees: &'a Option<Vec<&'a str>>,
         reviewers: &'a Option<Vec<&'a str>>,
         team_reviewers: &'a Option<Vec<&'a str>>,
@@ -45,6 +47,7 @@ impl<'a> ApplyCommand<'a> {
             branch: branch,
             message: message,
             comment: comment,
+            default_branch: default_branch,
             assignees: assignees,
             reviewers: reviewers,
             team_reviewers: team_reviewers,
@@ -52,9 +55,12 @@ impl<'a> ApplyCommand<'a> {
     }
 
     fn update_file_in_branch(&self, file: &str) -> Result<HttpResponse<serde_json::Value>> {
-        let (_, _, maybe_file_sha) = GetFileCommand(&self.gh, &self.org, &self.name, file)
-            .execute()
-            .unwrap();
+        // A file that does not exist yet on GitHub is created without a sha
+        let maybe_file_sha = match GetFileCommand(&self.gh, &self.org, &self.name, file).execute() {
+            Ok((_, _, maybe_file_sha)) => maybe_file_sha,
+            Err(e) if e.is_not_found() => None,
+            Err(e) => return Err(e),
+        };
         let file_path = [&self.path, "/", file].concat();
         let file_content = crate::config::file::read(file_path).unwrap();
         let file_content_base64 = base64::encode(file_content);
@@ -68,8 +74,19 @@ impl<'a> ApplyCommand<'a> {
             .execute()
     }
 
-    fn get_latest_commit(&self) -> Result<HttpResponse<Sha>> {
-        GetLatestCommitCmd(&self.gh, &self.org, &self.name, "heads/master").execute()
+    /// The configured default branch or, if there is none, the one set on
+    /// GitHub
+    fn base_branch(&self) -> Result<String> {
+        if let Some(branch) = self.default_branch {
+            return Ok(branch.to_string());
+        }
+        let (_, _, repo) = GetRepoCmd(self.gh, self.org, self.name).execute()?;
+        Ok(repo.map(|r| r.default_branch().clone()).unwrap_or_else(|| "master".to_string()))
+    }
+
+    fn get_latest_commit(&self, base: &str) -> Result<HttpResponse<Sha>> {
+        let reference = format!("heads/{}", base);
+        GetLatestCommitCmd(&self.gh, &self.org, &self.name, &reference).execute()
     }
 
     fn create_branch(&self, sha: &str) -> Result<HttpResponse<CreateBranchResponse>> {
@@ -78,47 +95,44 @@ impl<'a> ApplyCommand<'a> {
         CreateBranchCmd(self.gh, self.org, self.name, &body).execute()
     }
 
-    fn create_pr(&self, head: &str) -> Result<HttpResponse<CreatePrResponse>> {
-        let base = "refs/heads/master";
+    fn create_pr(&self, head: &str, base: &str) -> Result<HttpResponse<CreatePrResponse>> {
+        let base = format!("refs/heads/{}", base);
         let body = serde_json::json!({"head": head, "base": base, "title": self.branch, "body": self.comment});
         CreatePrCommand(&self.gh, &self.org, &self.name, &body).execute()
     }
 
     fn add_assignees_to_pr(&self, number: &i32) {
-        if let Some(assignees) = self.assignees {
+        // an empty list, e.g. `assignees = []` of a repository, adds none
+        if let Some(assignees) = self.assignees.as_ref().filter(|a| !a.is_empty()) {
             let body = serde_json::json!({ "assignees": assignees });
 
-            if let Ok((_, status_code, Some(res))) =
+            if let Err(e) =
                 AddAssigneesToPrCommand(&self.gh, &self.org, &self.name, number, &body).execute()
             {
-                if !status_code.is_success() {
-                    let err_message: &str = &res.get("message").unwrap().as_str().unwrap();
-                    println!("Could not add assignees to PR #{}: {}", number, err_message);
-                }
+                println!("Could not add assignees to PR #{}: {}", number, e);
             }
         }
     }
 
     fn add_reviewers_to_pr(&self, number: &i32) {
-        let maybe_body = match Some((self.reviewers, self.team_reviewers)) {
+        let reviewers = self.reviewers.as_ref().filter(|r| !r.is_empty());
+        let team_reviewers = self.team_reviewers.as_ref().filter(|r| !r.is_empty());
+        let maybe_body = match Some((reviewers, team_reviewers)) {
             Some((Some(r), Some(tr))) => Some(
-                serde_json::json!({"reviewers": self.reviewers, "team_reviewers": self.team_reviewers}),
+                serde_json::json!({"reviewers": reviewers, "team_reviewers": team_reviewers}),
             ),
-            Some((Some(r), None)) => Some(serde_json::json!({"reviewers": self.reviewers})),
+            Some((Some(r), None)) => Some(serde_json::json!({"reviewers": reviewers})),
             Some((None, Some(tr))) => {
-                Some(serde_json::json!({"team_reviewers": self.team_reviewers}))
+                Some(serde_json::json!({"team_reviewers": team_reviewers}))
             }
             Some((None, None)) | None => None,
         };
 
         if let Some(body) = maybe_body {
-            if let Ok((_, status_code, Some(res))) =
+            if let Err(e) =
                 AddReviewersToPrCommand(&self.gh, &self.org, &self.name, number, &body).execute()
             {
-                if !status_code.is_success() {
-                    let err_message: &str = &res.get("message").unwrap().as_str().unwrap();
-                    println!("Could not add reviewers to PR #{}: {}", number, err_message);
-                }
+                println!("Could not add reviewers to PR #{}: {}", number, e);
             }
         }
     }
@@ -143,9 +157,10 @@ impl<'a> Command<()> for ApplyCommand<'a> {
         if status.success() {
             let files = ListChangedFilesCommand(self.path).execute().unwrap();
             if !files.is_empty() {
-                let (_, _, maybe_commit_sha) = self.get_latest_commit().unwrap();
+                let base = self.base_branch()?;
+                let (_, _, maybe_commit_sha) = self.get_latest_commit(&base)?;
                 let (_, _, maybe_branch) =
-                    self.create_branch(maybe_commit_sha.unwrap().sha()).unwrap();
+                    self.create_branch(maybe_commit_sha.unwrap().sha())?;
 
                 for file in files {
                     match self.update_file_in_branch(&file) {
@@ -157,7 +172,7 @@ impl<'a> Command<()> for ApplyCommand<'a> {
                 }
 
                 let branch_response = maybe_branch.unwrap();
-                let (_, _, maybe_pr) = self.create_pr(branch_response.reference()).unwrap();
+                let (_, _, maybe_pr) = self.create_pr(branch_response.reference(), &base)?;
                 let pr = maybe_pr.unwrap();
                 let pr_number = pr.number();
                 self.add_reviewers_to_pr(pr_number);
@@ -237,11 +252,16 @@ impl ApplyCommandExecutor {
 }
 
 impl<'a> CommandExecutor for ApplyCommandExecutor {
+    fn required_scopes(&self) -> &'static [&'static str] {
+        &["repo"]
+    }
+
     fn execute(&self, args: &Vec<Vec<&str>>) {
-        let maybe_repo = crate::utils::pick_repo(args);
         let profile = self.context.profile();
-        let section = &self.context.config().get_profiles()[profile];
-        let repositories = section.repositories();
+        let section = match self.context.section() {
+            Some(section) => section,
+            None => return println!("The config file has no profile '{}'", profile),
+        };
         let path = &section.clone_path().0;
         let cmd = crate::utils::pick_command(args).unwrap();
         let branch = crate::utils::pick_branch(args).unwrap();
@@ -251,30 +271,43 @@ impl<'a> CommandExecutor for ApplyCommandExecutor {
         let reviewers = crate::utils::pick_reviewers(args);
         let team_reviewers = crate::utils::pick_team_reviewers(args);
 
+        let jobs = crate::utils::pick_jobs(args, section);
+
         let temp_clone_path = format!("{}/.temp", path);
-        repositories
-            .iter()
-            .filter(|r| crate::utils::maybe_filter_repo(r, &maybe_repo))
-            .for_each(|repo| match (repo.org(), repo.name()) {
-                (o, Some(n)) => {
-                    // let _ = crate::commands::repo::clone::CloneRepoCommand::new(&temp_clone_path, &o.0, &n.0).execute();
-                    let project_path = [&temp_clone_path, "/", &n.0].concat();
-                    ApplyCommand::new(
-                        &self.gh,
-                        &project_path,
-                        &o.0,
-                        &n.0,
-                        cmd,
-                        branch,
-                        message,
-                        comment,
-                        &assignees,
-                        &reviewers,
-                        &team_reviewers,
-                    )
-                    .execute();
-                }
-                (_, _) => (),
-            });
+        let repositories = crate::utils::pick_repositories(&self.gh, profile, section, args);
+
+        crate::utils::map_parallel(&repositories, jobs, |(o, n)| {
+            // let _ = crate::commands::repo::clone::CloneRepoCommand::new(&temp_clone_path, o, n).execute();
+            let project_path = [&temp_clone_path, "/", n].concat();
+            // the options take precedence over the settings of the repository
+            let settings = crate::utils::repository_settings(section, o, n);
+            let assignees = or_setting(&assignees, settings.assignees());
+            let reviewers = or_setting(&reviewers, settings.reviewers());
+            let team_reviewers = or_setting(&team_reviewers, settings.team_reviewers());
+            let res = ApplyCommand::new(
+                &self.gh,
+                &project_path,
+                o,
+                n,
+                cmd,
+                branch,
+                message,
+                comment,
+                settings.default_branch(),
+                &assignees,
+                &reviewers,
+                &team_reviewers,
+            )
+            .execute();
+
+            if let Err(e) = res {
+                println!("Could not apply the command to {}/{}: {}", o, n, e);
+            }
+        });
     }
 }
+
+/// The values of an option or, if it is not given, of the setting
+fn or_setting<'s>(option: &Option<Vec<&'s str>>, setting: Option<&'s Vec<String>>) -> Option<Vec<&'s str>> {
+    option.clone().or_else(|| setting.map(|s| s.iter().map(String::as_str).collect()))
+}
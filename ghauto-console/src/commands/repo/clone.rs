@@ -1,17 +1,19 @@
 use crate::cmd::Command;
 use crate::cmd::CommandExecutor;
-use client::client::Result;
+use client::client::{Github, Result};
 use config::context::BardoContext;
 
 pub struct CloneRepoCommand<'a> {
+    url: &'a str,
     path: &'a str,
     org: &'a str,
     name: &'a str,
 }
 
 impl<'a> CloneRepoCommand<'a> {
-    pub fn new(path: &'a str, org: &'a str, name: &'a str) -> Self {
+    pub fn new(url: &'a str, path: &'a str, org: &'a str, name: &'a str) -> Self {
         Self {
+            url: url,
             path: path,
             org: org,
             name: name,
@@ -21,28 +23,28 @@ impl<'a> CloneRepoCommand<'a> {
 
 impl<'a> Command<std::process::ExitStatus> for CloneRepoCommand<'a> {
     fn execute(&self) -> Result<std::process::ExitStatus> {
-        let ssh_url = format!("git@github.com:{}/{}.git", self.org, self.name);
-
         let status = std::process::Command::new("sh")
             .current_dir(self.path)
             .arg("-c")
-            .arg(format!("git clone {}", ssh_url))
+            .arg(format!("git clone {}", self.url))
             .status()
             .expect("failed to execute process");
 
-        println!("process exited with: {}", status);
+        println!("cloning {}/{} exited with: {}", self.org, self.name, status);
 
         Ok(status)
     }
 }
 
 pub struct CloneRepoCommandExecutor {
+    gh: Github,
     context: BardoContext,
 }
 
 impl CloneRepoCommandExecutor {
-     pub fn new(ctx: BardoContext) -> Self {
+     pub fn new(gh: Github, ctx: BardoContext) -> Self {
         Self {
+            gh: gh,
             context: ctx,
         }
     }
@@ -50,22 +52,31 @@ impl CloneRepoCommandExecutor {
 
 impl<'a> CommandExecutor for CloneRepoCommandExecutor {
     fn execute(&self, args: &Vec<Vec<&str>>) {
-        let maybe_repo = crate::utils::pick_repo(args);
         let profile = self.context.profile();
-        let section = &self.context.config().get_profiles()[profile];
-        let repositories = section.repositories();
+        let section = match self.context.section() {
+            Some(section) => section,
+            None => return println!("The config file has no profile '{}'", profile),
+        };
         let path = &section.clone_path().0;
+        let host = match section.web_url() {
+            Ok(web_url) => web_url.host_str().unwrap_or("github.com").to_string(),
+            Err(e) => return println!("Profile '{}': invalid 'host' or 'api_url': {}", profile, e),
+        };
 
         println!("");
         println!("start cloning repos in {}", path);
         println!("");
 
-        repositories
-            .iter()
-            .filter(|r| crate::utils::maybe_filter_repo(r, &maybe_repo))
-            .for_each(|repo| match (repo.org(), repo.name()) {
-                (o, Some(n)) => {let _ = CloneRepoCommand::new(&path, &o.0, &n.0).execute();},
-                (_, _) => (),
-            });
+        let jobs = crate::utils::pick_jobs(args, section);
+        let repositories = crate::utils::pick_repositories(&self.gh, profile, section, args);
+
+        crate::utils::map_parallel(&repositories, jobs, |(o, n)| {
+            let settings = crate::utils::repository_settings(section, o, n);
+            let url = match settings.clone_url() {
+                Some(url) => url.to_string(),
+                None => format!("git@{}:{}/{}.git", host, o, n),
+            };
+            let _ = CloneRepoCommand::new(&url, &path, o, n).execute();
+        });
     }
 }
@@ -4,6 +4,7 @@ use client::client::{Github, Result,Executor};
 #[derive(Deserialize, Debug)]
 pub struct Repository {
     full_name: String,
+    default_branch: String,
     has_projects: bool,
     has_wiki: bool,
     open_issues_count: u32,
@@ -14,6 +15,10 @@ impl Repository {
         &self.full_name
     }
 
+    pub fn default_branch(&self) -> &String {
+        &self.default_branch
+    }
+
     pub fn has_projects(&self) -> &bool {
         &self.has_projects
     }
@@ -0,0 +1,135 @@
+use crate::cmd::Command;
+use client::client::{Executor, Github, Result};
+use client::pagination::Paginate;
+use client::params::Affiliation;
+use config::file::WriteOptions;
+
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+
+/// How long the listed repositories of an owner are used before they are
+/// listed again
+pub const CACHE_TTL: Duration = Duration::from_secs(10 * 60);
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct OwnerRepository {
+    name: String,
+    #[serde(default)]
+    archived: bool,
+    #[serde(default)]
+    fork: bool,
+}
+
+impl OwnerRepository {
+    pub fn name(&self) -> &String {
+        &self.name
+    }
+
+    pub fn archived(&self) -> &bool {
+        &self.archived
+    }
+
+    pub fn fork(&self) -> &bool {
+        &self.fork
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct Login {
+    login: String,
+}
+
+/// Lists all repositories of an organization or, if there is no
+/// organization of that name, the repositories of the user: all of them,
+/// the private ones included, for the user the token belongs to and the
+/// public ones for any other user
+pub struct ListOwnerReposCmd<'a>(pub &'a Github, pub &'a str);
+
+impl<'a> ListOwnerReposCmd<'a> {
+    /// Whether the owner is the user the token belongs to. A token that
+    /// cannot read `/user`, e.g. of a GitHub App, belongs to no user.
+    fn is_authenticated_user(&self) -> bool {
+        match self.0.get().user().execute::<Login>() {
+            Ok((_, _, Some(user))) => user.login.eq_ignore_ascii_case(self.1),
+            _ => false,
+        }
+    }
+}
+
+impl<'a> Command<Vec<OwnerRepository>> for ListOwnerReposCmd<'a> {
+    fn execute(&self) -> Result<Vec<OwnerRepository>> {
+        let repos = self
+            .0
+            .get()
+            .orgs()
+            .org(self.1)
+            .repos()
+            .per_page(100)
+            .paginate::<OwnerRepository>()
+            .collect();
+
+        match repos {
+            Err(ref e) if e.is_not_found() && self.is_authenticated_user() => self
+                .0
+                .get()
+                .user()
+                .repos()
+                .affiliation(Affiliation::Owner)
+                .per_page(100)
+                .paginate::<OwnerRepository>()
+                .collect(),
+            Err(ref e) if e.is_not_found() => self
+                .0
+                .get()
+                .users()
+                .username(self.1)
+                .repos()
+                .per_page(100)
+                .paginate::<OwnerRepository>()
+                .collect(),
+            repos => repos,
+        }
+    }
+}
+
+/// Keeps the listed repositories for `CACHE_TTL`, one file per owner
+pub struct OwnerReposCache {
+    dir: PathBuf,
+}
+
+impl OwnerReposCache {
+    pub fn new<P>(dir: P) -> Self
+    where
+        P: Into<PathBuf>,
+    {
+        Self { dir: dir.into() }
+    }
+
+    fn path(&self, owner: &str) -> PathBuf {
+        let file: String = owner
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() || c == '-' { c } else { '_' })
+            .collect();
+        self.dir.join(format!("{}.json", file))
+    }
+
+    /// The repositories of `owner` if they were listed within `CACHE_TTL`
+    pub fn get(&self, owner: &str) -> Option<Vec<OwnerRepository>> {
+        let path = self.path(owner);
+        let modified = fs::metadata(&path).and_then(|m| m.modified()).ok()?;
+        match SystemTime::now().duration_since(modified) {
+            Ok(age) if age < CACHE_TTL => (),
+            _ => return None,
+        }
+        fs::read(&path).ok().and_then(|bytes| serde_json::from_slice(&bytes).ok())
+    }
+
+    pub fn store(&self, owner: &str, repos: &Vec<OwnerRepository>) -> io::Result<()> {
+        let json = serde_json::to_vec(repos).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        // the names of private repositories are not for other users
+        config::file::create_private_dir(&self.dir)?;
+        WriteOptions::new().private(true).write(self.path(owner), json)
+    }
+}
@@ -3,3 +3,4 @@ pub mod post;
 pub mod put;
 pub mod clone;
 pub mod apply;
+pub mod list;
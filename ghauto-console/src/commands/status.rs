@@ -0,0 +1,131 @@
+use crate::cmd::{CommandExecutor, PrintStd};
+use client::client::{Github, Result};
+use config::context::BardoContext;
+
+use prettytable::{format, Table};
+use std::collections::HashMap;
+use termion::{color, style};
+
+/// How many repositories are selected by a single GraphQL query
+const REPOS_PER_QUERY: usize = 50;
+
+#[derive(Deserialize, Debug)]
+struct Count {
+    #[serde(rename = "totalCount")]
+    total_count: u32,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct RepositoryStatus {
+    #[serde(rename = "nameWithOwner")]
+    name_with_owner: String,
+    issues: Count,
+    #[serde(rename = "pullRequests")]
+    pull_requests: Count,
+}
+
+/// Fetches the number of open issues and pull requests of many repositories
+/// with a single GraphQL query, every repository selected under an alias.
+pub struct GetStatusCmd<'a>(pub &'a Github, pub &'a [(&'a str, &'a str)]);
+
+impl<'a> GetStatusCmd<'a> {
+    fn query(&self) -> String {
+        let vars: Vec<String> = (0..self.1.len())
+            .map(|i| format!("$o{i}: String!, $n{i}: String!", i = i))
+            .collect();
+        let fields: Vec<String> = (0..self.1.len())
+            .map(|i| {
+                format!(
+                    "r{i}: repository(owner: $o{i}, name: $n{i}) {{ nameWithOwner issues(states: OPEN) {{ totalCount }} pullRequests(states: OPEN) {{ totalCount }} }}",
+                    i = i
+                )
+            })
+            .collect();
+        format!("query({}) {{ {} }}", vars.join(", "), fields.join(" "))
+    }
+
+    /// The status of each repository, `None` for a repository that does not
+    /// exist or is not visible. Other errors of single repositories are
+    /// printed, they do not fail the whole query.
+    pub fn execute(&self) -> Result<Vec<Option<RepositoryStatus>>> {
+        let mut query = self.0.graphql(self.query());
+        for (i, (owner, name)) in self.1.iter().enumerate() {
+            query = query.variable(&format!("o{}", i), owner).variable(&format!("n{}", i), name);
+        }
+
+        let (mut data, errors): (HashMap<String, Option<RepositoryStatus>>, _) = query.execute_partial()?;
+        for error in errors.iter().filter(|e| e.kind.as_deref() != Some("NOT_FOUND")) {
+            println!("{}", error);
+        }
+        Ok((0..self.1.len()).map(|i| data.remove(&format!("r{}", i)).and_then(|r| r)).collect())
+    }
+}
+
+pub struct StatusCommandExecutor {
+    gh: Github,
+    context: BardoContext,
+}
+
+impl StatusCommandExecutor {
+    pub fn new(gh: Github, context: BardoContext) -> Self {
+        Self {
+            gh: gh,
+            context: context,
+        }
+    }
+}
+
+impl CommandExecutor for StatusCommandExecutor {
+    fn required_scopes(&self) -> &'static [&'static str] {
+        &["repo"]
+    }
+
+    fn execute(&self, args: &Vec<Vec<&str>>) {
+        let profile = self.context.profile();
+        let section = match self.context.section() {
+            Some(section) => section,
+            None => return println!("The config file has no profile '{}'", profile),
+        };
+        let repositories = crate::utils::pick_repositories(&self.gh, profile, section, args);
+        let repositories: Vec<(&str, &str)> = repositories.iter().map(|(o, n)| (&o[..], &n[..])).collect();
+
+        let mut statuses = Vec::with_capacity(repositories.len());
+        for chunk in repositories.chunks(REPOS_PER_QUERY) {
+            match GetStatusCmd(&self.gh, chunk).execute() {
+                Ok(results) => {
+                    for ((o, n), status) in chunk.iter().zip(results) {
+                        match status {
+                            Some(status) => statuses.push(status),
+                            None => println!("Could not find {}/{}", o, n),
+                        }
+                    }
+                }
+                Err(e) => println!("Could not fetch the status of the repositories: {}", e),
+            }
+        }
+        statuses.to_std_out();
+    }
+}
+
+impl PrintStd for Vec<RepositoryStatus> {
+    fn to_std_out(&self) {
+        let mut table = Table::new();
+        let format = format::FormatBuilder::new().padding(1, 1).build();
+        table.set_format(format);
+        table.set_titles(row![
+            format!("{}{}repository{}", style::Bold, color::Fg(color::Magenta), style::Reset),
+            format!("{}{}open issues{}", style::Bold, color::Fg(color::Green), style::Reset),
+            format!("{}{}open pull requests{}", style::Bold, color::Fg(color::Green), style::Reset),
+        ]);
+
+        for s in self.iter() {
+            table.add_row(row![
+                format!("{}{}{}", color::Fg(color::Magenta), s.name_with_owner, style::Reset),
+                format!("{}{}{}", color::Fg(color::White), s.issues.total_count, style::Reset),
+                format!("{}{}{}", color::Fg(color::White), s.pull_requests.total_count, style::Reset),
+            ]);
+        }
+        println!("");
+        table.printstd();
+    }
+}
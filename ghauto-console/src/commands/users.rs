@@ -24,14 +24,19 @@ impl Command {
     }
 
     pub fn run(&self) {
-        let (_, _, res) = self
+        let res = self
             .gh
             .get()
             .user()
             .emails()
-            .execute::<Vec<Email>>()
-            .unwrap();
-        let emails: Vec<Email> = res.unwrap();
+            .execute::<Vec<Email>>();
+        let emails: Vec<Email> = match res {
+            Ok((_, _, emails)) => emails.unwrap_or_default(),
+            Err(e) => {
+                println!("Could not fetch emails: {}", e);
+                return;
+            }
+        };
         let mut table = Table::new();
         let format = format::FormatBuilder::new()
             .padding(1, 1)
@@ -1,3 +1,11 @@
+use crate::cmd::Command;
+use crate::commands::repo::list::{ListOwnerReposCmd, OwnerReposCache, OwnerRepository};
+use client::client::Github;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
 pub fn pick_repo<'a>(args: &'a Vec<Vec<&'a str>>) -> Option<(&'a str, &'a str)> {
     for v in args {
         if v[0] == "REPO" {
@@ -11,16 +19,234 @@ pub fn pick_repo<'a>(args: &'a Vec<Vec<&'a str>>) -> Option<(&'a str, &'a str)>
     None
 }
 
-pub fn maybe_filter_repo<'a>(
-    repo: &'a config::config::Repository,
-    arg: &'a Option<(&str, &str)>,
+/// The repositories picked by `--repo`, `--group`, `--tag` and `--exclude`
+pub struct RepoFilter<'a> {
+    repo: Option<(&'a str, &'a str)>,
+    groups: Vec<&'a str>,
+    tags: Vec<&'a str>,
+    exclude: Vec<&'a str>,
+}
+
+pub fn pick_filter<'a>(args: &'a Vec<Vec<&'a str>>) -> RepoFilter<'a> {
+    let list = |key: &str| -> Vec<&'a str> {
+        args.iter()
+            .filter(|v| v[0] == key)
+            .flat_map(|v| v[1].split(',').map(str::trim).filter(|s| !s.is_empty()))
+            .collect()
+    };
+
+    RepoFilter {
+        repo: pick_repo(args),
+        groups: list("GROUP"),
+        tags: list("TAG"),
+        exclude: list("EXCLUDE"),
+    }
+}
+
+/// Whether the repository `name` of the entry `repo` is picked. It has to
+/// be the `--repo`, a member of one of the `--group`s and carry one of the
+/// `--tag`s, each if given, and must not be `--exclude`d by `org/name` or
+/// by name.
+pub fn maybe_filter_repo(
+    section: &config::config::Configuration,
+    repo: &config::config::Repository,
+    name: &str,
+    filter: &RepoFilter,
 ) -> bool {
-    match Some((arg, repo.org(), repo.name())) {
-        Some((Some((org, name)), r_org, Some(r_name))) => {
-            r_org.0 == org.to_string() && r_name.0 == name.to_string()
+    let org = repo.org().0.as_str();
+    let in_group = |group: &&str| section.groups().get(*group).is_some_and(|g| g.contains(org, name));
+
+    filter.repo.is_none_or(|(o, n)| o == org && n == name)
+        && (filter.groups.is_empty() || filter.groups.iter().any(in_group))
+        && (filter.tags.is_empty() || filter.tags.iter().any(|t| repo.has_tag(t)))
+        && !filter.exclude.iter().any(|e| *e == name || e.split_once('/') == Some((org, name)))
+}
+
+/// The repositories a command runs on. Entries with a `name` are taken as
+/// they are, `regex` entries are expanded to the repositories of their
+/// owner whose whole name matches. Archived repositories and forks are skipped
+/// unless `--include-archived` or `--include-forks` is given. The listed
+/// repositories are cached for a few minutes, `--no-cache` lists them again.
+/// `--repo`, `--group`, `--tag` and `--exclude` select a subset, see
+/// `maybe_filter_repo`.
+pub fn pick_repositories<'a>(
+    gh: &Github,
+    profile: &str,
+    section: &config::config::Configuration,
+    args: &'a Vec<Vec<&'a str>>,
+) -> Vec<(String, String)> {
+    let cache = config::file::cache_dir().map(|dir| OwnerReposCache::new(dir.join("repos").join(profile)));
+    let refresh = no_cache(args);
+    select_repositories(profile, section, args, |owner| {
+        list_owner_repos(gh, owner, cache.as_ref(), refresh)
+    })
+}
+
+/// `pick_repositories` with the repositories of an owner taken from `list`
+fn select_repositories<'a, L>(
+    profile: &str,
+    section: &config::config::Configuration,
+    args: &'a Vec<Vec<&'a str>>,
+    mut list: L,
+) -> Vec<(String, String)>
+where
+    L: FnMut(&str) -> Option<Vec<OwnerRepository>>,
+{
+    let filter = pick_filter(args);
+    if let Some(group) = filter.groups.iter().find(|g| !section.groups().contains_key(**g)) {
+        println!("profile '{}' has no group '{}'", profile, group);
+        return Vec::new();
+    }
+    let include_archived = include_archived(args);
+    let include_forks = include_forks(args);
+
+    let mut listed: HashMap<String, Option<Vec<OwnerRepository>>> = HashMap::new();
+    let mut repositories = Vec::new();
+    for r in section.repositories().iter() {
+        let org = &r.org().0;
+        let regex = match (r.name(), r.regex()) {
+            (Some(name), _) => {
+                if maybe_filter_repo(section, r, &name.0, &filter) {
+                    repositories.push((org.clone(), name.0.clone()));
+                }
+                continue;
+            }
+            (None, Some(regex)) => regex,
+            (None, None) => continue,
+        };
+        if filter.repo.is_some_and(|(o, _)| o != org) {
+            continue;
+        }
+        let pattern = match name_pattern(regex) {
+            Ok(pattern) => pattern,
+            Err(e) => {
+                println!("ignoring the invalid regex '{}' of {}: {}", regex.0, org, e);
+                continue;
+            }
+        };
+
+        let owned = listed
+            .entry(org.clone())
+            .or_insert_with(|| list(org));
+        for repo in owned.iter().flatten() {
+            if !pattern.is_match(repo.name())
+                || (*repo.archived() && !include_archived)
+                || (*repo.fork() && !include_forks)
+                || !maybe_filter_repo(section, r, repo.name(), &filter)
+            {
+                continue;
+            }
+            repositories.push((org.clone(), repo.name().clone()));
+        }
+    }
+
+    // a repository can be listed by name and match a regex as well
+    let mut seen = HashSet::new();
+    repositories.retain(|r| seen.insert(r.clone()));
+    repositories
+}
+
+/// The regex of an entry, matching whole repository names only: `nodejs-.*`
+/// matches `nodejs-api` but not `legacy-nodejs-api`
+fn name_pattern(regex: &config::config::Regex) -> Result<regex::Regex, regex::Error> {
+    regex::Regex::new(&format!("^(?:{})$", regex.0))
+}
+
+fn list_owner_repos(gh: &Github, owner: &str, cache: Option<&OwnerReposCache>, refresh: bool) -> Option<Vec<OwnerRepository>> {
+    if let Some(repos) = cache.filter(|_| !refresh).and_then(|c| c.get(owner)) {
+        return Some(repos);
+    }
+
+    match ListOwnerReposCmd(gh, owner).execute() {
+        Ok(repos) => {
+            if let Some(cache) = cache {
+                let _ = cache.store(owner, &repos);
+            }
+            Some(repos)
+        }
+        Err(e) => {
+            println!("Could not list the repositories of {}: {}", owner, e);
+            None
+        }
+    }
+}
+
+/// The settings of the repository `org/name`, taken from the first entry
+/// naming or matching it, with the defaults of the profile for the ones
+/// the entry does not set
+pub fn repository_settings(
+    section: &config::config::Configuration,
+    org: &str,
+    name: &str,
+) -> config::config::RepositorySettings {
+    let entry = section
+        .repositories()
+        .iter()
+        .filter(|r| r.org().0 == org)
+        .find(|r| match (r.name(), r.regex()) {
+            (Some(n), _) => n.0 == name,
+            (None, Some(regex)) => name_pattern(regex).is_ok_and(|re| re.is_match(name)),
+            (None, None) => false,
+        });
+
+    match entry {
+        Some(repository) => section.settings_of(repository),
+        None => section.repository_defaults(),
+    }
+}
+
+/// The number of repositories processed at the same time if neither
+/// `--jobs` nor the profile sets it
+pub const DEFAULT_JOBS: usize = 4;
+
+/// The number of repositories processed at the same time, `--jobs` takes
+/// precedence over the `jobs` setting of the profile
+pub fn pick_jobs<'a>(args: &'a Vec<Vec<&'a str>>, section: &config::config::Configuration) -> usize {
+    for v in args {
+        if v[0] == "JOBS" {
+            match v[1].parse::<usize>() {
+                Ok(jobs) if jobs > 0 => return jobs,
+                _ => println!("ignoring invalid value '{}' for --jobs", v[1]),
+            }
         }
-        _ => true,
     }
+
+    section.jobs().map(|j| j.0).unwrap_or(DEFAULT_JOBS)
+}
+
+/// Apply `f` to every item on at most `jobs` worker threads. The results
+/// are returned in the order of `items`, so they can be printed in the same
+/// order as the repositories are configured.
+pub fn map_parallel<T, R, F>(items: &[T], jobs: usize, f: F) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> R + Sync,
+{
+    let next = AtomicUsize::new(0);
+    let results: Mutex<Vec<Option<R>>> = Mutex::new(items.iter().map(|_| None).collect());
+
+    thread::scope(|scope| {
+        for _ in 0..jobs.max(1).min(items.len()) {
+            scope.spawn(|| loop {
+                let i = next.fetch_add(1, Ordering::SeqCst);
+                match items.get(i) {
+                    Some(item) => {
+                        let result = f(item);
+                        results.lock().unwrap()[i] = Some(result);
+                    }
+                    None => break,
+                }
+            });
+        }
+    });
+
+    results
+        .into_inner()
+        .unwrap()
+        .into_iter()
+        .map(|r| r.expect("every item is processed"))
+        .collect()
 }
 
 pub fn print_all<'a>(args: &'a Vec<Vec<&'a str>>) -> bool {
@@ -33,6 +259,66 @@ pub fn print_all<'a>(args: &'a Vec<Vec<&'a str>>) -> bool {
     false
 }
 
+pub fn include_archived<'a>(args: &'a Vec<Vec<&'a str>>) -> bool {
+    for v in args {
+        if v[0] == "INCLUDE_ARCHIVED" {
+            return true;
+        }
+    }
+
+    false
+}
+
+pub fn include_forks<'a>(args: &'a Vec<Vec<&'a str>>) -> bool {
+    for v in args {
+        if v[0] == "INCLUDE_FORKS" {
+            return true;
+        }
+    }
+
+    false
+}
+
+pub fn no_cache<'a>(args: &'a Vec<Vec<&'a str>>) -> bool {
+    for v in args {
+        if v[0] == "NO_CACHE" {
+            return true;
+        }
+    }
+
+    false
+}
+
+pub fn backup<'a>(args: &'a Vec<Vec<&'a str>>) -> bool {
+    for v in args {
+        if v[0] == "BACKUP" {
+            return true;
+        }
+    }
+
+    false
+}
+
+pub fn pick_value<'a>(args: &'a Vec<Vec<&'a str>>, key: &str) -> Option<&'a str> {
+    for v in args {
+        if v[0] == key {
+            return v.get(1).copied();
+        }
+    }
+
+    None
+}
+
+pub fn pick_older_than<'a>(args: &'a Vec<Vec<&'a str>>) -> Option<&'a str> {
+    for v in args {
+        if v[0] == "OLDER_THAN" {
+            return Some(v[1]);
+        }
+    }
+
+    None
+}
+
 pub fn pick_command<'a>(args: &'a Vec<Vec<&'a str>>) -> Option<&'a str> {
     for v in args {
         if v[0] == "CMD" {
@@ -105,3 +391,164 @@ pub fn pick_team_reviewers<'a>(args: &'a Vec<Vec<&'a str>>) -> Option<Vec<&'a st
 
     None
 }
+
+pub fn pick_port<'a>(args: &'a Vec<Vec<&'a str>>) -> Option<&'a str> {
+    for v in args {
+        if v[0] == "PORT" {
+            return Some(v[1]);
+        }
+    }
+
+    None
+}
+
+pub fn pick_client_id<'a>(args: &'a Vec<Vec<&'a str>>) -> Option<&'a str> {
+    for v in args {
+        if v[0] == "CLIENT_ID" {
+            return Some(v[1]);
+        }
+    }
+
+    None
+}
+
+pub fn use_web_flow<'a>(args: &'a Vec<Vec<&'a str>>) -> bool {
+    for v in args {
+        if v[0] == "WEB" {
+            return true;
+        }
+    }
+
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use config::config::Configuration;
+    use config::file::read_bytes;
+
+    const CONFIG: &str = r#"
+        clone_path = "/path"
+        repositories = [
+          { org = "o", name = "api", tags = ["backend"] },
+          { org = "o", name = "web", tags = ["frontend"] },
+          { org = "o", regex = "nodejs-.*", tags = ["node"] },
+          { org = "other", name = "api" },
+        ]
+
+        [groups]
+        core = ["o/api", "o/nodejs-a"]
+    "#;
+
+    fn section() -> Configuration {
+        Configuration::read_from(|| read_bytes(CONFIG.as_bytes())).expect("invalid format")
+    }
+
+    fn listing() -> Vec<OwnerRepository> {
+        serde_json::from_value(serde_json::json!([
+            {"name": "nodejs-a"},
+            {"name": "nodejs-old", "archived": true},
+            {"name": "nodejs-fork", "fork": true},
+            {"name": "legacy-nodejs-b"},
+            {"name": "api"},
+        ]))
+        .unwrap()
+    }
+
+    fn select(args: &Vec<Vec<&str>>) -> Vec<String> {
+        select_repositories("default", &section(), args, |owner| match owner {
+            "o" => Some(listing()),
+            _ => None,
+        })
+        .into_iter()
+        .map(|(o, n)| format!("{}/{}", o, n))
+        .collect()
+    }
+
+    #[test]
+    fn filter_from_args() {
+        let args = vec![
+            vec!["REPO", "o/api"],
+            vec!["GROUP", "core, extra"],
+            vec!["TAG", "backend"],
+            vec!["TAG", "node,"],
+            vec!["EXCLUDE", "o/web"],
+        ];
+        let filter = pick_filter(&args);
+        assert_eq!(Some(("o", "api")), filter.repo);
+        assert_eq!(vec!["core", "extra"], filter.groups);
+        assert_eq!(vec!["backend", "node"], filter.tags);
+        assert_eq!(vec!["o/web"], filter.exclude);
+
+        let args = Vec::new();
+        let filter = pick_filter(&args);
+        assert!(filter.repo.is_none() && filter.groups.is_empty() && filter.tags.is_empty() && filter.exclude.is_empty());
+    }
+
+    #[test]
+    fn filter_repo() {
+        let section = section();
+        let api = &section.repositories()[0];
+        let picked = |args: Vec<Vec<&str>>| maybe_filter_repo(&section, api, "api", &pick_filter(&args));
+
+        assert!(picked(vec![]));
+        assert!(picked(vec![vec!["REPO", "o/api"]]));
+        assert!(!picked(vec![vec!["REPO", "other/api"]]));
+        assert!(picked(vec![vec!["GROUP", "core"]]));
+        assert!(!picked(vec![vec!["GROUP", "unknown"]]));
+        assert!(picked(vec![vec!["TAG", "frontend,backend"]]));
+        assert!(!picked(vec![vec!["TAG", "frontend"]]));
+        assert!(!picked(vec![vec!["EXCLUDE", "o/api"]]));
+        assert!(!picked(vec![vec!["EXCLUDE", "api"]]));
+        assert!(picked(vec![vec!["EXCLUDE", "other/api"]]));
+        assert!(!picked(vec![vec!["GROUP", "core"], vec!["TAG", "frontend"]]));
+    }
+
+    #[test]
+    fn repositories_of_entries_and_regex() {
+        assert_eq!(vec!["o/api", "o/web", "o/nodejs-a", "other/api"], select(&vec![]));
+        assert_eq!(
+            vec!["o/api", "o/web", "o/nodejs-a", "o/nodejs-old", "o/nodejs-fork", "other/api"],
+            select(&vec![vec!["INCLUDE_ARCHIVED"], vec!["INCLUDE_FORKS"]])
+        );
+    }
+
+    #[test]
+    fn repositories_of_filters() {
+        assert_eq!(vec!["o/nodejs-a"], select(&vec![vec!["REPO", "o/nodejs-a"]]));
+        assert_eq!(vec!["o/api", "o/nodejs-a"], select(&vec![vec!["GROUP", "core"]]));
+        assert_eq!(vec!["o/nodejs-a"], select(&vec![vec!["TAG", "node"]]));
+        assert_eq!(vec!["o/web", "o/nodejs-a"], select(&vec![vec!["EXCLUDE", "api"]]));
+        assert!(select(&vec![vec!["GROUP", "unknown"]]).is_empty());
+    }
+
+    #[test]
+    fn owners_are_listed_once() {
+        let section = Configuration::read_from(|| {
+            read_bytes(
+                br#"
+                clone_path = "/path"
+                repositories = [
+                  { org = "o", regex = "nodejs-.*" },
+                  { org = "o", regex = "a.*" },
+                  { org = "o", name = "api" },
+                  { org = "o", regex = "[" },
+                ]
+            "#,
+            )
+        })
+        .expect("invalid format");
+
+        let mut listings = 0;
+        let repositories = select_repositories("default", &section, &vec![], |_| {
+            listings += 1;
+            Some(listing())
+        });
+        assert_eq!(1, listings);
+        assert_eq!(
+            vec![("o".to_string(), "nodejs-a".to_string()), ("o".to_string(), "api".to_string())],
+            repositories
+        );
+    }
+}